@@ -0,0 +1,519 @@
+//! Implementation of the `arch` subcommands
+//!
+//! Every command returns a value that can be printed either as human readable
+//! text or as JSON, see [crate::print].
+
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use arch_program::account::AccountMeta;
+use arch_program::instruction::Instruction;
+use arch_program::pubkey::Pubkey;
+use arch_program::system_instruction::SystemInstruction;
+use sdk::deploy::{deploy_program, DeployOptions, DeploymentReport};
+use sdk::elf::{validate_elf, ElfReport};
+use sdk::helper::{
+    get_processed_transaction, read_account_info, send_and_confirm, try_read_account_info,
+    try_send_utxo,
+};
+use sdk::idl::{generate_client, load_idl};
+use sdk::loader::ProgramInfo;
+use sdk::processed_transaction::{ProcessedTransaction, Status};
+use serde::{Deserialize, Serialize};
+
+use crate::keys::{self, Key, KeyList, KeyView};
+
+pub fn key_new(keys_dir: &Path, name: &str) -> Result<KeyView> {
+    Ok(keys::generate(keys_dir, name)?.view())
+}
+
+pub fn key_list(keys_dir: &Path) -> Result<KeyList> {
+    Ok(KeyList(
+        keys::list(keys_dir)?.iter().map(Key::view).collect(),
+    ))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FundView {
+    pub account: String,
+    pub txid: String,
+    pub vout: u32,
+}
+
+impl fmt::Display for FundView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Account          : {}", self.account)?;
+        write!(f, "UTXO             : {}:{}", self.txid, self.vout)
+    }
+}
+
+/// Sends a UTXO to the bitcoin address of `account`
+pub fn fund(url: &str, keys_dir: &Path, account: &str) -> Result<FundView> {
+    let pubkey = keys::resolve_pubkey(keys_dir, account)?;
+    let (txid, vout) = try_send_utxo(url, pubkey)?;
+
+    Ok(FundView {
+        account: format!("{:x}", pubkey),
+        txid,
+        vout,
+    })
+}
+
+fn create_account_instruction(txid: &str, vout: u32, pubkey: Pubkey) -> Result<Instruction> {
+    let txid: [u8; 32] = hex::decode(txid)?
+        .try_into()
+        .map_err(|_| anyhow!("UTXO txid should be 32 bytes"))?;
+
+    Ok(SystemInstruction::new_create_account_instruction(
+        txid, vout, pubkey,
+    ))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateAccountView {
+    pub account: String,
+    pub utxo: String,
+    pub owner: Option<String>,
+    pub txids: Vec<String>,
+}
+
+impl fmt::Display for CreateAccountView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Account          : {}", self.account)?;
+        writeln!(f, "UTXO             : {}", self.utxo)?;
+        if let Some(owner) = &self.owner {
+            writeln!(f, "Owner            : {}", owner)?;
+        }
+        write!(f, "Transactions     : {}", self.txids.join(", "))
+    }
+}
+
+/// Funds a UTXO for `key`, creates its account and optionally hands it over to `owner`
+pub fn create_account(
+    url: &str,
+    keys_dir: &Path,
+    key: &str,
+    owner: Option<&str>,
+) -> Result<CreateAccountView> {
+    let key = keys::load(keys_dir, key)?;
+    let owner = owner
+        .map(|owner| keys::resolve_pubkey(keys_dir, owner))
+        .transpose()?;
+
+    let (utxo_txid, utxo_vout) = try_send_utxo(url, key.pubkey)?;
+    let mut txids = vec![send_and_confirm(
+        url,
+        vec![create_account_instruction(
+            &utxo_txid, utxo_vout, key.pubkey,
+        )?],
+        &[key.keypair],
+    )?];
+
+    if let Some(owner) = owner {
+        txids.push(send_and_confirm(
+            url,
+            vec![SystemInstruction::new_assign_ownership_instruction(
+                key.pubkey, owner,
+            )],
+            &[key.keypair],
+        )?);
+    }
+
+    Ok(CreateAccountView {
+        account: format!("{:x}", key.pubkey),
+        utxo: format!("{}:{}", utxo_txid, utxo_vout),
+        owner: owner.map(|owner| format!("{:x}", owner)),
+        txids,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeployView {
+    pub program_id: String,
+    pub elf: ElfReport,
+    /// `None` if the same ELF was already deployed
+    pub deployment: Option<DeploymentReport>,
+    pub txids: Vec<String>,
+}
+
+impl fmt::Display for DeployView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.elf)?;
+        match &self.deployment {
+            Some(report) => writeln!(f, "{}", report)?,
+            None => writeln!(f, "Program          : {}", self.program_id)?,
+        }
+        if self.deployment.is_none() {
+            write!(f, "Same program already deployed, nothing to do")
+        } else {
+            write!(f, "Transactions     : {}", self.txids.join(", "))
+        }
+    }
+}
+
+/// Creates the program account of `key` if needed, uploads `elf_path` and makes it executable
+pub fn deploy(
+    url: &str,
+    keys_dir: &Path,
+    key: &str,
+    elf_path: &Path,
+    options: DeployOptions,
+) -> Result<DeployView> {
+    let key = keys::load(keys_dir, key)?;
+    let elf =
+        fs::read(elf_path).map_err(|e| anyhow!("Unable to read {}: {}", elf_path.display(), e))?;
+    let elf_report = validate_elf(&elf)?;
+
+    let mut txids = vec![];
+    match try_read_account_info(url, key.pubkey)? {
+        Some(account) if account.is_executable && account.data == elf => {
+            return Ok(DeployView {
+                program_id: format!("{:x}", key.pubkey),
+                elf: elf_report,
+                deployment: None,
+                txids,
+            });
+        }
+        Some(account) if account.is_executable => {
+            return Err(anyhow!(
+                "Program {:x} is already deployed with a different ELF",
+                key.pubkey
+            ));
+        }
+        Some(_) => {}
+        None => {
+            let (utxo_txid, utxo_vout) = try_send_utxo(url, key.pubkey)?;
+            txids.push(send_and_confirm(
+                url,
+                vec![create_account_instruction(
+                    &utxo_txid, utxo_vout, key.pubkey,
+                )?],
+                &[key.keypair],
+            )?);
+        }
+    }
+
+    let report = deploy_program(url, key.keypair, &elf, options)?;
+
+    txids.push(send_and_confirm(
+        url,
+        vec![SystemInstruction::new_make_executable_instruction(
+            key.pubkey,
+        )],
+        &[key.keypair],
+    )?);
+
+    Ok(DeployView {
+        program_id: format!("{:x}", key.pubkey),
+        elf: elf_report,
+        deployment: Some(report),
+        txids,
+    })
+}
+
+/// JSON form of an instruction accepted by `arch send --instruction`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InstructionJson {
+    pub program_id: String,
+    #[serde(default)]
+    pub accounts: Vec<AccountMetaJson>,
+    /// Hex encoded instruction data
+    #[serde(default)]
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AccountMetaJson {
+    pub pubkey: String,
+    #[serde(default)]
+    pub is_signer: bool,
+    #[serde(default)]
+    pub is_writable: bool,
+}
+
+impl InstructionJson {
+    fn into_instruction(self, keys_dir: &Path) -> Result<Instruction> {
+        Ok(Instruction {
+            program_id: keys::resolve_pubkey(keys_dir, &self.program_id)?,
+            accounts: self
+                .accounts
+                .into_iter()
+                .map(|account| {
+                    Ok(AccountMeta {
+                        pubkey: keys::resolve_pubkey(keys_dir, &account.pubkey)?,
+                        is_signer: account.is_signer,
+                        is_writable: account.is_writable,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            data: hex::decode(self.data)
+                .map_err(|e| anyhow!("Instruction data is not valid hex: {}", e))?,
+        })
+    }
+}
+
+/// Parses an account given as `<pubkey or key>[:flags]`, where flags may
+/// contain `s` (signer) and `w` (writable)
+pub fn parse_account_meta(keys_dir: &Path, spec: &str) -> Result<AccountMeta> {
+    let (account, flags) = spec.split_once(':').unwrap_or((spec, ""));
+    if let Some(flag) = flags.chars().find(|flag| *flag != 's' && *flag != 'w') {
+        return Err(anyhow!("Unknown account flag `{}` in {}", flag, spec));
+    }
+
+    Ok(AccountMeta {
+        pubkey: keys::resolve_pubkey(keys_dir, account)?,
+        is_signer: flags.contains('s'),
+        is_writable: flags.contains('w'),
+    })
+}
+
+/// Where the instruction sent by `arch send` comes from
+pub enum InstructionSource<'a> {
+    Args {
+        program: &'a str,
+        data: &'a str,
+        accounts: &'a [String],
+    },
+    /// Path to a JSON file, `-` for stdin
+    Json(&'a Path),
+}
+
+/// Builds an instruction, signs it with `signers` and waits until it is processed
+pub fn send(
+    url: &str,
+    keys_dir: &Path,
+    source: InstructionSource,
+    signers: &[String],
+) -> Result<TransactionView> {
+    let instruction = match source {
+        InstructionSource::Args {
+            program,
+            data,
+            accounts,
+        } => Instruction {
+            program_id: keys::resolve_pubkey(keys_dir, program)?,
+            accounts: accounts
+                .iter()
+                .map(|spec| parse_account_meta(keys_dir, spec))
+                .collect::<Result<Vec<_>>>()?,
+            data: hex::decode(data.trim_start_matches("0x"))
+                .map_err(|e| anyhow!("Instruction data is not valid hex: {}", e))?,
+        },
+        InstructionSource::Json(path) => {
+            let mut json = String::new();
+            if path == Path::new("-") {
+                std::io::stdin().read_to_string(&mut json)?;
+            } else {
+                json = fs::read_to_string(path)
+                    .map_err(|e| anyhow!("Unable to read {}: {}", path.display(), e))?;
+            }
+            serde_json::from_str::<InstructionJson>(&json)
+                .map_err(|e| anyhow!("Invalid instruction JSON: {}", e))?
+                .into_instruction(keys_dir)?
+        }
+    };
+
+    let signers = signers
+        .iter()
+        .map(|signer| keys::load(keys_dir, signer).map(|key| key.keypair))
+        .collect::<Result<Vec<_>>>()?;
+
+    let txid = send_and_confirm(url, vec![instruction], &signers)?;
+    transaction(url, &txid)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountView {
+    pub pubkey: String,
+    pub owner: String,
+    pub is_executable: bool,
+    pub utxo: String,
+    pub tag: String,
+    pub data_len: usize,
+    /// Hex encoded account data
+    pub data: String,
+    /// Set for executable accounts
+    pub program: Option<ProgramInfo>,
+}
+
+impl fmt::Display for AccountView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Account          : {}", self.pubkey)?;
+        writeln!(f, "Owner            : {}", self.owner)?;
+        writeln!(f, "Executable       : {}", self.is_executable)?;
+        if let Some(program) = &self.program {
+            writeln!(f, "Upgrade authority: {:?}", program.authority)?;
+        }
+        writeln!(f, "UTXO             : {}", self.utxo)?;
+        writeln!(f, "Tag              : {}", self.tag)?;
+        write!(f, "Data             : {} Bytes", self.data_len)?;
+        for (index, line) in self.data.as_bytes().chunks(64).enumerate() {
+            write!(
+                f,
+                "\n  {:08x}  {}",
+                index * 32,
+                String::from_utf8_lossy(line)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn account(url: &str, keys_dir: &Path, account: &str) -> Result<AccountView> {
+    let pubkey = keys::resolve_pubkey(keys_dir, account)?;
+    let info = read_account_info(url, pubkey)?;
+
+    let program = if info.is_executable {
+        ProgramInfo::from_account(pubkey, &info).ok()
+    } else {
+        None
+    };
+
+    Ok(AccountView {
+        pubkey: format!("{:x}", pubkey),
+        owner: format!("{:x}", info.owner),
+        is_executable: info.is_executable,
+        utxo: info.utxo,
+        tag: info.tag,
+        data_len: info.data.len(),
+        data: hex::encode(&info.data),
+        program,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionView {
+    pub txid: String,
+    pub status: Status,
+    pub bitcoin_txid: Option<String>,
+    pub signers: Vec<String>,
+    pub instructions: Vec<InstructionJson>,
+}
+
+impl From<ProcessedTransaction> for TransactionView {
+    fn from(processed: ProcessedTransaction) -> Self {
+        let message = &processed.runtime_transaction.message;
+
+        Self {
+            txid: processed.txid(),
+            status: processed.status.clone(),
+            bitcoin_txid: processed.bitcoin_txid.clone(),
+            signers: message
+                .signers
+                .iter()
+                .map(|signer| format!("{:x}", signer))
+                .collect(),
+            instructions: message
+                .instructions
+                .iter()
+                .map(|instruction| InstructionJson {
+                    program_id: format!("{:x}", instruction.program_id),
+                    accounts: instruction
+                        .accounts
+                        .iter()
+                        .map(|account| AccountMetaJson {
+                            pubkey: format!("{:x}", account.pubkey),
+                            is_signer: account.is_signer,
+                            is_writable: account.is_writable,
+                        })
+                        .collect(),
+                    data: hex::encode(&instruction.data),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for TransactionView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Transaction      : {}", self.txid)?;
+        match &self.status {
+            Status::Failed(reason) => writeln!(f, "Status           : Failed ({})", reason)?,
+            status => writeln!(f, "Status           : {:?}", status)?,
+        }
+        if let Some(bitcoin_txid) = &self.bitcoin_txid {
+            writeln!(f, "Bitcoin tx       : {}", bitcoin_txid)?;
+        }
+        write!(f, "Signers          : {}", self.signers.join(", "))?;
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            write!(
+                f,
+                "\nInstruction {:<5}: program {}",
+                index, instruction.program_id
+            )?;
+            for account in &instruction.accounts {
+                write!(
+                    f,
+                    "\n  {} {}{}",
+                    account.pubkey,
+                    if account.is_signer { "s" } else { "-" },
+                    if account.is_writable { "w" } else { "-" }
+                )?;
+            }
+            write!(f, "\n  data: {}", instruction.data)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn transaction(url: &str, txid: &str) -> Result<TransactionView> {
+    Ok(get_processed_transaction(url, txid.to_string())?.into())
+}
+
+/// Source of a Rust client module for the IDL stored at `idl_path`
+pub fn idl_client(idl_path: &Path) -> Result<String> {
+    let idl = load_idl(&idl_path.to_string_lossy())?;
+    Ok(generate_client(&idl))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_account_meta, InstructionJson};
+    use arch_program::pubkey::Pubkey;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_account_meta() {
+        let keys_dir = Path::new("/nonexistent");
+        let pubkey = Pubkey::new_unique();
+        let hex_pubkey = format!("{:x}", pubkey);
+
+        let meta = parse_account_meta(keys_dir, &hex_pubkey).unwrap();
+        assert_eq!(meta.pubkey, pubkey);
+        assert!(!meta.is_signer && !meta.is_writable);
+
+        let meta = parse_account_meta(keys_dir, &format!("{}:sw", hex_pubkey)).unwrap();
+        assert!(meta.is_signer && meta.is_writable);
+
+        let meta = parse_account_meta(keys_dir, &format!("{}:w", hex_pubkey)).unwrap();
+        assert!(!meta.is_signer && meta.is_writable);
+
+        assert!(parse_account_meta(keys_dir, &format!("{}:x", hex_pubkey)).is_err());
+        assert!(parse_account_meta(keys_dir, "unknown-key").is_err());
+    }
+
+    #[test]
+    fn test_instruction_json() {
+        let keys_dir = Path::new("/nonexistent");
+        let program_id = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+
+        let json = format!(
+            r#"{{"program_id": "{:x}", "accounts": [{{"pubkey": "{:x}", "is_writable": true}}], "data": "0102"}}"#,
+            program_id, account
+        );
+        let instruction = serde_json::from_str::<InstructionJson>(&json)
+            .unwrap()
+            .into_instruction(keys_dir)
+            .unwrap();
+
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts[0].pubkey, account);
+        assert!(!instruction.accounts[0].is_signer);
+        assert!(instruction.accounts[0].is_writable);
+        assert_eq!(instruction.data, vec![1, 2]);
+    }
+}
@@ -0,0 +1,235 @@
+//! Named keys stored as secret key files in the keys directory
+//!
+//! A key file holds the hex encoded secret key, in the same format as the
+//! files read by `sdk::helper::with_secret_key_file`.
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use arch_program::pubkey::Pubkey;
+use bitcoin::key::UntweakedKeypair;
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use bitcoin::{Address, XOnlyPublicKey};
+use rand_core::OsRng;
+use sdk::constants::BITCOIN_NETWORK;
+use serde::Serialize;
+
+const KEY_EXTENSION: &str = "key";
+
+#[derive(Debug, Clone)]
+pub struct Key {
+    pub name: String,
+    pub path: PathBuf,
+    pub keypair: UntweakedKeypair,
+    pub pubkey: Pubkey,
+}
+
+impl Key {
+    fn from_secret_key(name: String, path: PathBuf, secret_key: &SecretKey) -> Self {
+        let keypair = UntweakedKeypair::from_secret_key(&Secp256k1::new(), secret_key);
+        let pubkey = Pubkey::from_slice(&XOnlyPublicKey::from_keypair(&keypair).0.serialize());
+
+        Self {
+            name,
+            path,
+            keypair,
+            pubkey,
+        }
+    }
+
+    /// Taproot address of the key on [BITCOIN_NETWORK]
+    pub fn address(&self) -> Address {
+        let (x_only_public_key, _parity) = XOnlyPublicKey::from_keypair(&self.keypair);
+        Address::p2tr(&Secp256k1::new(), x_only_public_key, None, BITCOIN_NETWORK)
+    }
+
+    pub fn view(&self) -> KeyView {
+        KeyView {
+            name: self.name.clone(),
+            pubkey: format!("{:x}", self.pubkey),
+            address: self.address().to_string(),
+            path: self.path.display().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyView {
+    pub name: String,
+    pub pubkey: String,
+    pub address: String,
+    pub path: String,
+}
+
+impl fmt::Display for KeyView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Name             : {}", self.name)?;
+        writeln!(f, "Pubkey           : {}", self.pubkey)?;
+        writeln!(f, "Address          : {}", self.address)?;
+        write!(f, "File             : {}", self.path)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(transparent)]
+pub struct KeyList(pub Vec<KeyView>);
+
+impl fmt::Display for KeyList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "No keys found");
+        }
+        for (index, key) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:<16} {}", key.name, key.pubkey)?;
+        }
+        Ok(())
+    }
+}
+
+fn key_path(keys_dir: &Path, name: &str) -> PathBuf {
+    keys_dir.join(format!("{}.{}", name, KEY_EXTENSION))
+}
+
+fn read_key(name: String, path: PathBuf) -> Result<Key> {
+    let secret = fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Unable to read key {}: {}", path.display(), e))?;
+    let secret_key = SecretKey::from_str(secret.trim())
+        .map_err(|e| anyhow!("Invalid secret key in {}: {}", path.display(), e))?;
+
+    Ok(Key::from_secret_key(name, path, &secret_key))
+}
+
+/// Generates a new key and stores it as `name` in `keys_dir`, readable by
+/// the owner only on unix
+pub fn generate(keys_dir: &Path, name: &str) -> Result<Key> {
+    let path = key_path(keys_dir, name);
+    fs::create_dir_all(keys_dir)
+        .map_err(|e| anyhow!("Unable to create {}: {}", keys_dir.display(), e))?;
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => {
+            anyhow!("Key {} already exists at {}", name, path.display())
+        }
+        _ => anyhow!("Unable to create {}: {}", path.display(), e),
+    })?;
+
+    let (secret_key, _) = Secp256k1::new().generate_keypair(&mut OsRng);
+    file.write_all(secret_key.display_secret().to_string().as_bytes())
+        .map_err(|e| anyhow!("Unable to write {}: {}", path.display(), e))?;
+
+    Ok(Key::from_secret_key(name.to_string(), path, &secret_key))
+}
+
+/// Loads a key by name from `keys_dir`, or from a path to a secret key file
+pub fn load(keys_dir: &Path, name_or_path: &str) -> Result<Key> {
+    let path = key_path(keys_dir, name_or_path);
+    if path.is_file() {
+        return read_key(name_or_path.to_string(), path);
+    }
+
+    let path = PathBuf::from(name_or_path);
+    if path.is_file() {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        return read_key(name, path);
+    }
+
+    Err(anyhow!(
+        "No key named {} in {}",
+        name_or_path,
+        keys_dir.display()
+    ))
+}
+
+/// Loads every key stored in `keys_dir`, sorted by name
+pub fn list(keys_dir: &Path) -> Result<Vec<Key>> {
+    if !keys_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut keys = vec![];
+    for entry in fs::read_dir(keys_dir)
+        .map_err(|e| anyhow!("Unable to read {}: {}", keys_dir.display(), e))?
+    {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(KEY_EXTENSION) {
+            continue;
+        }
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        keys.push(read_key(name, path)?);
+    }
+    keys.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(keys)
+}
+
+/// Parses a hex encoded pubkey, falling back to the pubkey of a stored key
+pub fn resolve_pubkey(keys_dir: &Path, pubkey_or_key: &str) -> Result<Pubkey> {
+    if pubkey_or_key.len() == 64 {
+        if let Ok(bytes) = hex::decode(pubkey_or_key) {
+            return Ok(Pubkey::from_slice(&bytes));
+        }
+    }
+
+    load(keys_dir, pubkey_or_key).map(|key| key.pubkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, list, load, resolve_pubkey};
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_generate_load_and_list() {
+        let keys_dir = env::temp_dir().join(format!("arch-cli-keys-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&keys_dir);
+
+        let alice = generate(&keys_dir, "alice").unwrap();
+        let bob = generate(&keys_dir, "bob").unwrap();
+        assert!(generate(&keys_dir, "alice").is_err());
+        assert_eq!(alice.path, keys_dir.join("alice.key"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&alice.path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert_eq!(load(&keys_dir, "alice").unwrap().pubkey, alice.pubkey);
+        assert_eq!(
+            load(&keys_dir, bob.path.to_str().unwrap()).unwrap().pubkey,
+            bob.pubkey
+        );
+        assert!(load(&keys_dir, "carol").is_err());
+
+        let names = list(&keys_dir)
+            .unwrap()
+            .into_iter()
+            .map(|key| key.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["alice", "bob"]);
+
+        let hex_pubkey = format!("{:x}", bob.pubkey);
+        assert_eq!(resolve_pubkey(&keys_dir, &hex_pubkey).unwrap(), bob.pubkey);
+        assert_eq!(resolve_pubkey(&keys_dir, "alice").unwrap(), alice.pubkey);
+
+        fs::remove_dir_all(&keys_dir).unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+//! `arch` command line tool
+//!
+//! Wraps the routine operations of the sdk: managing keys, funding and
+//! creating accounts, deploying programs, sending instructions and inspecting
+//! accounts and transactions.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use sdk::constants::NODE1_ADDRESS;
+use sdk::deploy::DeployOptions;
+use serde::Serialize;
+
+mod commands;
+mod keys;
+
+use commands::InstructionSource;
+
+#[derive(Parser)]
+#[command(name = "arch", version, about = "Interact with an Arch node")]
+struct Cli {
+    /// RPC endpoint of the Arch node
+    #[arg(long, global = true, env = "ARCH_RPC_URL", default_value = NODE1_ADDRESS)]
+    url: String,
+    /// Directory holding named keys
+    #[arg(long, global = true, env = "ARCH_KEYS_DIR", default_value = ".arch")]
+    keys_dir: PathBuf,
+    /// Print JSON instead of human readable output
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage local keys
+    #[command(subcommand)]
+    Key(KeyCommand),
+    /// Send a UTXO to the bitcoin address of an account
+    Fund {
+        /// Hex pubkey or key name
+        account: String,
+    },
+    /// Create the account of a key, optionally assigning it to a program
+    CreateAccount {
+        /// Key name or path to a secret key file
+        key: String,
+        /// Program (hex pubkey or key name) that will own the account
+        #[arg(long)]
+        owner: Option<String>,
+    },
+    /// Validate and deploy a program ELF, then make it executable
+    Deploy {
+        /// Program key name or path to a secret key file
+        key: String,
+        /// Path to the program ELF
+        elf: PathBuf,
+        /// Maximum number of chunk transactions in flight
+        #[arg(long, default_value_t = DeployOptions::default().max_parallel)]
+        max_parallel: usize,
+        /// Maximum number of passes over the pending chunks
+        #[arg(long, default_value_t = DeployOptions::default().max_attempts)]
+        max_attempts: usize,
+    },
+    /// Sign and send a single instruction
+    Send {
+        /// Program to invoke (hex pubkey or key name)
+        #[arg(long, required_unless_present = "instruction")]
+        program: Option<String>,
+        /// Hex encoded instruction data
+        #[arg(long, default_value = "")]
+        data: String,
+        /// Account as `<pubkey or key>[:flags]`, flags being `s` (signer) and/or `w` (writable)
+        #[arg(long = "account")]
+        accounts: Vec<String>,
+        /// Read the instruction from a JSON file (`-` for stdin) instead
+        #[arg(long, conflicts_with_all = ["program", "data", "accounts"])]
+        instruction: Option<PathBuf>,
+        /// Key signing the transaction, may be repeated
+        #[arg(long = "signer", required = true)]
+        signers: Vec<String>,
+    },
+    /// Read an account and dump its data as hex
+    Account {
+        /// Hex pubkey or key name
+        account: String,
+    },
+    /// Fetch a processed transaction
+    Tx { txid: String },
+    /// Work with program interface descriptions
+    #[command(subcommand)]
+    Idl(IdlCommand),
+}
+
+#[derive(Subcommand)]
+enum KeyCommand {
+    /// Generate a new key
+    New { name: String },
+    /// List the stored keys
+    List,
+}
+
+#[derive(Subcommand)]
+enum IdlCommand {
+    /// Generate a typed Rust client module from an IDL JSON file
+    Client {
+        idl: PathBuf,
+        /// File to write the module to, printed to stdout if omitted
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn print<T: Serialize + fmt::Display>(json: bool, value: T) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(&value)?);
+    } else {
+        println!("{}", value);
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let url = cli.url.as_str();
+    let keys_dir = cli.keys_dir.as_path();
+
+    match cli.command {
+        Command::Key(KeyCommand::New { name }) => {
+            print(cli.json, commands::key_new(keys_dir, &name)?)
+        }
+        Command::Key(KeyCommand::List) => print(cli.json, commands::key_list(keys_dir)?),
+        Command::Fund { account } => print(cli.json, commands::fund(url, keys_dir, &account)?),
+        Command::CreateAccount { key, owner } => print(
+            cli.json,
+            commands::create_account(url, keys_dir, &key, owner.as_deref())?,
+        ),
+        Command::Deploy {
+            key,
+            elf,
+            max_parallel,
+            max_attempts,
+        } => print(
+            cli.json,
+            commands::deploy(
+                url,
+                keys_dir,
+                &key,
+                &elf,
+                DeployOptions {
+                    max_parallel,
+                    max_attempts,
+                },
+            )?,
+        ),
+        Command::Send {
+            program,
+            data,
+            accounts,
+            instruction,
+            signers,
+        } => {
+            let source = match (&instruction, &program) {
+                (Some(path), _) => InstructionSource::Json(path),
+                (None, Some(program)) => InstructionSource::Args {
+                    program,
+                    data: &data,
+                    accounts: &accounts,
+                },
+                (None, None) => unreachable!("clap requires --program or --instruction"),
+            };
+            print(cli.json, commands::send(url, keys_dir, source, &signers)?)
+        }
+        Command::Account { account } => {
+            print(cli.json, commands::account(url, keys_dir, &account)?)
+        }
+        Command::Tx { txid } => print(cli.json, commands::transaction(url, &txid)?),
+        Command::Idl(IdlCommand::Client { idl, output }) => {
+            let client = commands::idl_client(&idl)?;
+            match output {
+                Some(output) => std::fs::write(&output, client)
+                    .map_err(|e| anyhow!("Unable to write {}: {}", output.display(), e)),
+                None => {
+                    print!("{}", client);
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cli;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+    }
+}
@@ -2,8 +2,6 @@ use arch_program::{
     account::AccountInfo,
     account::AccountMeta,
     entrypoint,
-    helper::add_state_transition,
-    input_to_sign::InputToSign,
     instruction::Instruction,
     msg,
     program::{
@@ -13,9 +11,9 @@ use arch_program::{
     program_error::ProgramError,
     pubkey::Pubkey,
     system_instruction::SystemInstruction,
-    transaction_to_sign::TransactionToSign,
+    state_transition::StateTransitionBuilder,
     utxo::UtxoMeta,
-    bitcoin::{self, absolute::LockTime, transaction::Version, Transaction}
+    bitcoin::{self, Transaction}
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 
@@ -106,27 +104,13 @@ pub fn process_instruction(
 
         let fees_tx: Transaction = bitcoin::consensus::deserialize(&serialized_tx).unwrap();
 
-        let mut tx = Transaction {
-            version: Version::TWO,
-            lock_time: LockTime::ZERO,
-            input: vec![],
-            output: vec![],
-        };
-
-        add_state_transition(&mut tx, account);
-        
-        let index = 0;
+        let mut builder = StateTransitionBuilder::new();
+        builder.add_account(account)?;
 
         if !anchoring_should_fail {
-            tx.input.push(fees_tx.input[0].clone());
+            builder.add_fee_input(fees_tx.input[0].previous_output);
         }
-        let tx_to_sign = TransactionToSign {
-            tx_bytes: &bitcoin::consensus::serialize(&tx),
-            inputs_to_sign: &[InputToSign {
-                index,
-                signer: account.key.clone(),
-            }],
-        };
+        let tx_to_sign = builder.build()?;
     
         msg!("Transaction to sign {:?}", tx_to_sign);
     
@@ -19,46 +19,54 @@ pub fn try_deploy_program(
 
     let elf = fs::read(elf_path).expect("elf path should be available");
 
-    match read_account_info(NODE1_ADDRESS, program_pubkey) {
+    let already_created = match read_account_info(NODE1_ADDRESS, program_pubkey) {
         Ok(account_info_result) => {
-            if account_info_result.data != elf {
+            if account_info_result.data == elf && account_info_result.is_executable {
+                println!("\x1b[33m Same program already deployed ! Skipping deployment. \x1b[0m");
+                return Ok(program_pubkey);
+            }
+            if account_info_result.is_executable {
                 error!("Program account content is different from provided ELF file !");
                 panic!();
             }
-            println!("\x1b[33m Same program already deployed ! Skipping deployment. \x1b[0m");
-            return Ok(program_pubkey);
+            println!("\x1b[33m Program account already exists, resuming deployment. \x1b[0m");
+            true
         }
-        Err(_) => {}
+        Err(_) => false,
     };
 
-    let (deploy_utxo_btc_txid, deploy_utxo_vout) = send_utxo(program_pubkey);
+    if !already_created {
+        let (deploy_utxo_btc_txid, deploy_utxo_vout) = send_utxo(program_pubkey);
 
-    println!(
-        "\x1b[32m Step 1/4 Successful :\x1b[0m BTC Transaction for program account UTXO successfully sent : https://mempool.dev.aws.archnetwork.xyz/tx/{} -- vout : {}",
-        deploy_utxo_btc_txid, deploy_utxo_vout
-    );
+        println!(
+            "\x1b[32m Step 1/4 Successful :\x1b[0m BTC Transaction for program account UTXO successfully sent : https://mempool.dev.aws.archnetwork.xyz/tx/{} -- vout : {}",
+            deploy_utxo_btc_txid, deploy_utxo_vout
+        );
 
-    let (pa_arch_txid, _pa_arch_txid_hash) = sign_and_send_instruction(
-        SystemInstruction::new_create_account_instruction(
-            hex::decode(deploy_utxo_btc_txid)
-                .unwrap()
-                .try_into()
-                .unwrap(),
-            deploy_utxo_vout,
-            program_pubkey,
-        ),
-        vec![program_keypair],
-    )
-    .expect("signing and sending a transaction should not fail");
+        let (pa_arch_txid, _pa_arch_txid_hash) = sign_and_send_instruction(
+            SystemInstruction::new_create_account_instruction(
+                hex::decode(deploy_utxo_btc_txid)
+                    .unwrap()
+                    .try_into()
+                    .unwrap(),
+                deploy_utxo_vout,
+                program_pubkey,
+            ),
+            vec![program_keypair],
+        )
+        .expect("signing and sending a transaction should not fail");
 
-    let _processed_tx = get_processed_transaction(NODE1_ADDRESS, pa_arch_txid.clone())
-        .expect("get processed transaction should not fail");
+        let _processed_tx = get_processed_transaction(NODE1_ADDRESS, pa_arch_txid.clone())
+            .expect("get processed transaction should not fail");
+
+        println!("\x1b[32m Step 2/4 Successful :\x1b[0m Program account creation transaction successfully processed !.\x1b[0m");
 
-    println!("\x1b[32m Step 2/4 Successful :\x1b[0m Program account creation transaction successfully processed !.\x1b[0m");
+        debug!("{:?}", _processed_tx);
+    }
 
-    debug!("{:?}", _processed_tx);
+    let deployment_report = deploy_program_txs(program_keypair, elf_path)?;
 
-    deploy_program_txs(program_keypair, elf_path);
+    debug!("{:?}", deployment_report);
 
     let elf = fs::read(elf_path).expect("elf path should be available");
 
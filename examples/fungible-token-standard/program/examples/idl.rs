@@ -0,0 +1,6 @@
+//! Prints the IDL of the program: `cargo run --example idl --features idl-build > idl.json`
+
+fn main() {
+    let idl = fungible_token_standard_program::idl::idl();
+    println!("{}", serde_json::to_string_pretty(&idl).unwrap());
+}
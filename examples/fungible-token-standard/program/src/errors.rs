@@ -1,8 +1,12 @@
-use borsh::{BorshDeserialize, BorshSerialize};
+use arch_program::program_error::CustomError;
 
-#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+/// Errors of the token program, returned as `ProgramError::Custom(code)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CustomError)]
 pub enum FungibleTokenError {
-    InsufficientBalance,
+    #[msg("Not enough tokens in the balance account")]
+    InsufficientBalance = 600,
+    #[msg("The mint has reached its supply")]
     MintOver,
+    #[msg("Not enough remaining supply to mint the requested amount")]
     NotEnoughRemainingMintableTokens,
 }
@@ -0,0 +1,20 @@
+use arch_program::{event::Event, pubkey::Pubkey};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Tokens minted to a balance account
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize, Event)]
+pub struct Minted {
+    pub mint: Pubkey,
+    pub balance: Pubkey,
+    pub amount: u64,
+    pub circulating_supply: u64,
+}
+
+/// Tokens moved between two balance accounts
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize, Event)]
+pub struct Transferred {
+    pub mint: Pubkey,
+    pub sender: Pubkey,
+    pub receiver: Pubkey,
+    pub amount: u64,
+}
@@ -0,0 +1,33 @@
+//! Interface description of the fungible token program
+
+use arch_program::account_state::Discriminator;
+use arch_program::idl::Idl;
+
+use crate::errors::FungibleTokenError;
+use crate::mint::TokenMintDetails;
+use crate::token_account::TokenBalance;
+use crate::TokenInstruction;
+
+pub fn idl() -> Idl {
+    Idl::new("fungible_token_standard", env!("CARGO_PKG_VERSION"))
+        .instructions::<TokenInstruction>()
+        .account::<TokenMintDetails>("mint", &TokenMintDetails::DISCRIMINATOR)
+        .account::<TokenBalance>("balance", &TokenBalance::DISCRIMINATOR)
+        .errors::<FungibleTokenError>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::idl;
+
+    #[test]
+    fn test_idl_json_is_up_to_date() {
+        let committed: arch_program::idl::Idl =
+            serde_json::from_str(include_str!("../idl.json")).unwrap();
+        assert_eq!(
+            idl(),
+            committed,
+            "idl.json is stale, run `cargo run --example idl --features idl-build > idl.json`"
+        );
+    }
+}
@@ -2,14 +2,12 @@
 use arch_program::{
     account::AccountInfo,
     entrypoint,
-    helper::add_state_transition,
     input_to_sign::InputToSign,
-    msg,
     program::{
-        get_account_script_pubkey, get_bitcoin_block_height, invoke, next_account_info,
-        set_transaction_to_sign,
+        get_account_script_pubkey, get_bitcoin_block_height, invoke, set_transaction_to_sign,
     },
     program_error::ProgramError,
+    program_instruction::ProgramInstruction,
     pubkey::Pubkey,
     system_instruction::SystemInstruction,
     transaction_to_sign::TransactionToSign,
@@ -18,16 +16,50 @@ use arch_program::{
 use bitcoin::{self, absolute::LockTime, transaction::Version, Transaction};
 use borsh::{BorshDeserialize, BorshSerialize};
 use mint::{
-    initialize_mint, mint_tokens, InitializeMintInput, MintInput, MintStatus, TokenMintDetails,
+    initialize_mint, mint_tokens, InitializeMintAccounts, InitializeMintInput, MintInput,
+    MintStatus, MintTokensAccounts, TokenMintDetails,
 };
 use std::collections::HashMap;
-use token_account::{initialize_balance_account, TokenBalance};
-use transfer::{transfer_tokens, TransferInput};
+use token_account::{initialize_balance_account, InitializeBalanceAccounts, TokenBalance};
+use transfer::{transfer_tokens, TransferAccounts, TransferInput};
 pub mod errors;
+pub mod events;
+#[cfg(feature = "idl-build")]
+pub mod idl;
 pub mod mint;
 pub mod token_account;
 pub mod transfer;
 
+/// Instructions of the token program, the first byte of the instruction data
+/// selecting the variant
+#[derive(Debug, Clone, ProgramInstruction)]
+#[cfg_attr(feature = "idl-build", instruction(idl))]
+pub enum TokenInstruction {
+    /// 1 - Mint account ( owned by program, uninitialized )
+    #[instruction(discriminator = 0, accounts = InitializeMintAccounts, handler = initialize_mint)]
+    InitializeMint(InitializeMintInput),
+    /// 1 - Token balance owner ( signer )
+    /// 2 - Mint account ( owned by program and writable )
+    /// 3 - Supplied account ( owned by program, uninitialized )
+    #[instruction(
+        discriminator = 1,
+        accounts = InitializeBalanceAccounts,
+        handler = initialize_balance_account
+    )]
+    InitializeBalanceAccount,
+    /// 1 - Mint account ( owned by program and writable )
+    /// 2 - Balance account ( owned by program and writable, balance owner is Account 3 )
+    /// 3 - Owner account ( signer )
+    #[instruction(discriminator = 2, accounts = MintTokensAccounts, handler = mint_tokens)]
+    MintTokens(MintInput),
+    /// 1 - Owner Account ( is_signer )
+    /// 2 - Mint Account ( owned by program )
+    /// 3 - Sender Account ( writable and owned by program, balance owner is Account 1 )
+    /// 4 - Receiver Account ( writable and owned by program )
+    #[instruction(discriminator = 3, accounts = TransferAccounts, handler = transfer_tokens)]
+    TransferTokens(TransferInput),
+}
+
 #[cfg(not(feature = "no-entrypoint"))]
 entrypoint!(process_instruction);
 pub fn process_instruction(
@@ -35,148 +67,41 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> Result<(), ProgramError> {
-    let account_iter = &mut accounts.iter();
-
-    match instruction_data[0] {
-        0 => {
-            /* -------------------------------------------------------------------------- */
-            /*                               INITIALIZE MINT                              */
-            /* -------------------------------------------------------------------------- */
-            // 1 Account : (owned by program, uninitialized)
-            msg!("Initializing Mint Account ");
-
-            if accounts.len() != 1 {
-                return Err(ProgramError::Custom(502));
-            }
-
-            let account = next_account_info(account_iter)?;
-
-            let initialize_mint_input: InitializeMintInput =
-                borsh::from_slice(&instruction_data[1..])
-                    .map_err(|_e| ProgramError::InvalidArgument)?;
-
-            initialize_mint(account, program_id, initialize_mint_input)?;
-        }
-        1 => {
-            /* -------------------------------------------------------------------------- */
-            /*                         INITIALIZE BALANCE ACCOUNT                         */
-            /* -------------------------------------------------------------------------- */
-            // No instruction data needed, only 3 accounts
-            // 1 - Token balance owner (signer, writable)
-            // 2 - Mint account (owned by program and writable)
-            // 3 - Supplied account (owned by program, uninitialized )
-            if accounts.len() != 3 {
-                return Err(ProgramError::Custom(502));
-            }
-
-            let owner_account = next_account_info(account_iter)?;
-
-            let mint_account = next_account_info(account_iter)?;
-
-            let balance_account = next_account_info(account_iter)?;
-
-            initialize_balance_account(owner_account, mint_account, balance_account, program_id)?;
-        }
-        2 => {
-            /* -------------------------------------------------------------------------- */
-            /*                                 MINT TOKENS                                */
-            /* -------------------------------------------------------------------------- */
-            // 1 - Mint account ( owned by program and writable )
-            // 2 - Balance account ( owned by program and writable )
-            // 3 - Owner account( signer )
-            if accounts.len() != 3 {
-                return Err(ProgramError::Custom(502));
-            }
-
-            let mint_account = next_account_info(account_iter)?;
-
-            let balance_account = next_account_info(account_iter)?;
-
-            let owner_account = next_account_info(account_iter)?;
-
-            let mint_input: MintInput = borsh::from_slice(&instruction_data[1..])
-                .map_err(|_e| ProgramError::InvalidArgument)?;
-
-            mint_tokens(
-                balance_account,
-                mint_account,
-                owner_account,
-                program_id,
-                mint_input,
-            )?;
-        }
-        3 => {
-            /* -------------------------------------------------------------------------- */
-            /*                               TRANSFER TOKENS                              */
-            /* -------------------------------------------------------------------------- */
-            // 1 - Owner Account ( is_signer )
-            // 2 - Mint Account ( writable and owned by program )
-            // 3 - Sender Account ( writable and owned by program, balance owner is Account 1 )
-            // 4 - Receiver Account ( writable and owned by program )
-
-            if accounts.len() != 4 {
-                return Err(ProgramError::Custom(502));
-            }
-
-            let owner_account = next_account_info(account_iter)?;
-
-            let mint_account = next_account_info(account_iter)?;
-
-            let sender_account = next_account_info(account_iter)?;
-
-            let receiver_account = next_account_info(account_iter)?;
-
-            let transfer_input: TransferInput = borsh::from_slice(&instruction_data[1..])
-                .map_err(|_e| ProgramError::InvalidArgument)?;
-
-            transfer_tokens(
-                owner_account,
-                mint_account,
-                sender_account,
-                receiver_account,
-                program_id,
-                transfer_input,
-            )?;
-        }
-        // 4 => {
-        //     /* -------------------------------------------------------------------------- */
-        //     /*                                 BURN TOKENS                                */
-        //     /* -------------------------------------------------------------------------- */
-        //     // 1 - Owner Account ( is_signer )
-        //     // 2 - Mint Account ( writable and owned by program )
-        //     // 3 - Burner Account ( writable and owned by program, balance owner is Account 1 )
-        //     // 4 - Token Burn Account ( writable and owned by program )
-
-        //     if accounts.len() != 4 {
-        //         return Err(ProgramError::Custom(502));
-        //     }
-
-        //     let owner_account = next_account_info(account_iter)?;
-
-        //     let mint_account = next_account_info(account_iter)?;
-
-        //     let sender_account = next_account_info(account_iter)?;
-
-        //     let token_burn_account = next_account_info(account_iter)?;
-
-        //     let transfer_input: TransferInput = borsh::from_slice(&instruction_data[1..])
-        //         .map_err(|_e| ProgramError::InvalidArgument)?;
+    TokenInstruction::process(program_id, accounts, instruction_data)
+}
 
-        //     transfer_tokens(
-        //         owner_account,
-        //         mint_account,
-        //         sender_account,
-        //         token_burn_account,
-        //         program_id,
-        //         transfer_input,
-        //         true,
-        //     )?;
-        // }
-        _ => {
-            msg!("Invalid argument provided !");
-            return Err(ProgramError::InvalidArgument);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arch_program::accounts::ToAccountMetas;
+    use mint::MintTokensAccountsKeys;
+
+    #[test]
+    fn test_discriminators_are_stable() {
+        assert_eq!(
+            TokenInstruction::DISCRIMINATORS,
+            &[&[0][..], &[1], &[2], &[3]]
+        );
+        assert_eq!(
+            TokenInstruction::unpack(&[]).unwrap_err(),
+            ProgramError::InvalidInstructionData
+        );
     }
 
-    Ok(())
+    #[test]
+    fn test_mint_tokens_instruction() {
+        let keys = MintTokensAccountsKeys {
+            mint: Pubkey([1; 32]),
+            balance: Pubkey([2; 32]),
+            owner: Pubkey([3; 32]),
+        };
+        let instruction = TokenInstruction::new_mint_tokens_instruction(
+            Pubkey([9; 32]),
+            keys.clone(),
+            MintInput::new(5),
+        );
+        assert_eq!(instruction.accounts, keys.to_account_metas());
+        assert!(instruction.accounts[2].is_signer);
+        assert_eq!(instruction.data, [&[2][..], &5u64.to_le_bytes()].concat());
+    }
 }
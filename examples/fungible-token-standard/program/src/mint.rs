@@ -1,11 +1,15 @@
 use std::collections::HashMap;
 
-use arch_program::{account::AccountInfo, msg, program_error::ProgramError, pubkey::Pubkey};
+use arch_program::{
+    account::AccountInfo, account_state::AccountState, accounts::Accounts, emit, msg,
+    program_error::ProgramError,
+};
 use borsh::{BorshDeserialize, BorshSerialize};
 
-use crate::token_account::TokenBalance;
+use crate::{errors::FungibleTokenError, events::Minted, token_account::TokenBalance};
 
-#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, AccountState)]
+#[cfg_attr(feature = "idl-build", derive(arch_program::idl::IdlBuild))]
 pub struct TokenMintDetails {
     owner: [u8; 32],
     pub status: MintStatus,
@@ -34,12 +38,14 @@ impl TokenMintDetails {
     }
 }
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, Eq, PartialEq)]
+#[cfg_attr(feature = "idl-build", derive(arch_program::idl::IdlBuild))]
 pub enum MintStatus {
     Ongoing,
     Finished,
 }
 
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "idl-build", derive(arch_program::idl::IdlBuild))]
 pub struct InitializeMintInput {
     owner: [u8; 32],
     supply: u64, // in lowest denomination
@@ -57,39 +63,26 @@ impl InitializeMintInput {
     }
 }
 
+#[derive(Accounts)]
+pub struct InitializeMintAccounts<'a> {
+    #[account(mut, owner = program, empty)]
+    pub mint: AccountInfo<'a>,
+}
+
 pub(crate) fn initialize_mint(
-    account: &AccountInfo<'_>,
-    program_id: &Pubkey,
+    accounts: &InitializeMintAccounts<'_>,
     mint_input: InitializeMintInput,
 ) -> Result<(), ProgramError> {
-    if !account.data_is_empty() {
-        return Err(ProgramError::AccountAlreadyInitialized);
-    }
-
-    if account.owner != program_id {
-        return Err(ProgramError::IllegalOwner);
-    }
+    msg!("Initializing Mint Account ");
 
     let mint_initial_details =
         TokenMintDetails::new(mint_input, MintStatus::Ongoing, HashMap::new());
 
-    let serialized_mint_details = borsh::to_vec(&mint_initial_details)
-        .map_err(|e| ProgramError::BorshIoError(e.to_string()))?;
-
-    if serialized_mint_details.len() > 0 {
-        account.realloc(serialized_mint_details.len(), true)?;
-    }
-
-    account
-        .data
-        .try_borrow_mut()
-        .map_err(|_e| ProgramError::AccountBorrowFailed)?
-        .copy_from_slice(&serialized_mint_details);
-
-    Ok(())
+    accounts.mint.init(&mint_initial_details)
 }
 
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+#[cfg_attr(feature = "idl-build", derive(arch_program::idl::IdlBuild))]
 pub struct MintInput {
     pub amount: u64,
 }
@@ -98,61 +91,42 @@ impl MintInput {
         MintInput { amount }
     }
 }
+#[derive(Accounts)]
+pub struct MintTokensAccounts<'a> {
+    #[account(mut, owner = program, initialized)]
+    pub mint: AccountInfo<'a>,
+    #[account(mut, owner = program, data = TokenBalance, has_one(mint_account = mint), has_one = owner)]
+    pub balance: AccountInfo<'a>,
+    #[account(signer)]
+    pub owner: AccountInfo<'a>,
+}
+
 pub fn mint_tokens(
-    balance_account: &AccountInfo<'_>,
-    mint_account: &AccountInfo<'_>,
-    owner_account: &AccountInfo<'_>,
-    program_id: &Pubkey,
+    accounts: &MintTokensAccounts<'_>,
     mint_input: MintInput,
 ) -> Result<(), ProgramError> {
-    /* ------------------------- Balance account checks ------------------------- */
-    let mut token_balance_data = balance_account
-        .data
-        .try_borrow_mut()
-        .map_err(|_| ProgramError::AccountBorrowFailed)?;
-
-    let mut token_balance = TokenBalance::deserialize(&mut &token_balance_data[..])
-        .map_err(|_| ProgramError::InvalidAccountData)?;
-
-    if balance_account.owner != program_id {
-        return Err(ProgramError::Custom(501));
-    };
-
-    if token_balance.mint_account != mint_account.key.serialize() {
-        return Err(ProgramError::Custom(503));
-    }
-
-    if token_balance.owner != owner_account.key.serialize() {
-        return Err(ProgramError::Custom(502));
-    }
-
-    /* --------------------------- MINT ACCOUNT CHECKS -------------------------- */
+    let MintTokensAccounts {
+        mint: mint_account,
+        balance: balance_account,
+        ..
+    } = accounts;
 
-    let mut mint_data = mint_account
-        .data
-        .try_borrow_mut()
-        .map_err(|_| ProgramError::AccountBorrowFailed)?;
-
-    let mut mint_details = TokenMintDetails::deserialize(&mut &mint_data[..])
-        .map_err(|_| ProgramError::InvalidAccountData)?;
-
-    if mint_account.owner != program_id {
-        return Err(ProgramError::Custom(504));
-    }
+    let mut token_balance = balance_account.load_mut::<TokenBalance>()?;
+    let mut mint_details = mint_account.load_mut::<TokenMintDetails>()?;
 
     if mint_details.status == MintStatus::Finished {
-        return Err(ProgramError::Custom(502));
-    }
-
-    /* -------------------------- OWNER ACCOUNT CHECKS -------------------------- */
-    if !owner_account.is_signer {
-        return Err(ProgramError::MissingRequiredSignature);
+        return Err(FungibleTokenError::MintOver.into());
     }
 
     /* -------------------------------- EXECUTION ------------------------------- */
 
     add_mint_to_circulating_supply(&mut mint_details, &mint_input)?;
-    msg!("circulating_supply: {}", mint_details.circulating_supply);
+    emit!(Minted {
+        mint: *mint_account.key,
+        balance: *balance_account.key,
+        amount: mint_input.amount,
+        circulating_supply: mint_details.circulating_supply,
+    });
 
     if mint_details.circulating_supply == mint_details.supply {
         mint_details.status = MintStatus::Finished;
@@ -160,23 +134,8 @@ pub fn mint_tokens(
 
     token_balance.increase_balance(mint_input.amount, &mint_details);
 
-    let new_serialized_token_balance = borsh::to_vec(&token_balance).unwrap();
-
-    let new_serialized_mint_details = borsh::to_vec(&mint_details).unwrap();
-
-    /* -------------------------- UPDATE TOKEN BALANCE -------------------------- */
-
-    if new_serialized_token_balance.len() > token_balance_data.len() {
-        balance_account.realloc(new_serialized_token_balance.len(), true)?;
-    }
-    token_balance_data.copy_from_slice(&new_serialized_token_balance);
-
-    /* ---------------------------- UPDATE MINT DATA ---------------------------- */
-
-    if new_serialized_mint_details.len() > mint_data.len() {
-        mint_account.realloc(new_serialized_mint_details.len(), true)?;
-    }
-    mint_data.copy_from_slice(&new_serialized_mint_details);
+    token_balance.save()?;
+    mint_details.save()?;
 
     Ok(())
 }
@@ -191,7 +150,7 @@ pub fn add_mint_to_circulating_supply(
             mint_details.supply - mint_details.circulating_supply,
             mint_input.amount
         );
-        return Err(ProgramError::InsufficientFunds); // Not enough remaining supply
+        return Err(FungibleTokenError::NotEnoughRemainingMintableTokens.into());
     }
 
     mint_details.circulating_supply += mint_input.amount;
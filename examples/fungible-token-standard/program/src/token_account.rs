@@ -1,9 +1,13 @@
-use arch_program::{account::AccountInfo, msg, program_error::ProgramError, pubkey::Pubkey};
+use arch_program::{
+    account::AccountInfo, account_state::AccountState, accounts::Accounts, msg,
+    program_error::ProgramError,
+};
 use borsh::{BorshDeserialize, BorshSerialize};
 
-use crate::mint::TokenMintDetails;
+use crate::{errors::FungibleTokenError, mint::TokenMintDetails};
 
-#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, Debug)]
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, Debug, AccountState)]
+#[cfg_attr(feature = "idl-build", derive(arch_program::idl::IdlBuild))]
 pub struct TokenBalance {
     pub owner: [u8; 32],
     pub mint_account: [u8; 32],
@@ -57,7 +61,7 @@ impl TokenBalance {
                 self.current_balance,
                 amount
             );
-            return Err(ProgramError::InsufficientFunds);
+            return Err(FungibleTokenError::InsufficientBalance.into());
         }
 
         self.current_balance -= amount;
@@ -66,48 +70,31 @@ impl TokenBalance {
     }
 }
 
+#[derive(Accounts)]
+pub struct InitializeBalanceAccounts<'a> {
+    #[account(signer)]
+    pub owner: AccountInfo<'a>,
+    #[account(mut, owner = program, initialized, data = TokenMintDetails)]
+    pub mint: AccountInfo<'a>,
+    #[account(mut, owner = program, empty)]
+    pub balance: AccountInfo<'a>,
+}
+
 pub fn initialize_balance_account(
-    owner_account: &AccountInfo<'_>,
-    mint_account: &AccountInfo<'_>,
-    balance_account: &AccountInfo<'_>,
-    program_id: &Pubkey,
+    accounts: &InitializeBalanceAccounts<'_>,
 ) -> Result<(), ProgramError> {
-    if !owner_account.is_signer {
-        return Err(ProgramError::MissingRequiredSignature);
-    }
-
-    if !mint_account.is_writable {
-        return Err(ProgramError::Immutable);
-    }
-
-    if mint_account.owner != program_id {
-        return Err(ProgramError::IllegalOwner);
-    }
-
-    if !balance_account.data_is_empty() || balance_account.is_executable {
-        return Err(ProgramError::AccountAlreadyInitialized);
-    }
-
-    if balance_account.owner != program_id {
-        return Err(ProgramError::IllegalOwner);
-    }
+    let InitializeBalanceAccounts {
+        owner: owner_account,
+        mint: mint_account,
+        balance: balance_account,
+    } = accounts;
 
     let token_balance =
         TokenBalance::new(owner_account.key.serialize(), mint_account.key.serialize());
 
-    let serialized_token_balance =
-        borsh::to_vec(&token_balance).map_err(|e| ProgramError::BorshIoError(e.to_string()))?;
-
-    balance_account.realloc(serialized_token_balance.len(), true)?;
-
     msg!("Changing account data to {:?}!", token_balance);
 
-    balance_account
-        .data
-        .try_borrow_mut()
-        .unwrap()
-        .copy_from_slice(&serialized_token_balance);
-    Ok(())
+    balance_account.init(&token_balance)
 }
 
 //cargo test --features=no-entrypoint
@@ -152,6 +139,79 @@ mod balance_change_tests {
 
         balance.increase_balance(2, &mint_details); // Start with 2 tokens
         let result = balance.decrease_balance(3, &mint_details); // Attempt to subtract more than available
-        assert!(result.is_err());
+        assert_eq!(result, Err(FungibleTokenError::InsufficientBalance.into()));
+    }
+}
+
+#[cfg(test)]
+mod initialize_balance_tests {
+    use super::*;
+    use crate::mint::{InitializeMintInput, MintStatus};
+    use arch_program::{pubkey::Pubkey, utxo::UtxoMeta};
+    use std::collections::HashMap;
+
+    fn program_id() -> Pubkey {
+        Pubkey([9; 32])
+    }
+
+    fn state_data<T: AccountState>(state: &T) -> Vec<u8> {
+        [&T::DISCRIMINATOR[..], &borsh::to_vec(state).unwrap()].concat()
+    }
+
+    fn mint_data() -> Vec<u8> {
+        let input = InitializeMintInput::new([1; 32], 1000, "TEST".to_string(), 2);
+        state_data(&TokenMintDetails::new(
+            input,
+            MintStatus::Ongoing,
+            HashMap::new(),
+        ))
+    }
+
+    /// Checks the accounts of `initialize_balance_account` with `mint_data` in the mint
+    fn try_initialize_balance(mint_data: Vec<u8>) -> Result<(), ProgramError> {
+        let keys = [Pubkey([1; 32]), Pubkey([2; 32]), Pubkey([3; 32])];
+        let (owner, program) = (Pubkey::system_program(), program_id());
+        let utxo = UtxoMeta::from([0; 32], 0);
+        let (mut owner_data, mut mint_data, mut balance_data) = (vec![], mint_data, vec![]);
+        let infos = [
+            AccountInfo::new(&keys[0], &mut owner_data, &owner, &utxo, true, false, false),
+            AccountInfo::new(
+                &keys[1],
+                &mut mint_data,
+                &program,
+                &utxo,
+                false,
+                true,
+                false,
+            ),
+            AccountInfo::new(
+                &keys[2],
+                &mut balance_data,
+                &program,
+                &utxo,
+                false,
+                true,
+                false,
+            ),
+        ];
+        InitializeBalanceAccounts::try_accounts(&program_id(), &infos).map(|_| ())
+    }
+
+    #[test]
+    fn test_mint_account() {
+        assert_eq!(try_initialize_balance(mint_data()), Ok(()));
+    }
+
+    #[test]
+    fn test_non_mint_account_is_rejected() {
+        let balance = TokenBalance::new([1; 32], [2; 32]);
+        assert_eq!(
+            try_initialize_balance(state_data(&balance)),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            try_initialize_balance(vec![]),
+            Err(ProgramError::UninitializedAccount)
+        );
     }
 }
@@ -1,10 +1,9 @@
 use anyhow::{anyhow, Result};
 use arch_program::{
-    account::AccountMeta, instruction::Instruction, pubkey::Pubkey,
+    account::AccountMeta, account_state::decode_state, instruction::Instruction, pubkey::Pubkey,
     system_instruction::SystemInstruction,
 };
 use bitcoin::key::Keypair;
-use borsh::BorshDeserialize;
 use ebpf_counter::{
     counter_helpers::{assign_ownership_to_program, generate_new_keypair, print_title},
     counter_instructions::{build_and_send_block, build_transaction, fetch_processed_transactions},
@@ -55,7 +54,7 @@ pub(crate) fn try_create_mint_account(single_use_mint: bool) -> Result<Pubkey> {
         with_secret_key_file(MINT_OWNER_FILE_PATH).expect("getting caller info should not fail");
 
     if let Ok(account_info_result) = read_account_info(NODE1_ADDRESS, mint_pubkey) {
-        match TokenMintDetails::try_from_slice(&account_info_result.data) {
+        match decode_state::<TokenMintDetails>(&account_info_result.data) {
             Ok(_mint_details) => {
                 println!("");
                 println!("\x1b[33m Mint Details already exist in account ! Skipping mint initialization. \x1b[0m");
@@ -127,7 +126,7 @@ pub(crate) fn try_create_mint_account(single_use_mint: bool) -> Result<Pubkey> {
 
     let account_info = read_account_info(NODE1_ADDRESS, mint_pubkey.clone()).unwrap();
 
-    let mint_details = TokenMintDetails::try_from_slice(&account_info.data).unwrap();
+    let mint_details = decode_state::<TokenMintDetails>(&account_info.data).unwrap();
 
     assert!(account_info.owner == program_pubkey);
 
@@ -171,14 +170,10 @@ pub(crate) fn provide_empty_account_to_program(
 }
 
 pub(crate) fn get_mint_info(account_pubkey: &Pubkey) -> Result<TokenMintDetails> {
-    use borsh::BorshDeserialize;
-
     let account_info = read_account_info(NODE1_ADDRESS, account_pubkey.clone())
         .map_err(|e| anyhow!(format!("Error reading account content {}", e.to_string())))?;
 
-    let mut account_info_data = account_info.data.as_slice();
-
-    let account_counter = TokenMintDetails::deserialize(&mut account_info_data)
+    let account_counter = decode_state::<TokenMintDetails>(&account_info.data)
         .map_err(|e| anyhow!(format!("Error corrupted account data {}", e.to_string())))?;
 
     Ok(account_counter)
@@ -188,9 +183,7 @@ pub(crate) fn get_balance_account(account_pubkey: &Pubkey) -> Result<TokenBalanc
     let account_info = read_account_info(NODE1_ADDRESS, account_pubkey.clone())
         .map_err(|e| anyhow!(format!("Error reading account content {}", e.to_string())))?;
 
-    let mut account_info_data = account_info.data.as_slice();
-
-    let token_balance = TokenBalance::deserialize(&mut account_info_data)
+    let token_balance = decode_state::<TokenBalance>(&account_info.data)
         .map_err(|e| anyhow!(format!("Error corrupted account data {}", e.to_string())))?;
 
     Ok(token_balance)
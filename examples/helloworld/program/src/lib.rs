@@ -1,17 +1,15 @@
 use arch_program::{
     account::AccountInfo,
     entrypoint, msg,
-    helper::add_state_transition,
-    input_to_sign::InputToSign,
     program::{
         get_account_script_pubkey, get_bitcoin_block_height,
         next_account_info, set_transaction_to_sign, invoke
     },
     program_error::ProgramError,
     pubkey::Pubkey, utxo::UtxoMeta, 
-    transaction_to_sign::TransactionToSign,
+    state_transition::StateTransitionBuilder,
     system_instruction::SystemInstruction,
-    bitcoin::{self, Transaction, transaction::Version, absolute::LockTime}
+    bitcoin::{self, Transaction}
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 
@@ -71,22 +69,11 @@ pub fn process_instruction(
         ).expect("failed");
     }
 
-    let mut tx = Transaction {
-        version: Version::TWO,
-        lock_time: LockTime::ZERO,
-        input: vec![],
-        output: vec![],
-    };
-    add_state_transition(&mut tx, account);
-    tx.input.push(fees_tx.input[0].clone());
-
-    let tx_to_sign = TransactionToSign {
-        tx_bytes: &bitcoin::consensus::serialize(&tx),
-        inputs_to_sign: &[InputToSign {
-            index: 0,
-            signer: account.key.clone(),
-        }],
-    };
+    let mut builder = StateTransitionBuilder::new();
+    builder
+        .add_account(account)?
+        .add_fee_input(fees_tx.input[0].previous_output);
+    let tx_to_sign = builder.build()?;
 
     msg!("tx_to_sign{:?}", tx_to_sign);
 
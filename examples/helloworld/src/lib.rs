@@ -86,7 +86,8 @@ mod tests {
         deploy_program_txs(
             program_keypair,
             "program/target/sbf-solana-solana/release/helloworldprogram.so",
-        );
+        )
+        .expect("program deployment should not fail");
 
         println!("{:?}", ());
 
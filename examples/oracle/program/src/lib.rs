@@ -1,10 +1,9 @@
 use arch_program::{
     account::AccountInfo,
-    entrypoint,
-    helper::get_state_transition_tx,
+    emit, entrypoint,
+    event::Event,
     input_to_sign::InputToSign,
     instruction::Instruction,
-    msg,
     program::{
         get_account_script_pubkey, get_bitcoin_tx, get_network_xonly_pubkey, invoke,
         next_account_info, set_return_data, set_transaction_to_sign, validate_utxo_ownership,
@@ -17,6 +16,13 @@ use arch_program::{
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 
+/// The oracle account was written new data
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize, Event)]
+pub struct OracleUpdated {
+    pub oracle: Pubkey,
+    pub data: Vec<u8>,
+}
+
 entrypoint!(update_data);
 pub fn update_data(
     program_id: &Pubkey,
@@ -41,7 +47,10 @@ pub fn update_data(
         .unwrap()
         .copy_from_slice(instruction_data);
 
-    msg!("updated");
+    emit!(OracleUpdated {
+        oracle: *oracle_account.key,
+        data: instruction_data.to_vec(),
+    });
 
     Ok(())
 }
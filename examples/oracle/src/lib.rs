@@ -51,9 +51,10 @@ mod tests {
             .expect("get processed transaction should not fail");
         println!("processed_tx {:?}", processed_tx);
 
-        let txids = deploy_program_txs(program_keypair, "program/target/deploy/oracleprogram.so");
+        let (_, report) = deploy_program_txs(program_keypair, "program/target/deploy/oracleprogram.so")
+            .expect("program deployment should not fail");
 
-        println!("{:?}", txids);
+        println!("{:?}", report.txids);
 
         let elf = fs::read("program/target/deploy/oracleprogram.so").expect("elf path should be available");
         assert!(read_account_info(NODE1_ADDRESS, program_pubkey.clone()).unwrap().data == elf);
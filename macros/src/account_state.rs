@@ -0,0 +1,183 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitInt, LitStr, Path};
+
+/// `#[account_state(name = "..", version = N, migrate_from = Type)]`
+struct StateAttr {
+    name: String,
+    version: u32,
+    migrate_from: Option<Path>,
+}
+
+impl StateAttr {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut name = None;
+        let mut version = None;
+        let mut migrate_from = None;
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("account_state"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("version") {
+                    version = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                } else if meta.path.is_ident("migrate_from") {
+                    migrate_from = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected `name`, `version` or `migrate_from`"));
+                }
+                Ok(())
+            })?;
+        }
+        if let (Some(path), None | Some(0)) = (&migrate_from, version) {
+            return Err(syn::Error::new_spanned(
+                path,
+                "`migrate_from` needs a `version` above 0",
+            ));
+        }
+        Ok(Self {
+            name: name.unwrap_or_else(|| input.ident.to_string()),
+            version: version.unwrap_or(0),
+            migrate_from,
+        })
+    }
+
+    fn discriminator(&self) -> Vec<u8> {
+        match self.version {
+            0 => discriminator(&format!("account:{}", self.name)),
+            version => discriminator(&format!("account:{}:v{}", self.name, version)),
+        }
+    }
+}
+
+/// First 8 bytes of `sha256(preimage)`
+pub(crate) fn discriminator(preimage: &str) -> Vec<u8> {
+    let digest = sha256::digest(preimage);
+    (0..8)
+        .map(|i| u8::from_str_radix(&digest[2 * i..2 * i + 2], 16).unwrap())
+        .collect()
+}
+
+fn impl_discriminator(input: &DeriveInput, attr: &StateAttr) -> TokenStream {
+    impl_discriminator_bytes(input, &attr.discriminator())
+}
+
+pub(crate) fn impl_discriminator_bytes(input: &DeriveInput, bytes: &[u8]) -> TokenStream {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::arch_program::account_state::Discriminator for #ident #ty_generics #where_clause {
+            const DISCRIMINATOR: [u8; 8] = [#(#bytes),*];
+        }
+    }
+}
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let attr = StateAttr::parse(input)?;
+    let discriminator = impl_discriminator(input, &attr);
+
+    let account_state = quote!(::arch_program::account_state::AccountState);
+    let program_error = quote!(::arch_program::program_error::ProgramError);
+    let version = attr.version;
+    let migration = match &attr.migrate_from {
+        Some(previous) => quote! {
+            const VERSION: u32 = #version;
+
+            fn version_of(data: &[u8]) -> Option<u32> {
+                if data.starts_with(&<Self as ::arch_program::account_state::Discriminator>::DISCRIMINATOR) {
+                    return Some(#version);
+                }
+                <#previous as #account_state>::version_of(data)
+            }
+
+            fn upgrade(data: &[u8]) -> Result<Self, #program_error> {
+                const _: () = assert!(
+                    <#previous as #account_state>::VERSION < #version,
+                    "`migrate_from` must name an older version"
+                );
+                if data.starts_with(&<Self as ::arch_program::account_state::Discriminator>::DISCRIMINATOR) {
+                    return ::arch_program::account_state::decode_current(data);
+                }
+                <#previous as #account_state>::upgrade(data).map(<Self as ::core::convert::From<#previous>>::from)
+            }
+        },
+        None => quote!(const VERSION: u32 = #version;),
+    };
+
+    Ok(quote! {
+        #discriminator
+
+        impl #impl_generics #account_state for #ident #ty_generics #where_clause {
+            #migration
+        }
+    })
+}
+
+pub fn derive_zero_copy(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "ZeroCopy cannot be derived for generic types",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Unit => return Err(syn::Error::new_spanned(ident, "ZeroCopy needs fields")),
+            fields => fields,
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "ZeroCopy can only be derived for structs",
+            ))
+        }
+    };
+    let mut repr_c = false;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("repr")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") {
+                repr_c = true;
+            }
+            Ok(())
+        })?;
+    }
+    if !repr_c {
+        return Err(syn::Error::new_spanned(
+            ident,
+            "ZeroCopy needs a `#[repr(C)]` layout",
+        ));
+    }
+
+    let types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let discriminator = impl_discriminator(
+        input,
+        &StateAttr {
+            name: ident.to_string(),
+            version: 0,
+            migrate_from: None,
+        },
+    );
+    Ok(quote! {
+        #discriminator
+
+        // every field is plain data and the layout has no padding
+        const _: fn() = || {
+            fn assert_pod<T: ::arch_program::account_state::Pod>() {}
+            #(assert_pod::<#types>();)*
+        };
+        const _: () = assert!(
+            0 #(+ ::core::mem::size_of::<#types>())* == ::core::mem::size_of::<#ident>(),
+            "ZeroCopy structs cannot have padding"
+        );
+
+        unsafe impl ::arch_program::account_state::Pod for #ident {}
+
+        impl ::arch_program::account_state::ZeroCopy for #ident {}
+    })
+}
@@ -0,0 +1,257 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Data, DeriveInput, Expr, Fields, Ident, Token, Type};
+
+enum Check {
+    Signer,
+    Mut,
+    Initialized,
+    Empty,
+    OwnerProgram,
+    Owner(Expr),
+    Data(Type),
+    HasOne { field: Ident, account: Ident },
+}
+
+struct Constraint {
+    check: Check,
+    error: Option<Expr>,
+    span: Span,
+}
+
+impl Parse for Constraint {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let span = input.span();
+        let check = if input.peek(Token![mut]) {
+            input.parse::<Token![mut]>()?;
+            Check::Mut
+        } else {
+            let name: Ident = input.parse()?;
+            match name.to_string().as_str() {
+                "signer" => Check::Signer,
+                "initialized" => Check::Initialized,
+                "empty" => Check::Empty,
+                "owner" => {
+                    input.parse::<Token![=]>()?;
+                    let owner: Expr = input.parse()?;
+                    match &owner {
+                        Expr::Path(path) if path.path.is_ident("program") => Check::OwnerProgram,
+                        _ => Check::Owner(owner),
+                    }
+                }
+                "data" => {
+                    input.parse::<Token![=]>()?;
+                    Check::Data(input.parse()?)
+                }
+                "has_one" => {
+                    if input.peek(Token![=]) {
+                        input.parse::<Token![=]>()?;
+                        let account: Ident = input.parse()?;
+                        Check::HasOne {
+                            field: account.clone(),
+                            account,
+                        }
+                    } else {
+                        let content;
+                        syn::parenthesized!(content in input);
+                        let field = content.parse()?;
+                        content.parse::<Token![=]>()?;
+                        let account = content.parse()?;
+                        Check::HasOne { field, account }
+                    }
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "expected one of `signer`, `mut`, `owner`, `initialized`, `empty`, `data` or `has_one`",
+                    ))
+                }
+            }
+        };
+        let error = if input.peek(Token![@]) {
+            input.parse::<Token![@]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Self { check, error, span })
+    }
+}
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    let lifetime = match input.generics.lifetimes().next() {
+        Some(param) => &param.lifetime,
+        None => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "Accounts structs take the lifetime of their `AccountInfo`s, e.g. `struct Accounts<'a>`",
+            ))
+        }
+    };
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "Accounts can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "Accounts can only be derived for structs",
+            ))
+        }
+    };
+
+    let names: Vec<&Ident> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let mut checks = Vec::new();
+    let mut metas = Vec::new();
+    let mut idl_metas = Vec::new();
+    for field in fields {
+        let name = field.ident.as_ref().unwrap();
+        let mut constraints = Vec::new();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("account")) {
+            constraints.extend(
+                attr.parse_args_with(Punctuated::<Constraint, Token![,]>::parse_terminated)?,
+            );
+        }
+        let is_signer = constraints.iter().any(|c| matches!(c.check, Check::Signer));
+        let is_writable = constraints.iter().any(|c| matches!(c.check, Check::Mut));
+        metas.push(quote! {
+            ::arch_program::account::AccountMeta {
+                pubkey: self.#name,
+                is_signer: #is_signer,
+                is_writable: #is_writable,
+            }
+        });
+        let idl_name = name.to_string();
+        idl_metas.push(quote! {
+            ::arch_program::idl::IdlAccountMeta {
+                name: ::arch_program::__private::String::from(#idl_name),
+                is_signer: #is_signer,
+                is_writable: #is_writable,
+            }
+        });
+        checks.push(field_checks(name, &names, constraints)?);
+    }
+
+    let len = names.len();
+    let bind = if names.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            let [#(#names,)* ..] = __accounts else {
+                return Err(::arch_program::program_error::ProgramError::NotEnoughAccountKeys);
+            };
+        }
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let vis = &input.vis;
+    let keys = format_ident!("{}Keys", ident);
+    let keys_doc = format!("Keys of the accounts of [`{}`]", ident);
+
+    Ok(quote! {
+        #[doc = #keys_doc]
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        #vis struct #keys {
+            #(pub #names: ::arch_program::pubkey::Pubkey,)*
+        }
+
+        impl ::arch_program::accounts::ToAccountMetas for #keys {
+            fn to_account_metas(&self) -> ::arch_program::__private::Vec<::arch_program::account::AccountMeta> {
+                ::arch_program::__private::vec![#(#metas),*]
+            }
+        }
+
+        impl #impl_generics ::arch_program::accounts::Accounts<#lifetime> for #ident #ty_generics #where_clause {
+            const LEN: usize = #len;
+
+            type Keys = #keys;
+
+            fn try_accounts(
+                __program_id: &::arch_program::pubkey::Pubkey,
+                __accounts: &[::arch_program::account::AccountInfo<#lifetime>],
+            ) -> Result<Self, ::arch_program::program_error::ProgramError> {
+                #bind
+                #(#checks)*
+                Ok(Self {
+                    #(#names: #names.clone(),)*
+                })
+            }
+        }
+
+        impl #impl_generics ::arch_program::idl::IdlAccounts for #ident #ty_generics #where_clause {
+            fn idl_accounts() -> ::arch_program::__private::Vec<::arch_program::idl::IdlAccountMeta> {
+                ::arch_program::__private::vec![#(#idl_metas),*]
+            }
+        }
+    })
+}
+
+/// Statements checking the constraints of the account bound to `name`
+fn field_checks(
+    name: &Ident,
+    names: &[&Ident],
+    constraints: Vec<Constraint>,
+) -> syn::Result<TokenStream> {
+    let accounts = quote!(::arch_program::accounts);
+    let data = format_ident!("__{}_data", name);
+    let has_data = constraints
+        .iter()
+        .any(|c| matches!(c.check, Check::Data(_)));
+    let has_one = constraints
+        .iter()
+        .any(|c| matches!(c.check, Check::HasOne { .. }));
+
+    // key checks run last as they need the decoded data
+    let mut checks = Vec::new();
+    let mut key_checks = Vec::new();
+    for constraint in constraints {
+        let check = match &constraint.check {
+            Check::Signer => quote!(#accounts::check_signer(#name)),
+            Check::Mut => quote!(#accounts::check_writable(#name)),
+            Check::Initialized => quote!(#accounts::check_initialized(#name)),
+            Check::Empty => quote!(#accounts::check_empty(#name)),
+            Check::OwnerProgram => quote!(#accounts::check_owner(#name, __program_id)),
+            Check::Owner(owner) => quote!(#accounts::check_owner(#name, &(#owner))),
+            Check::Data(ty) => quote!(#accounts::load_data::<#ty>(#name)),
+            Check::HasOne { field, account } => {
+                if !has_data {
+                    return Err(syn::Error::new(
+                        constraint.span,
+                        "`has_one` needs the account data type, add `data = <Type>`",
+                    ));
+                }
+                if !names.contains(&account) {
+                    return Err(syn::Error::new_spanned(
+                        account,
+                        format!("no account named `{}` in this struct", account),
+                    ));
+                }
+                quote!(#accounts::check_key(&#data.#field, #account.key))
+            }
+        };
+        let check = match &constraint.error {
+            Some(error) => quote! {
+                #check.map_err(|_| -> ::arch_program::program_error::ProgramError {
+                    ::core::convert::Into::into(#error)
+                })?
+            },
+            None => quote!(#check?),
+        };
+        match &constraint.check {
+            Check::Data(_) if has_one => checks.push(quote!(let #data = #check;)),
+            Check::HasOne { .. } => key_checks.push(quote!(#check;)),
+            _ => checks.push(quote!(#check;)),
+        }
+    }
+
+    Ok(quote!(#(#checks)* #(#key_checks)*))
+}
@@ -0,0 +1,106 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Expr, ExprLit, Fields, Lit, LitStr};
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "CustomError cannot be derived for generic types",
+        ));
+    }
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "CustomError can only be derived for enums",
+            ))
+        }
+    };
+
+    let mut variants = Vec::new();
+    let mut codes = Vec::new();
+    let mut messages = Vec::new();
+    let mut next_code = 0u32;
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "CustomError variants cannot have fields",
+            ));
+        }
+        // codes follow the rules of enum discriminants
+        let code = match &variant.discriminant {
+            Some((
+                _,
+                Expr::Lit(ExprLit {
+                    lit: Lit::Int(int), ..
+                }),
+            )) => int.base10_parse::<u32>()?,
+            Some((_, expr)) => {
+                return Err(syn::Error::new_spanned(
+                    expr,
+                    "error codes must be integer literals",
+                ))
+            }
+            None => next_code,
+        };
+        if let Some(i) = codes.iter().position(|c| *c == code) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!("error code {} is already used by `{}`", code, variants[i]),
+            ));
+        }
+        next_code = code.wrapping_add(1);
+
+        let mut message = None;
+        for attr in variant.attrs.iter().filter(|a| a.path().is_ident("msg")) {
+            message = Some(attr.parse_args::<LitStr>()?.value());
+        }
+        variants.push(&variant.ident);
+        codes.push(code);
+        messages.push(message.unwrap_or_else(|| variant.ident.to_string()));
+    }
+    let names: Vec<String> = variants.iter().map(|v| v.to_string()).collect();
+
+    Ok(quote! {
+        impl ::arch_program::program_error::CustomError for #ident {
+            const ERRORS: &'static [(u32, &'static str, &'static str)] = &[
+                #((#codes, #names, #messages)),*
+            ];
+
+            fn code(&self) -> u32 {
+                match self {
+                    #(Self::#variants => #codes,)*
+                }
+            }
+
+            fn from_code(code: u32) -> Option<Self> {
+                match code {
+                    #(#codes => Some(Self::#variants),)*
+                    _ => None,
+                }
+            }
+
+            fn message(&self) -> &'static str {
+                match self {
+                    #(Self::#variants => #messages,)*
+                }
+            }
+        }
+
+        impl From<#ident> for ::arch_program::program_error::ProgramError {
+            fn from(error: #ident) -> Self {
+                Self::Custom(::arch_program::program_error::CustomError::code(&error))
+            }
+        }
+
+        impl ::core::fmt::Display for #ident {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(::arch_program::program_error::CustomError::message(self))
+            }
+        }
+    })
+}
@@ -0,0 +1,30 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, LitStr};
+
+use crate::account_state::{discriminator, impl_discriminator_bytes};
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // `#[event(name = "..")]` keeps the discriminator of a renamed event
+    let mut name = ident.to_string();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("event")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("expected `name`"))
+            }
+        })?;
+    }
+    let discriminator = impl_discriminator_bytes(input, &discriminator(&format!("event:{}", name)));
+
+    Ok(quote! {
+        #discriminator
+
+        impl #impl_generics ::arch_program::event::Event for #ident #ty_generics #where_clause {}
+    })
+}
@@ -0,0 +1,112 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields};
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "IdlBuild cannot be derived for generic types",
+        ));
+    }
+
+    let ident = &input.ident;
+    let name = ident.to_string();
+
+    let kind = match &input.data {
+        Data::Struct(data) => {
+            let fields = idl_fields(&data.fields);
+            quote!(::arch_program::idl::IdlTypeDefKind::Struct(#fields))
+        }
+        Data::Enum(data) => {
+            let variants = data.variants.iter().map(|variant| {
+                let name = variant.ident.to_string();
+                let fields = idl_fields(&variant.fields);
+                quote! {
+                    ::arch_program::idl::IdlEnumVariant {
+                        name: ::arch_program::__private::String::from(#name),
+                        fields: #fields,
+                    }
+                }
+            });
+            quote!(::arch_program::idl::IdlTypeDefKind::Enum(
+                ::arch_program::__private::vec![#(#variants),*]
+            ))
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "IdlBuild cannot be derived for unions",
+            ))
+        }
+    };
+    let field_type_defs = idl_field_type_defs(&input.data);
+
+    Ok(quote! {
+        impl ::arch_program::idl::IdlBuild for #ident {
+            fn idl_type() -> ::arch_program::idl::IdlType {
+                ::arch_program::idl::IdlType::Defined(::arch_program::__private::String::from(#name))
+            }
+
+            fn idl_type_defs(defs: &mut ::arch_program::__private::Vec<::arch_program::idl::IdlTypeDef>) {
+                if defs.iter().any(|def| def.name == #name) {
+                    return;
+                }
+                defs.push(::arch_program::idl::IdlTypeDef {
+                    name: ::arch_program::__private::String::from(#name),
+                    kind: #kind,
+                });
+                #(#field_type_defs)*
+            }
+        }
+    })
+}
+
+/// `arch_program::idl::IdlFields` expression describing `fields`
+fn idl_fields(fields: &Fields) -> TokenStream {
+    match fields {
+        Fields::Unit => quote!(::arch_program::idl::IdlFields::Unit),
+        Fields::Named(named) => {
+            let fields = named.named.iter().map(|field| {
+                let name = field.ident.as_ref().unwrap().to_string();
+                let ty = &field.ty;
+                quote! {
+                    ::arch_program::idl::IdlField {
+                        name: ::arch_program::__private::String::from(#name),
+                        ty: <#ty as ::arch_program::idl::IdlBuild>::idl_type(),
+                    }
+                }
+            });
+            quote!(::arch_program::idl::IdlFields::Named(
+                ::arch_program::__private::vec![#(#fields),*]
+            ))
+        }
+        Fields::Unnamed(unnamed) => {
+            let types = unnamed.unnamed.iter().map(|field| {
+                let ty = &field.ty;
+                quote!(<#ty as ::arch_program::idl::IdlBuild>::idl_type())
+            });
+            quote!(::arch_program::idl::IdlFields::Tuple(
+                ::arch_program::__private::vec![#(#types),*]
+            ))
+        }
+    }
+}
+
+/// Statements adding the type definitions of every field type to `defs`
+fn idl_field_type_defs(data: &Data) -> Vec<TokenStream> {
+    let fields: Vec<&Fields> = match data {
+        Data::Struct(data) => vec![&data.fields],
+        Data::Enum(data) => data.variants.iter().map(|v| &v.fields).collect(),
+        Data::Union(_) => vec![],
+    };
+
+    fields
+        .into_iter()
+        .flat_map(|fields| fields.iter())
+        .map(|field| {
+            let ty = &field.ty;
+            quote!(<#ty as ::arch_program::idl::IdlBuild>::idl_type_defs(defs);)
+        })
+        .collect()
+}
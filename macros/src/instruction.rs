@@ -0,0 +1,399 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    Data, DeriveInput, Expr, ExprLit, Fields, GenericArgument, Ident, Lit, Path, PathArguments,
+    Type, Variant,
+};
+
+struct InstructionVariant<'a> {
+    variant: &'a Variant,
+    discriminator: Vec<u8>,
+    accounts: Option<Path>,
+    handler: Option<Path>,
+}
+
+impl<'a> InstructionVariant<'a> {
+    fn parse(variant: &'a Variant) -> syn::Result<Self> {
+        let mut discriminator = None;
+        let mut accounts = None;
+        let mut handler = None;
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("instruction"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("discriminator") {
+                    discriminator = Some(parse_discriminator(&meta.value()?.parse()?)?);
+                } else if meta.path.is_ident("accounts") {
+                    accounts = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("handler") {
+                    handler = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected `discriminator`, `accounts` or `handler`"));
+                }
+                Ok(())
+            })?;
+        }
+        let discriminator = discriminator.unwrap_or_else(|| {
+            let digest = sha256::digest(format!("instruction:{}", variant.ident));
+            (0..8)
+                .map(|i| u8::from_str_radix(&digest[2 * i..2 * i + 2], 16).unwrap())
+                .collect()
+        });
+
+        Ok(Self {
+            variant,
+            discriminator,
+            accounts,
+            handler,
+        })
+    }
+
+    /// Pattern binding the fields of the variant to `__<field>` and the
+    /// bindings in declaration order
+    fn pattern(&self) -> (TokenStream, Vec<Ident>) {
+        let ident = &self.variant.ident;
+        match &self.variant.fields {
+            Fields::Unit => (quote!(Self::#ident), vec![]),
+            Fields::Unnamed(fields) => {
+                let bindings: Vec<Ident> = (0..fields.unnamed.len())
+                    .map(|i| format_ident!("__{}", i))
+                    .collect();
+                (quote!(Self::#ident(#(#bindings),*)), bindings)
+            }
+            Fields::Named(fields) => {
+                let names: Vec<&Ident> = fields
+                    .named
+                    .iter()
+                    .map(|f| f.ident.as_ref().unwrap())
+                    .collect();
+                let bindings: Vec<Ident> = names.iter().map(|n| format_ident!("__{}", n)).collect();
+                (quote!(Self::#ident { #(#names: #bindings),* }), bindings)
+            }
+        }
+    }
+
+    /// Names and types of the variant fields as builder arguments: the field
+    /// names, `args` for a single unnamed field or `arg<i>` for several
+    fn args(&self) -> Vec<(Ident, &'a Type)> {
+        match &self.variant.fields {
+            Fields::Unit => vec![],
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                vec![(format_ident!("args"), &fields.unnamed[0].ty)]
+            }
+            Fields::Unnamed(fields) => fields
+                .unnamed
+                .iter()
+                .enumerate()
+                .map(|(i, field)| (format_ident!("arg{}", i), &field.ty))
+                .collect(),
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(|field| (field.ident.clone().unwrap(), &field.ty))
+                .collect(),
+        }
+    }
+}
+
+/// Whether the enum has the `#[instruction(idl)]` attribute
+fn parse_idl(input: &DeriveInput) -> syn::Result<bool> {
+    let mut idl = false;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("instruction"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("idl") {
+                idl = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `idl`"))
+            }
+        })?;
+    }
+    Ok(idl)
+}
+
+/// `discriminator = 3` or `discriminator = [1, 2]`
+fn parse_discriminator(expr: &Expr) -> syn::Result<Vec<u8>> {
+    let byte = |expr: &Expr| match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse::<u8>(),
+        _ => Err(syn::Error::new_spanned(expr, "expected a byte")),
+    };
+    let discriminator = match expr {
+        Expr::Array(array) => array.elems.iter().map(byte).collect::<syn::Result<_>>()?,
+        _ => vec![byte(expr)?],
+    };
+    if discriminator.is_empty() {
+        return Err(syn::Error::new_spanned(
+            expr,
+            "discriminator cannot be empty",
+        ));
+    }
+    Ok(discriminator)
+}
+
+/// `path` with the lifetime argument `lifetime` when it has no arguments
+fn with_lifetime(path: &Path, lifetime: TokenStream) -> Path {
+    let mut path = path.clone();
+    let last = path.segments.last_mut().unwrap();
+    if let PathArguments::None = last.arguments {
+        let lifetime: GenericArgument = syn::parse2(lifetime).unwrap();
+        last.arguments = PathArguments::AngleBracketed(syn::parse_quote!(<#lifetime>));
+    }
+    path
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "ProgramInstruction cannot be derived for generic types",
+        ));
+    }
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "ProgramInstruction can only be derived for enums",
+            ))
+        }
+    };
+
+    let variants = data
+        .variants
+        .iter()
+        .map(InstructionVariant::parse)
+        .collect::<syn::Result<Vec<_>>>()?;
+    for (i, a) in variants.iter().enumerate() {
+        for b in &variants[..i] {
+            if a.discriminator.starts_with(&b.discriminator)
+                || b.discriminator.starts_with(&a.discriminator)
+            {
+                return Err(syn::Error::new_spanned(
+                    &a.variant.ident,
+                    format!(
+                        "discriminator {:?} of `{}` clashes with {:?} of `{}`",
+                        a.discriminator, a.variant.ident, b.discriminator, b.variant.ident
+                    ),
+                ));
+            }
+        }
+    }
+
+    let program_error = quote!(::arch_program::program_error::ProgramError);
+    let program_instruction = quote!(::arch_program::program_instruction);
+
+    let discriminators: Vec<TokenStream> = variants
+        .iter()
+        .map(|v| {
+            let bytes = &v.discriminator;
+            quote!(&[#(#bytes),*])
+        })
+        .collect();
+    let patterns: Vec<(TokenStream, Vec<Ident>)> = variants.iter().map(|v| v.pattern()).collect();
+    let wildcards = variants.iter().map(|v| {
+        let ident = &v.variant.ident;
+        match &v.variant.fields {
+            Fields::Unit => quote!(Self::#ident),
+            Fields::Unnamed(_) => quote!(Self::#ident(..)),
+            Fields::Named(_) => quote!(Self::#ident { .. }),
+        }
+    });
+    let unpack =
+        patterns
+            .iter()
+            .zip(&discriminators)
+            .map(|((pattern, bindings), discriminator)| {
+                let decode = if bindings.is_empty() {
+                    quote!()
+                } else {
+                    quote! {
+                        let mut __data = __data;
+                        #(let #bindings = #program_instruction::decode_field(&mut __data)?;)*
+                    }
+                };
+                quote! {
+                    if let Some(__data) = instruction_data.strip_prefix(#discriminator as &[u8]) {
+                        #decode
+                        if !__data.is_empty() {
+                            return Err(#program_error::InvalidInstructionData);
+                        }
+                        return Ok(#pattern);
+                    }
+                }
+            });
+    let pack = patterns.iter().map(|(pattern, bindings)| {
+        quote! {
+            #pattern => {
+                #(#program_instruction::encode_field(#bindings, &mut __data);)*
+            }
+        }
+    });
+
+    let process = if variants.iter().all(|v| v.handler.is_some()) {
+        let arms = variants.iter().zip(&patterns).map(|(v, (pattern, bindings))| {
+            let handler = v.handler.as_ref().unwrap();
+            match &v.accounts {
+                Some(accounts) => {
+                    let accounts = with_lifetime(accounts, quote!('a));
+                    quote! {
+                        #pattern => {
+                            let __accounts = <#accounts as ::arch_program::accounts::Accounts<'a>>::try_accounts(program_id, accounts)?;
+                            #handler(&__accounts #(, #bindings)*)
+                        }
+                    }
+                }
+                None => quote!(#pattern => #handler(program_id, accounts #(, #bindings)*),),
+            }
+        });
+        quote! {
+            /// Decodes the instruction, validates its accounts and calls the
+            /// handler of the variant
+            pub fn process<'a>(
+                program_id: &::arch_program::pubkey::Pubkey,
+                accounts: &[::arch_program::account::AccountInfo<'a>],
+                instruction_data: &[u8],
+            ) -> Result<(), #program_error> {
+                match <Self as #program_instruction::ProgramInstruction>::unpack(instruction_data)? {
+                    #(#arms)*
+                }
+            }
+        }
+    } else if let Some(v) = variants.iter().find(|v| v.handler.is_some()) {
+        return Err(syn::Error::new_spanned(
+            &v.variant.ident,
+            "dispatching needs a `handler` on every variant",
+        ));
+    } else {
+        quote!()
+    };
+
+    let builders = variants.iter().map(|v| {
+        let ident = &v.variant.ident;
+        let name = format_ident!("new_{}_instruction", snake_case(&ident.to_string()));
+        let (accounts_ty, account_metas) = match &v.accounts {
+            Some(accounts) => {
+                let accounts = with_lifetime(accounts, quote!('static));
+                (
+                    quote!(<#accounts as ::arch_program::accounts::Accounts<'static>>::Keys),
+                    quote!(::arch_program::accounts::ToAccountMetas::to_account_metas(
+                        &accounts
+                    )),
+                )
+            }
+            None => (
+                quote!(::arch_program::__private::Vec<::arch_program::account::AccountMeta>),
+                quote!(accounts),
+            ),
+        };
+        let args = v.args();
+        let names = args.iter().map(|(name, _)| name);
+        let instruction = match &v.variant.fields {
+            Fields::Unit => quote!(Self::#ident),
+            Fields::Unnamed(_) => quote!(Self::#ident(#(#names),*)),
+            Fields::Named(_) => quote!(Self::#ident { #(#names),* }),
+        };
+        let args = args.iter().map(|(name, ty)| quote!(#name: #ty));
+        let doc = format!("Instruction invoking `{}`", ident);
+        quote! {
+            #[doc = #doc]
+            #[allow(clippy::too_many_arguments)]
+            pub fn #name(
+                program_id: ::arch_program::pubkey::Pubkey,
+                accounts: #accounts_ty,
+                #(#args),*
+            ) -> ::arch_program::instruction::Instruction {
+                ::arch_program::instruction::Instruction {
+                    program_id,
+                    accounts: #account_metas,
+                    data: #program_instruction::ProgramInstruction::pack(&#instruction),
+                }
+            }
+        }
+    });
+
+    let idl = if parse_idl(input)? {
+        let instructions = variants.iter().map(|v| {
+            let name = snake_case(&v.variant.ident.to_string());
+            let bytes = &v.discriminator;
+            let accounts = v.accounts.as_ref().map(|accounts| {
+                let accounts = with_lifetime(accounts, quote!('static));
+                quote!(.accounts::<#accounts>())
+            });
+            let args = v.args().into_iter().map(|(name, ty)| {
+                let name = name.to_string();
+                quote!(.arg::<#ty>(#name))
+            });
+            quote! {
+                ::arch_program::idl::IdlInstruction::new(#name, &[#(#bytes),*])
+                    #accounts
+                    #(#args)*
+            }
+        });
+        quote! {
+            impl ::arch_program::idl::IdlInstructions for #ident {
+                fn idl_instructions() -> ::arch_program::__private::Vec<::arch_program::idl::IdlInstruction> {
+                    ::arch_program::__private::vec![#(#instructions),*]
+                }
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    Ok(quote! {
+        impl #program_instruction::ProgramInstruction for #ident {
+            const DISCRIMINATORS: &'static [&'static [u8]] = &[#(#discriminators),*];
+
+            fn discriminator(&self) -> &'static [u8] {
+                match self {
+                    #(#wildcards => #discriminators,)*
+                }
+            }
+
+            fn unpack(instruction_data: &[u8]) -> Result<Self, #program_error> {
+                #(#unpack)*
+                Err(#program_error::InvalidInstructionData)
+            }
+
+            fn pack(&self) -> ::arch_program::__private::Vec<u8> {
+                let mut __data = self.discriminator().to_vec();
+                match self {
+                    #(#pack)*
+                }
+                __data
+            }
+        }
+
+        impl #ident {
+            #process
+
+            #(#builders)*
+        }
+
+        #idl
+    })
+}
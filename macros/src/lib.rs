@@ -0,0 +1,88 @@
+//! Derive macros for arch_program. Use them through the re-exports in
+//! `arch_program` rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod account_state;
+mod accounts;
+mod custom_error;
+mod event;
+mod idl;
+mod instruction;
+
+/// Implements `arch_program::idl::IdlBuild` for a borsh encoded struct or enum
+#[proc_macro_derive(IdlBuild)]
+pub fn derive_idl_build(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    idl::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `arch_program::accounts::Accounts` for a struct of
+/// `AccountInfo`s, checking the constraints of its `#[account(...)]`
+/// attributes
+#[proc_macro_derive(Accounts, attributes(account))]
+pub fn derive_accounts(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    accounts::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `arch_program::program_instruction::ProgramInstruction` for an
+/// instruction enum, with dispatch and client builders configured by the
+/// `#[instruction(...)]` attribute of each variant, and described in the IDL
+/// with `#[instruction(idl)]` on the enum
+#[proc_macro_derive(ProgramInstruction, attributes(instruction))]
+pub fn derive_program_instruction(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    instruction::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `arch_program::program_error::CustomError` for a fieldless
+/// error enum, converting it into `ProgramError::Custom` with its discriminant
+/// as the code and its `#[msg("...")]` as the message
+#[proc_macro_derive(CustomError, attributes(msg))]
+pub fn derive_custom_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    custom_error::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `arch_program::account_state::AccountState` for a borsh encoded
+/// struct or enum, tagged by the first 8 bytes of `sha256("account:<Type>")`,
+/// and upgraded from the older layout named by
+/// `#[account_state(version = N, migrate_from = Type)]`
+#[proc_macro_derive(AccountState, attributes(account_state))]
+pub fn derive_account_state(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    account_state::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `arch_program::account_state::ZeroCopy` for a `#[repr(C)]`
+/// struct of plain data without padding, mapped in place on account data
+#[proc_macro_derive(ZeroCopy)]
+pub fn derive_zero_copy(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    account_state::derive_zero_copy(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `arch_program::event::Event` for a borsh serializable struct,
+/// logged by `emit!` behind the discriminator `sha256("event:<Name>")[..8]`.
+/// `#[event(name = "..")]` overrides the name hashed.
+#[proc_macro_derive(Event, attributes(event))]
+pub fn derive_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    event::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -1,4 +1,4 @@
-use crate::{msg, pubkey::Pubkey, utxo::UtxoMeta};
+use crate::{pubkey::Pubkey, utxo::UtxoMeta};
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
@@ -15,6 +15,108 @@ pub struct AccountInfo<'a> {
     pub is_executable: bool,
 }
 
+/// Where the realloc bookkeeping of account data is kept, outside of
+/// `AccountInfo` since the runtime reads its `#[repr(C)]` layout on CPI
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum DataOrigin {
+    /// Serialized by the runtime into the program input at the pointer,
+    /// after its original and current u64 lengths
+    Input(*mut u8),
+    /// Buffer of this length passed to `AccountInfo::new`
+    Buffer(usize),
+}
+
+#[cfg(all(not(target_os = "solana"), feature = "std"))]
+std::thread_local! {
+    /// Origin of the account data of the calling thread by address, since
+    /// host memory does not tell the program input apart
+    static DATA_ORIGINS: RefCell<BTreeMap<usize, DataOrigin>> = RefCell::default();
+}
+
+/// Records the origin of the account data at `data` on the host. On chain
+/// the runtime serializes the input at `INPUT_START_ADDRESS`.
+#[cfg_attr(
+    any(target_os = "solana", not(feature = "std")),
+    allow(unused_variables)
+)]
+pub(crate) fn set_data_origin(data: *const u8, origin: DataOrigin) {
+    #[cfg(all(not(target_os = "solana"), feature = "std"))]
+    DATA_ORIGINS.with(|origins| origins.borrow_mut().insert(data as usize, origin));
+}
+
+#[cfg_attr(not(feature = "std"), allow(unused_variables))]
+fn data_origin(data: *const u8) -> Option<DataOrigin> {
+    #[cfg(target_os = "solana")]
+    return (data as u64 >= INPUT_START_ADDRESS)
+        .then_some(DataOrigin::Input(INPUT_START_ADDRESS as *mut u8));
+
+    #[cfg(all(not(target_os = "solana"), feature = "std"))]
+    return DATA_ORIGINS.with(|origins| origins.borrow().get(&(data as usize)).copied());
+
+    #[cfg(all(not(target_os = "solana"), not(feature = "std")))]
+    None
+}
+
+/// Limits on resizing the data of an account
+struct DataBounds {
+    original_len: usize,
+    /// Bytes addressable from the start of the data
+    capacity: usize,
+    /// Net growth of the data of all the accounts of the instruction, for
+    /// data serialized by the runtime, which follows its length
+    allocations: Option<i64>,
+}
+
+impl DataBounds {
+    /// Bounds of the account data at `data`, currently `len` bytes long.
+    /// Data of unknown origin can shrink but not grow.
+    fn of(data: *const u8, len: usize) -> Self {
+        match data_origin(data) {
+            Some(DataOrigin::Input(input)) => {
+                // SAFETY: `input` is the program input the data was
+                // deserialized from
+                if let Some(bounds) = unsafe { Self::serialized(input, data) } {
+                    return bounds;
+                }
+            }
+            Some(DataOrigin::Buffer(buffer_len)) => {
+                return Self {
+                    original_len: buffer_len,
+                    capacity: buffer_len,
+                    allocations: None,
+                }
+            }
+            None => {}
+        }
+        Self {
+            original_len: len,
+            capacity: len,
+            allocations: None,
+        }
+    }
+
+    /// Bounds of the data at `data` if it belongs to an account of `input`,
+    /// with `MAX_PERMITTED_DATA_INCREASE` spare bytes after it
+    unsafe fn serialized(input: *mut u8, data: *const u8) -> Option<Self> {
+        let mut context = InstructionContext::new(input);
+        let mut original_len = None;
+        let mut allocations = 0;
+        while let Ok(account) = context.next_account() {
+            if let MaybeAccount::Account(account) = account {
+                allocations += account.data_len() as i64 - account.original_data_len() as i64;
+                if account.data_ptr() == data {
+                    original_len = Some(account.original_data_len());
+                }
+            }
+        }
+        original_len.map(|original_len| Self {
+            original_len,
+            capacity: original_len + MAX_PERMITTED_DATA_INCREASE,
+            allocations: Some(allocations),
+        })
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 #[repr(C)]
 pub struct AccountMeta {
@@ -43,15 +145,23 @@ impl AccountMeta {
     }
 }
 
+#[cfg(all(not(target_os = "solana"), feature = "std"))]
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
 use core::fmt;
-use std::{
+use core::{
     cell::{Ref, RefCell, RefMut},
     mem::size_of,
-    rc::Rc,
     slice::from_raw_parts_mut,
 };
 
-use crate::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+#[cfg(target_os = "solana")]
+use crate::entrypoint::INPUT_START_ADDRESS;
+use crate::entrypoint::{
+    MAX_PERMITTED_ACCOUNTS_DATA_ALLOCATIONS_PER_INSTRUCTION, MAX_PERMITTED_DATA_INCREASE,
+    MAX_PERMITTED_DATA_LENGTH,
+};
+use crate::lazy_entrypoint::{InstructionContext, MaybeAccount};
 
 use crate::debug_account_data::debug_account_data;
 use crate::program_error::ProgramError;
@@ -75,6 +185,9 @@ impl<'a> fmt::Debug for AccountInfo<'a> {
 }
 
 impl<'a> AccountInfo<'a> {
+    /// Account whose data can shrink and grow back within `data`, but not
+    /// beyond it. On chain, where only the accounts passed in are tracked,
+    /// the data can only shrink.
     pub fn new(
         key: &'a Pubkey,
         data: &'a mut [u8],
@@ -84,6 +197,7 @@ impl<'a> AccountInfo<'a> {
         is_writable: bool,
         is_executable: bool,
     ) -> Self {
+        set_data_origin(data.as_ptr(), DataOrigin::Buffer(data.len()));
         Self {
             key,
             data: Rc::new(RefCell::new(data)),
@@ -115,37 +229,23 @@ impl<'a> AccountInfo<'a> {
             .map_err(|_| ProgramError::AccountBorrowFailed)
     }
 
-    /// Return the utxo's original data length when it was serialized for the
-    /// current program invocation.
-    ///
-    /// # Safety
-    ///
-    /// This method assumes that the original data length was serialized as a u64
-    /// integer in the 1 bytes immediately succeeding is_executable.
-    pub unsafe fn original_data_len(&self) -> usize {
-        let key_ptr = self.key as *const _ as *const u8;
-        let original_data_len_ptr = key_ptr.offset(32) as *const u64;
-        *original_data_len_ptr as usize
+    /// Length of the data when the account was passed to the program
+    pub fn original_data_len(&self) -> usize {
+        let data = self.data.borrow();
+        DataBounds::of(data.as_ptr(), data.len()).original_len
     }
 
-    /// Realloc the account's data and optionally zero-initialize the new
-    /// memory.
-    ///
-    /// Note:  Account data can be increased within a single call by up to
-    /// `solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE` bytes.
+    /// Resizes the account's data.
     ///
-    /// Note: Memory used to grow is already zero-initialized upon program
-    /// entrypoint and re-zeroing it wastes compute units.  If within the same
-    /// call a program reallocs from larger to smaller and back to larger again
-    /// the new space could contain stale data.  Pass `true` for `zero_init` in
-    /// this case, otherwise compute units will be wasted re-zero-initializing.
+    /// The data can grow by up to `MAX_PERMITTED_DATA_INCREASE` bytes over its
+    /// original length and never beyond `MAX_PERMITTED_DATA_LENGTH`, failing
+    /// with `InvalidRealloc`. The accounts of an instruction can grow by
+    /// `MAX_PERMITTED_ACCOUNTS_DATA_ALLOCATIONS_PER_INSTRUCTION` bytes in
+    /// total, failing with `MaxAccountsDataAllocationsExceeded`.
     ///
-    /// # Safety
-    ///
-    /// This method makes assumptions about the layout and location of memory
-    /// referenced by `AccountInfo` fields. It should only be called for
-    /// instances of `AccountInfo` that were created by the runtime and received
-    /// in the `process_instruction` entrypoint of a program.
+    /// Shrinking zeroes the bytes cut off, so that growing back always
+    /// exposes zeroes and `zero_init` is only needed for data the runtime did
+    /// not zero.
     pub fn realloc(&self, new_len: usize, zero_init: bool) -> Result<(), ProgramError> {
         let mut data = self.try_borrow_mut_data()?;
         let old_len = data.len();
@@ -155,36 +255,34 @@ impl<'a> AccountInfo<'a> {
             return Ok(());
         }
 
-        // Return early if the length increase from the original serialized data
-        // length is too large and would result in an out of bounds allocation.
-        let original_data_len = unsafe { self.original_data_len() };
-        msg!(
-            "account realloc {} {} {}",
-            new_len,
-            original_data_len,
-            MAX_PERMITTED_DATA_INCREASE
-        );
-        if new_len.saturating_sub(original_data_len) > MAX_PERMITTED_DATA_INCREASE {
+        let bounds = DataBounds::of(data.as_ptr(), old_len);
+        if new_len > MAX_PERMITTED_DATA_LENGTH || new_len > bounds.capacity {
             return Err(ProgramError::InvalidRealloc);
         }
+        if let Some(allocations) = bounds.allocations {
+            if allocations + new_len as i64 - old_len as i64
+                > MAX_PERMITTED_ACCOUNTS_DATA_ALLOCATIONS_PER_INSTRUCTION
+            {
+                return Err(ProgramError::MaxAccountsDataAllocationsExceeded);
+            }
+        }
+
+        if new_len < old_len {
+            data[new_len..].fill(0);
+        }
 
-        // realloc
+        // SAFETY: `new_len` is within the capacity of the data, and runtime
+        // serialized data is preceded by its u64 length
         unsafe {
             let data_ptr = data.as_mut_ptr();
-
-            // First set new length in the serialized data
-            *(data_ptr.offset(-8) as *mut u64) = new_len as u64;
-
-            // Then recreate the local slice with the new length
+            if bounds.allocations.is_some() {
+                *(data_ptr.offset(-8) as *mut u64) = new_len as u64;
+            }
             *data = from_raw_parts_mut(data_ptr, new_len)
         }
 
-        if zero_init {
-            let len_increase = new_len.saturating_sub(old_len);
-            if len_increase > 0 {
-                let data = &mut data[old_len..];
-                data.fill(0);
-            }
+        if zero_init && new_len > old_len {
+            data[old_len..].fill(0);
         }
 
         Ok(())
@@ -194,7 +292,7 @@ impl<'a> AccountInfo<'a> {
     pub fn set_owner(&self, owner: &Pubkey) {
         // Set the non-mut owner field
         unsafe {
-            std::ptr::write_volatile(
+            core::ptr::write_volatile(
                 self.owner as *const Pubkey as *mut [u8; 32],
                 owner.serialize(),
             );
@@ -205,7 +303,7 @@ impl<'a> AccountInfo<'a> {
     pub fn set_utxo(&self, utxo: &UtxoMeta) {
         // Set the non-mut owner field
         unsafe {
-            std::ptr::write_volatile(
+            core::ptr::write_volatile(
                 self.utxo as *const UtxoMeta as *mut [u8; 36],
                 utxo.serialize(),
             );
@@ -215,10 +313,111 @@ impl<'a> AccountInfo<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{account::AccountMeta, pubkey::Pubkey};
+    use super::*;
+    use crate::entrypoint::{
+        deserialize,
+        test_input::{serialize, TestAccount},
+    };
 
     use proptest::prelude::*;
 
+    fn test_account(data: Vec<u8>) -> TestAccount {
+        TestAccount {
+            key: Pubkey([1; 32]),
+            owner: Pubkey([2; 32]),
+            data,
+            is_signer: false,
+            is_writable: true,
+            is_executable: false,
+        }
+    }
+
+    /// Serialized length of the data of the first account of `input`
+    fn serialized_len(input: &[u64]) -> u64 {
+        // count, then marker, padding, flags and key, then original length
+        input[(8 + 8 + 32 + 8) / 8]
+    }
+
+    #[test]
+    fn test_realloc_runtime_account() {
+        let mut input = serialize(&[test_account(vec![7; 16])], &[], &Pubkey([2; 32]));
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        let account = &accounts[0];
+        assert_eq!(account.original_data_len(), 16);
+
+        account.realloc(4, false).unwrap();
+        account
+            .realloc(16 + MAX_PERMITTED_DATA_INCREASE, false)
+            .unwrap();
+        {
+            let data = account.try_borrow_data().unwrap();
+            assert_eq!(&data[..4], &[7; 4]);
+            assert!(data[4..].iter().all(|b| *b == 0));
+        }
+        assert_eq!(
+            account.realloc(17 + MAX_PERMITTED_DATA_INCREASE, false),
+            Err(ProgramError::InvalidRealloc)
+        );
+        assert_eq!(account.original_data_len(), 16);
+        drop(accounts);
+        assert_eq!(
+            serialized_len(&input),
+            16 + MAX_PERMITTED_DATA_INCREASE as u64
+        );
+    }
+
+    #[test]
+    fn test_realloc_host_account() {
+        let (key, owner, utxo) = (Pubkey([1; 32]), Pubkey([2; 32]), UtxoMeta::from([0; 32], 0));
+        let mut data = [7; 8];
+        let account = AccountInfo::new(&key, &mut data, &owner, &utxo, false, true, false);
+        assert_eq!(account.original_data_len(), 8);
+
+        account.realloc(2, false).unwrap();
+        assert_eq!(account.data_len(), 2);
+        account.realloc(8, false).unwrap();
+        assert_eq!(
+            **account.try_borrow_data().unwrap(),
+            [7, 7, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(account.realloc(9, false), Err(ProgramError::InvalidRealloc));
+    }
+
+    #[test]
+    fn test_realloc_allocations_limit() {
+        // enough accounts growing by the most they can to reach the limit
+        let count = (MAX_PERMITTED_ACCOUNTS_DATA_ALLOCATIONS_PER_INSTRUCTION as usize)
+            .div_ceil(MAX_PERMITTED_DATA_INCREASE);
+        let accounts = vec![test_account(vec![]); count + 1];
+        let mut input = serialize(&accounts, &[], &Pubkey([2; 32]));
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+
+        for account in &accounts[..count] {
+            account.realloc(MAX_PERMITTED_DATA_INCREASE, false).unwrap();
+        }
+        assert_eq!(
+            accounts[count].realloc(1, false),
+            Err(ProgramError::MaxAccountsDataAllocationsExceeded)
+        );
+        assert_eq!(accounts[count].data_len(), 0);
+        accounts[0].realloc(0, false).unwrap();
+        accounts[count].realloc(1, false).unwrap();
+    }
+
+    #[test]
+    fn test_account_info_layout() {
+        use memoffset::offset_of;
+
+        assert_eq!(offset_of!(AccountInfo, key), 0);
+        assert_eq!(offset_of!(AccountInfo, utxo), 8);
+        assert_eq!(offset_of!(AccountInfo, data), 16);
+        assert_eq!(offset_of!(AccountInfo, owner), 24);
+        assert_eq!(offset_of!(AccountInfo, is_signer), 32);
+        assert_eq!(offset_of!(AccountInfo, is_writable), 33);
+        assert_eq!(offset_of!(AccountInfo, is_executable), 34);
+        assert_eq!(size_of::<AccountInfo>(), 40);
+    }
+
     proptest! {
         #[test]
         fn fuzz_serialize_deserialize_account_meta(
@@ -238,5 +437,22 @@ mod tests {
 
             assert_eq!(account_meta, deserialized);
         }
+
+        #[test]
+        fn fuzz_realloc_zeroes_on_shrink(
+            data in prop::collection::vec(1..=u8::MAX, 1..64),
+            lens in prop::collection::vec(0..128usize, 1..8),
+        ) {
+            let mut input = serialize(&[test_account(data.clone())], &[], &Pubkey([2; 32]));
+            let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+            let mut kept = data.len();
+            for len in lens {
+                accounts[0].realloc(len, false).unwrap();
+                kept = kept.min(len);
+                let data_now = accounts[0].try_borrow_data().unwrap();
+                prop_assert_eq!(&data_now[..kept], &data[..kept]);
+                prop_assert!(data_now[kept..].iter().all(|b| *b == 0));
+            }
+        }
     }
 }
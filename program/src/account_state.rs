@@ -0,0 +1,593 @@
+//! Typed account data.
+//!
+//! The data of an account holding a program's state starts with the 8 byte
+//! discriminator of the state type, so that an account of one type is never
+//! decoded as another. The discriminator is the first 8 bytes of
+//! `sha256("account:<Type>")`.
+//!
+//! Two encodings follow the discriminator:
+//!
+//! - [`AccountState`], derived with `#[derive(AccountState)]`, borsh encodes
+//!   the state. [`AccountInfo::init`], [`AccountInfo::load`],
+//!   [`AccountInfo::load_mut`] and [`AccountInfo::save`] read and write it,
+//!   resizing the account to fit. Clients decode it with [`decode_state`].
+//! - [`ZeroCopy`], derived with `#[derive(ZeroCopy)]` on `#[repr(C)]` structs
+//!   of plain data, maps the account data in place, which avoids decoding
+//!   large fixed size states. [`AccountInfo::init_zero_copy`],
+//!   [`AccountInfo::load_zero_copy`] and [`AccountInfo::load_zero_copy_mut`]
+//!   borrow it.
+//!
+//! ```ignore
+//! #[derive(BorshSerialize, BorshDeserialize, AccountState)]
+//! pub struct Counter {
+//!     pub count: u64,
+//! }
+//!
+//! accounts.counter.init(&Counter { count: 0 })?;
+//! let mut counter = accounts.counter.load_mut::<Counter>()?;
+//! counter.count += 1;
+//! counter.save()?;
+//! ```
+//!
+//! # Versioning
+//!
+//! Changing the fields of a state would leave the accounts already holding it
+//! undecodable. Instead, keep the previous layout under another name and
+//! declare the new one as its next version, with a `From` conversion between
+//! them:
+//!
+//! ```ignore
+//! #[derive(BorshSerialize, BorshDeserialize, AccountState)]
+//! #[account_state(name = "Counter")]
+//! pub struct CounterV0 {
+//!     pub count: u64,
+//! }
+//!
+//! #[derive(BorshSerialize, BorshDeserialize, AccountState)]
+//! #[account_state(version = 1, migrate_from = CounterV0)]
+//! pub struct Counter {
+//!     pub count: u64,
+//!     pub last_caller: Pubkey,
+//! }
+//!
+//! impl From<CounterV0> for Counter { .. }
+//! ```
+//!
+//! Each version has its own discriminator, `sha256("account:<name>:v<N>")`
+//! from version 1 on, `name` defaulting to the type name. Loading an account
+//! of an older version upgrades it through each following version, and
+//! saving it writes the current one, so accounts migrate on their first
+//! write. [`decode_state`] upgrades the same way for clients.
+
+use core::cell::{Ref, RefMut};
+use core::mem::{align_of, size_of};
+use core::ops::{Deref, DerefMut};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::account::AccountInfo;
+use crate::prelude::*;
+use crate::program_error::ProgramError;
+use crate::pubkey::Pubkey;
+
+pub use arch_program_macros::{AccountState, ZeroCopy};
+
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+/// Tags the data of accounts holding a state type
+pub trait Discriminator {
+    const DISCRIMINATOR: [u8; DISCRIMINATOR_LEN];
+}
+
+/// Borsh encoded account state. Derive it with `#[derive(AccountState)]`.
+pub trait AccountState: Discriminator + BorshSerialize + BorshDeserialize {
+    /// Layout version, raised by each migration
+    const VERSION: u32 = 0;
+
+    /// Version of account data holding this state or one it migrates from
+    fn version_of(data: &[u8]) -> Option<u32> {
+        data.starts_with(&Self::DISCRIMINATOR)
+            .then_some(Self::VERSION)
+    }
+
+    /// Decodes account data of this version or an older one, upgrading it
+    fn upgrade(data: &[u8]) -> Result<Self, ProgramError> {
+        decode_current(data)
+    }
+}
+
+/// Types valid for any bit pattern and without padding, which can be read
+/// from and written to raw bytes
+///
+/// # Safety
+///
+/// Implement it through `#[derive(ZeroCopy)]`, which checks these properties.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, Pubkey);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// Account state mapped in place. Derive it with `#[derive(ZeroCopy)]`.
+pub trait ZeroCopy: Discriminator + Pod {}
+
+/// Checks the discriminator of initialized account data
+fn check_discriminator<T: Discriminator>(data: &[u8]) -> Result<(), ProgramError> {
+    if data.is_empty() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if data.len() < DISCRIMINATOR_LEN || data[..DISCRIMINATOR_LEN] != T::DISCRIMINATOR {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Decodes account data holding any version of `T`, as read by clients
+pub fn decode_state<T: AccountState>(data: &[u8]) -> Result<T, ProgramError> {
+    T::upgrade(data)
+}
+
+/// Decodes account data holding the current version of `T` only
+pub fn decode_current<T: AccountState>(data: &[u8]) -> Result<T, ProgramError> {
+    check_discriminator::<T>(data)?;
+    T::deserialize(&mut &data[DISCRIMINATOR_LEN..]).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// `T` mapped on the bytes following the discriminator
+fn cast_mut<T: ZeroCopy>(data: &mut [u8]) -> Result<&mut T, ProgramError> {
+    let state = &mut data[DISCRIMINATOR_LEN..];
+    if state.len() < size_of::<T>() {
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    if state.as_ptr().align_offset(align_of::<T>()) != 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    // SAFETY: the bytes are in bounds and aligned, and any bit pattern is a
+    // valid `T`
+    Ok(unsafe { &mut *(state.as_mut_ptr() as *mut T) })
+}
+
+fn cast<T: ZeroCopy>(data: &[u8]) -> Result<&T, ProgramError> {
+    let state = &data[DISCRIMINATOR_LEN..];
+    if state.len() < size_of::<T>() {
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    if state.as_ptr().align_offset(align_of::<T>()) != 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    // SAFETY: as in `cast_mut`
+    Ok(unsafe { &*(state.as_ptr() as *const T) })
+}
+
+/// State loaded by [`AccountInfo::load_mut`], written back by [`Self::save`]
+pub struct StateMut<'b, 'a, T: AccountState> {
+    account: &'b AccountInfo<'a>,
+    state: T,
+}
+
+impl<'b, 'a, T: AccountState> StateMut<'b, 'a, T> {
+    pub fn save(self) -> Result<(), ProgramError> {
+        self.account.save(&self.state)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.state
+    }
+}
+
+impl<'b, 'a, T: AccountState> Deref for StateMut<'b, 'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.state
+    }
+}
+
+impl<'b, 'a, T: AccountState> DerefMut for StateMut<'b, 'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.state
+    }
+}
+
+impl<'a> AccountInfo<'a> {
+    /// Decodes the state of the account, upgrading older versions
+    ///
+    /// Fails with `UninitializedAccount` on an empty account and with
+    /// `InvalidAccountData` if it holds another type.
+    pub fn load<T: AccountState>(&self) -> Result<T, ProgramError> {
+        decode_state(&self.try_borrow_data()?)
+    }
+
+    /// Decodes the state of a writable account, to be saved once modified
+    pub fn load_mut<T: AccountState>(&self) -> Result<StateMut<'_, 'a, T>, ProgramError> {
+        if !self.is_writable {
+            return Err(ProgramError::Immutable);
+        }
+        Ok(StateMut {
+            account: self,
+            state: self.load()?,
+        })
+    }
+
+    /// Writes the initial state of an empty account
+    pub fn init<T: AccountState>(&self, state: &T) -> Result<(), ProgramError> {
+        if !self.data_is_empty() {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        self.save(state)
+    }
+
+    /// Rewrites the state of the account in the current version of `T`, and
+    /// tells whether it was an older one
+    pub fn migrate<T: AccountState>(&self) -> Result<bool, ProgramError> {
+        if T::version_of(&self.try_borrow_data()?) == Some(T::VERSION) {
+            return Ok(false);
+        }
+        self.save(&self.load::<T>()?)?;
+        Ok(true)
+    }
+
+    /// Replaces the state of the account, resizing it to the new encoding
+    pub fn save<T: AccountState>(&self, state: &T) -> Result<(), ProgramError> {
+        if !self.is_writable {
+            return Err(ProgramError::Immutable);
+        }
+        let mut serialized = T::DISCRIMINATOR.to_vec();
+        state
+            .serialize(&mut serialized)
+            .map_err(|e| ProgramError::BorshIoError(e.to_string()))?;
+
+        self.realloc(serialized.len(), false)?;
+        self.try_borrow_mut_data()?.copy_from_slice(&serialized);
+        Ok(())
+    }
+
+    /// Sizes an empty account for `T`, writes its discriminator and returns
+    /// the zeroed state
+    pub fn init_zero_copy<T: ZeroCopy>(&self) -> Result<RefMut<'_, T>, ProgramError> {
+        if !self.is_writable {
+            return Err(ProgramError::Immutable);
+        }
+        if !self.data_is_empty() {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        self.realloc(DISCRIMINATOR_LEN + size_of::<T>(), true)?;
+
+        let mut data = self.try_borrow_mut_data()?;
+        data[..DISCRIMINATOR_LEN].copy_from_slice(&T::DISCRIMINATOR);
+        cast_mut::<T>(&mut data)?;
+        Ok(RefMut::map(data, |data| cast_mut::<T>(data).unwrap()))
+    }
+
+    pub fn load_zero_copy<T: ZeroCopy>(&self) -> Result<Ref<'_, T>, ProgramError> {
+        let data = self.try_borrow_data()?;
+        check_discriminator::<T>(&data)?;
+        cast::<T>(&data)?;
+        Ok(Ref::map(data, |data| cast::<T>(data).unwrap()))
+    }
+
+    pub fn load_zero_copy_mut<T: ZeroCopy>(&self) -> Result<RefMut<'_, T>, ProgramError> {
+        if !self.is_writable {
+            return Err(ProgramError::Immutable);
+        }
+        let mut data = self.try_borrow_mut_data()?;
+        check_discriminator::<T>(&data)?;
+        cast_mut::<T>(&mut data)?;
+        Ok(RefMut::map(data, |data| cast_mut::<T>(data).unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entrypoint::{
+        deserialize,
+        test_input::{serialize, TestAccount},
+    };
+    use proptest::prelude::*;
+
+    #[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize, AccountState)]
+    struct Mint {
+        supply: u64,
+        ticker: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, BorshSerialize, BorshDeserialize, AccountState)]
+    struct Balance {
+        amount: u64,
+    }
+
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize, AccountState)]
+    #[account_state(name = "Counter")]
+    struct CounterV0 {
+        count: u32,
+    }
+
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize, AccountState)]
+    #[account_state(name = "Counter", version = 1, migrate_from = CounterV0)]
+    struct CounterV1 {
+        count: u64,
+    }
+
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize, AccountState)]
+    #[account_state(version = 2, migrate_from = CounterV1)]
+    struct Counter {
+        count: u64,
+        last_caller: Option<Pubkey>,
+    }
+
+    impl From<CounterV0> for CounterV1 {
+        fn from(v0: CounterV0) -> Self {
+            Self {
+                count: v0.count.into(),
+            }
+        }
+    }
+
+    impl From<CounterV1> for Counter {
+        fn from(v1: CounterV1) -> Self {
+            Self {
+                count: v1.count,
+                last_caller: None,
+            }
+        }
+    }
+
+    fn state_data<T: AccountState>(state: &T) -> Vec<u8> {
+        [&T::DISCRIMINATOR[..], &borsh::to_vec(state).unwrap()].concat()
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, ZeroCopy)]
+    #[repr(C)]
+    struct Book {
+        owner: Pubkey,
+        prices: [u64; 16],
+        count: u32,
+        flags: [u8; 4],
+    }
+
+    /// Runs `f` on an account laid out as by the runtime
+    fn with_account<R>(data: Vec<u8>, is_writable: bool, f: impl FnOnce(&AccountInfo) -> R) -> R {
+        let account = TestAccount {
+            key: Pubkey([1; 32]),
+            owner: Pubkey([2; 32]),
+            data,
+            is_signer: false,
+            is_writable,
+            is_executable: false,
+        };
+        let mut input = serialize(&[account], &[], &Pubkey([2; 32]));
+        let (_, accounts, _) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+        f(&accounts[0])
+    }
+
+    fn mint() -> Mint {
+        Mint {
+            supply: 21,
+            ticker: "ARCH".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_discriminator() {
+        let digest = sha256::digest("account:Mint");
+        assert_eq!(
+            Mint::DISCRIMINATOR.to_vec(),
+            hex::decode(&digest[..16]).unwrap()
+        );
+        assert_ne!(Mint::DISCRIMINATOR, Balance::DISCRIMINATOR);
+    }
+
+    #[test]
+    fn test_init_load_save() {
+        with_account(vec![], true, |account| {
+            account.init(&mint()).unwrap();
+            assert_eq!(account.data_len(), DISCRIMINATOR_LEN + 8 + 4 + 4);
+            assert_eq!(account.load::<Mint>(), Ok(mint()));
+            assert_eq!(
+                account.init(&mint()),
+                Err(ProgramError::AccountAlreadyInitialized)
+            );
+
+            let mut state = account.load_mut::<Mint>().unwrap();
+            state.ticker = "BITCOIN".to_string();
+            state.save().unwrap();
+            assert_eq!(account.data_len(), DISCRIMINATOR_LEN + 8 + 4 + 7);
+            assert_eq!(account.load::<Mint>().unwrap().ticker, "BITCOIN");
+
+            account.save(&mint()).unwrap();
+            assert_eq!(account.data_len(), DISCRIMINATOR_LEN + 8 + 4 + 4);
+            assert_eq!(account.load::<Mint>(), Ok(mint()));
+        });
+    }
+
+    #[test]
+    fn test_load_errors() {
+        with_account(vec![], true, |account| {
+            assert_eq!(
+                account.load::<Mint>(),
+                Err(ProgramError::UninitializedAccount)
+            );
+        });
+        let balance = [&Balance::DISCRIMINATOR[..], &5u64.to_le_bytes()].concat();
+        with_account(balance.clone(), true, |account| {
+            assert_eq!(account.load::<Balance>(), Ok(Balance { amount: 5 }));
+            assert_eq!(
+                account.load::<Mint>(),
+                Err(ProgramError::InvalidAccountData)
+            );
+        });
+        with_account(balance[..12].to_vec(), true, |account| {
+            assert_eq!(
+                account.load::<Balance>(),
+                Err(ProgramError::InvalidAccountData)
+            );
+        });
+        with_account(balance, false, |account| {
+            assert!(account.load::<Balance>().is_ok());
+            assert_eq!(
+                account.load_mut::<Balance>().err(),
+                Some(ProgramError::Immutable)
+            );
+            assert_eq!(
+                account.save(&Balance { amount: 1 }),
+                Err(ProgramError::Immutable)
+            );
+        });
+    }
+
+    #[test]
+    fn test_zero_copy() {
+        with_account(vec![], true, |account| {
+            {
+                let mut book = account.init_zero_copy::<Book>().unwrap();
+                assert_eq!(book.count, 0);
+                book.prices[3] = 42;
+                book.count = 1;
+            }
+            assert_eq!(account.data_len(), DISCRIMINATOR_LEN + size_of::<Book>());
+            assert_eq!(account.load_zero_copy::<Book>().unwrap().prices[3], 42);
+
+            account.load_zero_copy_mut::<Book>().unwrap().owner = Pubkey([7; 32]);
+            let book = account.load_zero_copy::<Book>().unwrap();
+            assert_eq!((book.owner, book.count), (Pubkey([7; 32]), 1));
+            drop(book);
+
+            assert_eq!(
+                account.init_zero_copy::<Book>().err(),
+                Some(ProgramError::AccountAlreadyInitialized)
+            );
+            assert_eq!(
+                account.load::<Mint>().err(),
+                Some(ProgramError::InvalidAccountData)
+            );
+        });
+        let short = [&Book::DISCRIMINATOR[..], &[0; 8]].concat();
+        with_account(short, true, |account| {
+            assert_eq!(
+                account.load_zero_copy::<Book>().err(),
+                Some(ProgramError::AccountDataTooSmall)
+            );
+        });
+    }
+
+    #[test]
+    fn test_version_discriminators() {
+        let digest = |preimage: &str| hex::decode(&sha256::digest(preimage)[..16]).unwrap();
+        assert_eq!(CounterV0::DISCRIMINATOR.to_vec(), digest("account:Counter"));
+        assert_eq!(
+            CounterV1::DISCRIMINATOR.to_vec(),
+            digest("account:Counter:v1")
+        );
+        assert_eq!(
+            Counter::DISCRIMINATOR.to_vec(),
+            digest("account:Counter:v2")
+        );
+        assert_eq!((CounterV0::VERSION, Counter::VERSION), (0, 2));
+
+        let v0 = state_data(&CounterV0 { count: 1 });
+        let v1 = state_data(&CounterV1 { count: 1 });
+        assert_eq!(Counter::version_of(&v0), Some(0));
+        assert_eq!(Counter::version_of(&v1), Some(1));
+        assert_eq!(
+            CounterV1::version_of(&state_data(&Counter::from(CounterV1 { count: 1 }))),
+            None
+        );
+        assert_eq!(Counter::version_of(&state_data(&mint())), None);
+    }
+
+    #[test]
+    fn test_decode_old_versions() {
+        let expected = Counter {
+            count: 7,
+            last_caller: None,
+        };
+        for data in [
+            state_data(&CounterV0 { count: 7 }),
+            state_data(&CounterV1 { count: 7 }),
+            state_data(&expected),
+        ] {
+            assert_eq!(decode_state::<Counter>(&data).as_ref(), Ok(&expected));
+        }
+        assert_eq!(
+            decode_current::<Counter>(&state_data(&CounterV1 { count: 7 })),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            decode_state::<Counter>(&[]),
+            Err(ProgramError::UninitializedAccount)
+        );
+        assert_eq!(
+            decode_state::<Counter>(&state_data(&mint())),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn test_migrate_on_write() {
+        let v0 = state_data(&CounterV0 { count: 3 });
+        with_account(v0.clone(), true, |account| {
+            // loading leaves the old version in place
+            let mut counter = account.load_mut::<Counter>().unwrap();
+            assert_eq!(*account.try_borrow_data().unwrap(), &v0[..]);
+
+            counter.last_caller = Some(Pubkey([4; 32]));
+            counter.save().unwrap();
+            assert_eq!(
+                Counter::version_of(&account.try_borrow_data().unwrap()),
+                Some(2)
+            );
+            assert_eq!(account.data_len(), DISCRIMINATOR_LEN + 8 + 33);
+            assert_eq!(
+                account.load::<Counter>(),
+                Ok(Counter {
+                    count: 3,
+                    last_caller: Some(Pubkey([4; 32]))
+                })
+            );
+        });
+        with_account(v0.clone(), true, |account| {
+            assert_eq!(account.migrate::<Counter>(), Ok(true));
+            assert_eq!(account.migrate::<Counter>(), Ok(false));
+            assert_eq!(account.data_len(), DISCRIMINATOR_LEN + 8 + 1);
+        });
+        with_account(v0, false, |account| {
+            assert_eq!(account.migrate::<Counter>(), Err(ProgramError::Immutable));
+        });
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_load_never_panics(data in prop::collection::vec(any::<u8>(), 0..64)) {
+            with_account(data, true, |account| {
+                let _ = account.load::<Mint>();
+                let _ = account.load_zero_copy::<Book>();
+            });
+        }
+
+        #[test]
+        fn fuzz_upgrade(count in any::<u32>()) {
+            let counter = decode_state::<Counter>(&state_data(&CounterV0 { count })).unwrap();
+            prop_assert_eq!(counter.count, count as u64);
+        }
+
+        #[test]
+        fn fuzz_save_load(supply in any::<u64>(), ticker in ".{0,64}", amount in any::<u64>()) {
+            with_account(vec![], true, |account| {
+                let state = Mint { supply, ticker };
+                account.init(&state).unwrap();
+                prop_assert_eq!(account.load::<Mint>(), Ok(state));
+                prop_assert_eq!(account.load::<Balance>(), Err(ProgramError::InvalidAccountData));
+                account.save(&Balance { amount }).unwrap();
+                prop_assert_eq!(account.load::<Balance>(), Ok(Balance { amount }));
+                Ok(())
+            })?;
+        }
+    }
+}
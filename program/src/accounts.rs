@@ -0,0 +1,325 @@
+//! Declarative validation of the accounts passed to an instruction.
+//!
+//! Deriving [`Accounts`] on a struct of [`AccountInfo`]s replaces the
+//! `next_account_info` calls and hand written checks at the top of every
+//! instruction handler. Each field takes the next account, in declaration
+//! order, and the constraints in its `#[account(...)]` attribute are checked
+//! before the struct is returned:
+//!
+//! | constraint               | fails with                                |
+//! |--------------------------|-------------------------------------------|
+//! | missing account          | `ProgramError::NotEnoughAccountKeys`      |
+//! | `signer`                 | `ProgramError::MissingRequiredSignature`  |
+//! | `mut`                    | `ProgramError::Immutable`                 |
+//! | `owner = program`        | `ProgramError::IllegalOwner`              |
+//! | `owner = <expr>`         | `ProgramError::IllegalOwner`              |
+//! | `initialized`            | `ProgramError::UninitializedAccount`      |
+//! | `empty`                  | `ProgramError::AccountAlreadyInitialized` |
+//! | `data = <Type>`          | `ProgramError::InvalidAccountData`        |
+//! | `has_one(field = other)` | `ProgramError::InvalidAccountData`        |
+//!
+//! `data = <Type>` loads the [`AccountState`] of the account, which must be
+//! initialized and start with the discriminator of `Type`.
+//! `has_one(field = other)` then requires `field` of the decoded data to hold
+//! the key of the `other` account, `has_one = other` being short for
+//! `has_one(other = other)`. Any constraint can return a different error with
+//! `@ <expr>`, where the expression converts into a `ProgramError`. Accounts
+//! beyond the declared ones are left to the handler.
+//!
+//! For clients the derive also declares `<Struct>Keys`, holding the key of
+//! each account, whose [`ToAccountMetas`] marks `signer` accounts as signers
+//! and `mut` accounts as writable.
+//!
+//! ```ignore
+//! #[derive(Accounts)]
+//! pub struct MintTokens<'a> {
+//!     #[account(mut, owner = program, data = TokenMintDetails)]
+//!     pub mint: AccountInfo<'a>,
+//!     #[account(mut, owner = program, data = TokenBalance, has_one(mint_account = mint), has_one = owner)]
+//!     pub balance: AccountInfo<'a>,
+//!     #[account(signer)]
+//!     pub owner: AccountInfo<'a>,
+//! }
+//!
+//! let accounts = MintTokens::try_accounts(program_id, accounts)?;
+//! ```
+
+use crate::account::{AccountInfo, AccountMeta};
+use crate::account_state::AccountState;
+use crate::prelude::*;
+use crate::program_error::ProgramError;
+use crate::pubkey::Pubkey;
+
+pub use arch_program_macros::Accounts;
+
+/// A struct of accounts validated on construction. Derive it with
+/// `#[derive(Accounts)]`.
+pub trait Accounts<'a>: Sized {
+    /// Number of accounts taken from the instruction
+    const LEN: usize;
+
+    /// Keys of the accounts, as passed by clients
+    type Keys: ToAccountMetas;
+
+    /// Takes the first [`Self::LEN`] accounts and checks their constraints
+    fn try_accounts(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo<'a>],
+    ) -> Result<Self, ProgramError>;
+}
+
+pub trait ToAccountMetas {
+    fn to_account_metas(&self) -> Vec<AccountMeta>;
+}
+
+pub fn check_signer(account: &AccountInfo) -> Result<(), ProgramError> {
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+pub fn check_writable(account: &AccountInfo) -> Result<(), ProgramError> {
+    if !account.is_writable {
+        return Err(ProgramError::Immutable);
+    }
+    Ok(())
+}
+
+pub fn check_owner(account: &AccountInfo, owner: &Pubkey) -> Result<(), ProgramError> {
+    if account.owner != owner {
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+pub fn check_initialized(account: &AccountInfo) -> Result<(), ProgramError> {
+    if account.data_is_empty() {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(())
+}
+
+/// The account holds no data and is not a program
+pub fn check_empty(account: &AccountInfo) -> Result<(), ProgramError> {
+    if !account.data_is_empty() || account.is_executable {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    Ok(())
+}
+
+/// Loads the state of an initialized account, ignoring trailing bytes
+pub fn load_data<T: AccountState>(account: &AccountInfo) -> Result<T, ProgramError> {
+    account.load()
+}
+
+/// `field` of the decoded account data is `key`
+pub fn check_key<K: AccountKey + ?Sized>(field: &K, key: &Pubkey) -> Result<(), ProgramError> {
+    if field.account_key() != key.serialize() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Account data field holding the key of another account
+pub trait AccountKey {
+    fn account_key(&self) -> [u8; 32];
+}
+
+impl AccountKey for Pubkey {
+    fn account_key(&self) -> [u8; 32] {
+        self.serialize()
+    }
+}
+
+impl AccountKey for [u8; 32] {
+    fn account_key(&self) -> [u8; 32] {
+        *self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account_state::Discriminator;
+    use crate::utxo::UtxoMeta;
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use proptest::prelude::*;
+
+    #[derive(BorshSerialize, BorshDeserialize, AccountState)]
+    struct Balance {
+        owner: Pubkey,
+        mint: [u8; 32],
+        amount: u64,
+    }
+
+    #[derive(Accounts)]
+    struct Transfer<'a> {
+        #[account(signer)]
+        owner: AccountInfo<'a>,
+        #[account(owner = program, initialized)]
+        mint: AccountInfo<'a>,
+        #[account(mut, owner = program, data = Balance, has_one = owner, has_one(mint = mint))]
+        balance: AccountInfo<'a>,
+        #[account(mut, owner = Pubkey::system_program(), empty @ ProgramError::Custom(7))]
+        receipt: AccountInfo<'a>,
+    }
+
+    struct Account {
+        key: Pubkey,
+        owner: Pubkey,
+        data: Vec<u8>,
+        utxo: UtxoMeta,
+        is_signer: bool,
+        is_writable: bool,
+    }
+
+    impl Account {
+        fn new(key: u8, owner: Pubkey, data: Vec<u8>, is_signer: bool, is_writable: bool) -> Self {
+            Self {
+                key: Pubkey([key; 32]),
+                owner,
+                data,
+                utxo: UtxoMeta::from([0; 32], 0),
+                is_signer,
+                is_writable,
+            }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            AccountInfo::new(
+                &self.key,
+                &mut self.data,
+                &self.owner,
+                &self.utxo,
+                self.is_signer,
+                self.is_writable,
+                false,
+            )
+        }
+    }
+
+    fn program_id() -> Pubkey {
+        Pubkey([9; 32])
+    }
+
+    fn balance_data(balance: &Balance) -> Vec<u8> {
+        [
+            &Balance::DISCRIMINATOR[..],
+            &borsh::to_vec(balance).unwrap(),
+        ]
+        .concat()
+    }
+
+    /// Accounts satisfying every constraint of `Transfer`
+    fn valid_accounts() -> Vec<Account> {
+        let balance = Balance {
+            owner: Pubkey([1; 32]),
+            mint: [2; 32],
+            amount: 10,
+        };
+        vec![
+            Account::new(1, Pubkey::system_program(), vec![], true, false),
+            Account::new(2, program_id(), vec![0; 4], false, false),
+            Account::new(3, program_id(), balance_data(&balance), false, true),
+            Account::new(4, Pubkey::system_program(), vec![], false, true),
+        ]
+    }
+
+    fn try_transfer(accounts: &mut [Account]) -> Result<(), ProgramError> {
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(Account::info).collect();
+        Transfer::try_accounts(&program_id(), &infos).map(|_| ())
+    }
+
+    #[test]
+    fn test_valid_accounts() {
+        let mut accounts = valid_accounts();
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(Account::info).collect();
+        let transfer = Transfer::try_accounts(&program_id(), &infos).unwrap();
+        assert_eq!(Transfer::LEN, 4);
+        assert_eq!(transfer.owner.key, &Pubkey([1; 32]));
+        assert_eq!(transfer.mint.key, &Pubkey([2; 32]));
+        assert_eq!(transfer.balance.key, &Pubkey([3; 32]));
+        assert_eq!(transfer.receipt.key, &Pubkey([4; 32]));
+    }
+
+    #[test]
+    fn test_account_metas() {
+        let keys = TransferKeys {
+            owner: Pubkey([1; 32]),
+            mint: Pubkey([2; 32]),
+            balance: Pubkey([3; 32]),
+            receipt: Pubkey([4; 32]),
+        };
+        let metas: Vec<(u8, bool, bool)> = keys
+            .to_account_metas()
+            .iter()
+            .map(|meta| (meta.pubkey.0[0], meta.is_signer, meta.is_writable))
+            .collect();
+        assert_eq!(
+            metas,
+            vec![
+                (1, true, false),
+                (2, false, false),
+                (3, false, true),
+                (4, false, true)
+            ]
+        );
+    }
+
+    type BreakAccounts = fn(&mut Vec<Account>);
+
+    #[test]
+    fn test_constraint_errors() {
+        let cases: Vec<(BreakAccounts, ProgramError)> = vec![
+            (
+                |a| {
+                    a.pop();
+                },
+                ProgramError::NotEnoughAccountKeys,
+            ),
+            (
+                |a| a[0].is_signer = false,
+                ProgramError::MissingRequiredSignature,
+            ),
+            (|a| a[1].owner = Pubkey([5; 32]), ProgramError::IllegalOwner),
+            (|a| a[1].data.clear(), ProgramError::UninitializedAccount),
+            (|a| a[2].is_writable = false, ProgramError::Immutable),
+            (|a| a[2].data.clear(), ProgramError::UninitializedAccount),
+            (|a| a[2].data.truncate(48), ProgramError::InvalidAccountData),
+            (|a| a[2].data[0] ^= 1, ProgramError::InvalidAccountData),
+            (|a| a[2].data[8] = 0, ProgramError::InvalidAccountData),
+            (|a| a[2].data[40] = 0, ProgramError::InvalidAccountData),
+            (|a| a[3].owner = program_id(), ProgramError::IllegalOwner),
+            (|a| a[3].data.push(1), ProgramError::Custom(7)),
+        ];
+
+        for (i, (break_accounts, error)) in cases.into_iter().enumerate() {
+            let mut accounts = valid_accounts();
+            break_accounts(&mut accounts);
+            assert_eq!(try_transfer(&mut accounts), Err(error), "case {}", i);
+        }
+    }
+
+    #[test]
+    fn test_extra_accounts_are_ignored() {
+        let mut accounts = valid_accounts();
+        accounts.push(Account::new(5, program_id(), vec![], false, false));
+        assert_eq!(try_transfer(&mut accounts), Ok(()));
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_has_one(owner in any::<[u8; 32]>(), mint in any::<[u8; 32]>()) {
+            let mut accounts = valid_accounts();
+            let balance = Balance { owner: Pubkey(owner), mint, amount: 0 };
+            accounts[2].data = balance_data(&balance);
+            let expected = if owner == [1; 32] && mint == [2; 32] {
+                Ok(())
+            } else {
+                Err(ProgramError::InvalidAccountData)
+            };
+            prop_assert_eq!(try_transfer(&mut accounts), expected);
+        }
+    }
+}
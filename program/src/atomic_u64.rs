@@ -2,7 +2,7 @@ pub(crate) use implementation::AtomicU64;
 
 #[cfg(target_pointer_width = "64")]
 mod implementation {
-    use std::sync::atomic;
+    use core::sync::atomic;
 
     pub(crate) struct AtomicU64(atomic::AtomicU64);
 
@@ -0,0 +1,178 @@
+//! Hashes and signature checks computed by the runtime.
+//!
+//! Hashing and verifying Schnorr signatures in the VM is slow and pulls
+//! large code into the program. These functions hand the work to syscalls
+//! instead, so that programs cheaply check orders and oracle prices signed
+//! off chain:
+//!
+//! ```ignore
+//! let digest = tagged_hash(b"MyDex/order", &[&order.try_to_vec()?]);
+//! if !verify_schnorr(&signature, &digest, &trader) {
+//!     return Err(ProgramError::MissingRequiredSignature);
+//! }
+//! ```
+//!
+//! Hashed data is passed as slices, hashed as if concatenated. On the host
+//! the functions are computed in Rust with the same results.
+
+/// SHA-256 of the concatenation of `vals`
+pub fn sha256(vals: &[&[u8]]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        crate::syscalls::arch_sha256(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        )
+    };
+
+    #[cfg(not(target_os = "solana"))]
+    crate::program_stubs::arch_sha256(vals, &mut hash);
+
+    hash
+}
+
+/// SHA-256 of the SHA-256 of the concatenation of `vals`, as bitcoin hashes
+/// transactions and blocks
+pub fn double_sha256(vals: &[&[u8]]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        crate::syscalls::arch_double_sha256(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        )
+    };
+
+    #[cfg(not(target_os = "solana"))]
+    crate::program_stubs::arch_double_sha256(vals, &mut hash);
+
+    hash
+}
+
+/// BIP-340 tagged hash of the concatenation of `vals`:
+/// `sha256(sha256(tag) || sha256(tag) || vals)`
+pub fn tagged_hash(tag: &[u8], vals: &[&[u8]]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        crate::syscalls::arch_tagged_hash(
+            tag.as_ptr(),
+            tag.len() as u64,
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            hash.as_mut_ptr(),
+        )
+    };
+
+    #[cfg(not(target_os = "solana"))]
+    crate::program_stubs::arch_tagged_hash(tag, vals, &mut hash);
+
+    hash
+}
+
+/// Whether `signature` is a valid BIP-340 Schnorr signature of `message` by
+/// the x-only public key `pubkey`
+pub fn verify_schnorr(signature: &[u8; 64], message: &[u8; 32], pubkey: &[u8; 32]) -> bool {
+    #[cfg(target_os = "solana")]
+    let valid = unsafe {
+        crate::syscalls::arch_verify_schnorr(signature.as_ptr(), message.as_ptr(), pubkey.as_ptr())
+    };
+
+    #[cfg(not(target_os = "solana"))]
+    let valid = crate::program_stubs::arch_verify_schnorr(signature, message, pubkey);
+
+    valid != 0
+}
+
+/// Whether `signature` is a valid BIP-322 simple signature of `message` by
+/// the taproot key path address of the x-only public key `pubkey`, signed
+/// with `SIGHASH_ALL` when `uses_sighash_all` and `SIGHASH_DEFAULT`
+/// otherwise, like `bip322::verify_message_bip322`
+pub fn verify_bip322(
+    message: &[u8],
+    pubkey: &[u8; 32],
+    signature: &[u8; 64],
+    uses_sighash_all: bool,
+) -> bool {
+    #[cfg(target_os = "solana")]
+    let valid = unsafe {
+        crate::syscalls::arch_verify_bip322(
+            message.as_ptr(),
+            message.len() as u64,
+            pubkey.as_ptr(),
+            signature.as_ptr(),
+            uses_sighash_all as u64,
+        )
+    };
+
+    #[cfg(not(target_os = "solana"))]
+    let valid =
+        crate::program_stubs::arch_verify_bip322(message, pubkey, signature, uses_sighash_all);
+
+    valid != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::{sha256d, Hash};
+    use bitcoin::key::{Keypair, Secp256k1};
+    use bitcoin::secp256k1::Message;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_hashes() {
+        assert_eq!(
+            hex::encode(sha256(&[b"ab", b"c"])),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            double_sha256(&[b"abc"]),
+            sha256d::Hash::hash(b"abc").to_byte_array()
+        );
+        let tag = sha256(&[b"BIP0340/challenge"]);
+        assert_eq!(
+            tagged_hash(b"BIP0340/challenge", &[b"m", b"sg"]),
+            sha256(&[&tag, &tag, b"msg"])
+        );
+    }
+
+    #[test]
+    fn test_verify_bip322() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &[3; 32]).unwrap();
+        let pubkey = keypair.x_only_public_key().0.serialize();
+        let signature = bip322::sign_message_bip322(&keypair, b"hello", bitcoin::Network::Testnet);
+
+        // the signer uses SIGHASH_ALL
+        assert!(verify_bip322(b"hello", &pubkey, &signature, true));
+        assert!(!verify_bip322(b"hello", &pubkey, &signature, false));
+        assert!(!verify_bip322(b"hellO", &pubkey, &signature, true));
+        assert!(!verify_bip322(b"hello", &[0; 32], &signature, true));
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_verify_schnorr(secret in any::<[u8; 32]>(), message in any::<[u8; 32]>(), flip in 0..64usize) {
+            let secp = Secp256k1::new();
+            let Ok(keypair) = Keypair::from_seckey_slice(&secp, &secret) else {
+                return Ok(());
+            };
+            let pubkey = keypair.x_only_public_key().0.serialize();
+            let signature = secp
+                .sign_schnorr_no_aux_rand(&Message::from_digest(message), &keypair)
+                .serialize();
+            prop_assert!(verify_schnorr(&signature, &message, &pubkey));
+
+            let mut forged = signature;
+            forged[flip] ^= 1;
+            prop_assert!(!verify_schnorr(&forged, &message, &pubkey));
+        }
+    }
+}
@@ -1,6 +1,6 @@
 //! Debug-formatting of utxo data.
 
-use std::{cmp, fmt};
+use core::{cmp, fmt};
 
 pub(crate) const MAX_DEBUG_UTXO_DATA: usize = 64;
 
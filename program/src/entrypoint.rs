@@ -1,22 +1,36 @@
-use std::{
+use crate::{
+    account::{set_data_origin, AccountInfo, DataOrigin},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    utxo::UtxoMeta,
+};
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::{
     alloc::Layout,
     cell::RefCell,
     mem::size_of,
     ptr::null_mut,
-    rc::Rc,
     slice::{from_raw_parts, from_raw_parts_mut},
 };
-extern crate alloc;
-use crate::{account::AccountInfo, program_error::ProgramError, pubkey::Pubkey, utxo::UtxoMeta};
-use alloc::vec::Vec;
 /// Start address of the memory region used for program heap.
 pub const HEAP_START_ADDRESS: u64 = 0x300000000;
 /// Length of the heap memory region used for program heap.
 pub const HEAP_LENGTH: usize = 32 * 1024;
+/// Largest heap frame a program can request through `entrypoint!`.
+pub const MAX_HEAP_LENGTH: usize = 256 * 1024;
+/// Heap frames are requested in multiples of this many bytes.
+pub const HEAP_FRAME_GRANULARITY: usize = 1024;
+/// Start address of the memory region the runtime serializes the program
+/// input to.
+pub const INPUT_START_ADDRESS: u64 = 0x400000000;
 /// Maximum permitted size of account data (10 MiB).
 pub const MAX_PERMITTED_DATA_LENGTH: usize = 10 * 1024 * 1024;
 /// Maximum number of bytes a program may add to an account during a single realloc
 pub const MAX_PERMITTED_DATA_INCREASE: usize = 10 * 1_024;
+/// Maximum number of bytes the accounts of an instruction may grow by in total
+pub const MAX_PERMITTED_ACCOUNTS_DATA_ALLOCATIONS_PER_INSTRUCTION: i64 =
+    MAX_PERMITTED_DATA_LENGTH as i64 * 2;
 
 pub const BPF_ALIGN_OF_U128: usize = 8;
 
@@ -46,7 +60,7 @@ pub struct BumpAllocator {
 /// operating on the prescribed `HEAP_START_ADDRESS` and `HEAP_LENGTH`. Any
 /// other use may overflow and is thus unsupported and at one's own risk.
 #[allow(clippy::arithmetic_side_effects)]
-unsafe impl std::alloc::GlobalAlloc for BumpAllocator {
+unsafe impl core::alloc::GlobalAlloc for BumpAllocator {
     #[inline]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         let pos_ptr = self.start as *mut usize;
@@ -59,6 +73,7 @@ unsafe impl std::alloc::GlobalAlloc for BumpAllocator {
         pos = pos.saturating_sub(layout.size());
         pos &= !(layout.align().wrapping_sub(1));
         if pos < self.start + size_of::<*mut u8>() {
+            log_alloc_failure(layout, self.len);
             return null_mut();
         }
         *pos_ptr = pos;
@@ -70,6 +85,139 @@ unsafe impl std::alloc::GlobalAlloc for BumpAllocator {
     }
 }
 
+/// Largest block [`FreeListAllocator`] reuses once freed
+pub const FREE_LIST_MAX_BLOCK: usize = 512;
+
+/// Blocks of 8, 16, .. `FREE_LIST_MAX_BLOCK` bytes
+const SIZE_CLASSES: usize = 7;
+
+/// State of [`FreeListAllocator`], kept at the start of the heap
+#[repr(C)]
+struct FreeLists {
+    /// Lowest allocated address, 0 before the first allocation
+    pos: usize,
+    /// First free block of each size class, linked through their first word
+    heads: [usize; SIZE_CLASSES],
+}
+
+/// Allocator reusing freed blocks of up to `FREE_LIST_MAX_BLOCK` bytes, for
+/// programs decoding and dropping many small values such as strings and map
+/// entries. Small allocations are rounded up to a power of two, larger ones
+/// are bump allocated and never freed.
+pub struct FreeListAllocator {
+    pub start: usize,
+    pub len: usize,
+}
+
+impl FreeListAllocator {
+    fn size_class(layout: Layout) -> Option<usize> {
+        let size = layout
+            .size()
+            .max(layout.align())
+            .max(size_of::<usize>())
+            .next_power_of_two();
+        (size <= FREE_LIST_MAX_BLOCK).then(|| size.trailing_zeros() as usize - 3)
+    }
+
+    unsafe fn bump(&self, lists: &mut FreeLists, size: usize, align: usize) -> *mut u8 {
+        let pos = lists.pos.saturating_sub(size) & !(align.wrapping_sub(1));
+        if pos < self.start + size_of::<FreeLists>() {
+            return null_mut();
+        }
+        lists.pos = pos;
+        pos as *mut u8
+    }
+}
+
+/// Same arithmetic caveats as [`BumpAllocator`]
+#[allow(clippy::arithmetic_side_effects)]
+unsafe impl core::alloc::GlobalAlloc for FreeListAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let lists = &mut *(self.start as *mut FreeLists);
+        if lists.pos == 0 {
+            lists.pos = self.start + self.len;
+        }
+        let ptr = match Self::size_class(layout) {
+            Some(class) => {
+                let head = lists.heads[class];
+                if head != 0 {
+                    lists.heads[class] = *(head as *const usize);
+                    return head as *mut u8;
+                }
+                let block = 8 << class;
+                self.bump(lists, block, block)
+            }
+            None => self.bump(lists, layout.size(), layout.align()),
+        };
+        if ptr.is_null() {
+            log_alloc_failure(layout, self.len);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if let Some(class) = Self::size_class(layout) {
+            let lists = &mut *(self.start as *mut FreeLists);
+            *(ptr as *mut usize) = lists.heads[class];
+            lists.heads[class] = ptr as usize;
+        }
+    }
+}
+
+/// Logs why an allocation failed, without allocating
+fn log_alloc_failure(layout: Layout, heap_len: usize) {
+    use core::fmt::Write;
+
+    struct Buffer {
+        bytes: [u8; 128],
+        len: usize,
+    }
+
+    impl Write for Buffer {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            let end = (self.len + s.len()).min(self.bytes.len());
+            self.bytes[self.len..end].copy_from_slice(&s.as_bytes()[..end - self.len]);
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    let mut buffer = Buffer {
+        bytes: [0; 128],
+        len: 0,
+    };
+    let _ = write!(
+        buffer,
+        "Error: out of heap memory allocating {} bytes, the heap is {} bytes",
+        layout.size(),
+        heap_len
+    );
+    crate::log::sol_log(core::str::from_utf8(&buffer.bytes[..buffer.len]).unwrap_or_default());
+}
+
+/// Checks at compile time that the heap length requested through
+/// `entrypoint!` is a whole number of KiB between `HEAP_LENGTH` and
+/// `MAX_HEAP_LENGTH`
+pub const fn heap_length(len: usize) -> usize {
+    assert!(
+        len >= HEAP_LENGTH && len <= MAX_HEAP_LENGTH && len & (HEAP_FRAME_GRANULARITY - 1) == 0,
+        "the heap size must be a multiple of 1 KiB between 32 KiB and 256 KiB"
+    );
+    len
+}
+
+/// Bytes of the heap the allocators of `entrypoint!` use for a requested
+/// heap size. Runtimes map `HEAP_LENGTH` bytes at `HEAP_START_ADDRESS`
+/// whatever the program requests, so the allocators never go past them.
+pub const fn usable_heap_length(len: usize) -> usize {
+    let len = heap_length(len);
+    if len < HEAP_LENGTH {
+        len
+    } else {
+        HEAP_LENGTH
+    }
+}
+
 #[allow(clippy::missing_safety_doc)]
 pub unsafe fn deserialize<'a>(input: *mut u8) -> (&'a Pubkey, Vec<AccountInfo<'a>>, &'a [u8]) {
     let mut offset: usize = 0;
@@ -104,6 +252,7 @@ pub unsafe fn deserialize<'a>(input: *mut u8) -> (&'a Pubkey, Vec<AccountInfo<'a
             let data_len = *(input.add(offset) as *const u64) as usize;
             offset += size_of::<u64>();
 
+            set_data_origin(input.add(offset), DataOrigin::Input(input));
             let data = Rc::new(RefCell::new({
                 from_raw_parts_mut(input.add(offset), data_len)
             }));
@@ -119,7 +268,7 @@ pub unsafe fn deserialize<'a>(input: *mut u8) -> (&'a Pubkey, Vec<AccountInfo<'a
 
             offset += 4 * size_of::<u8>();
 
-            accounts.push(AccountInfo {
+            let account = AccountInfo {
                 key,
                 utxo,
                 data,
@@ -127,7 +276,8 @@ pub unsafe fn deserialize<'a>(input: *mut u8) -> (&'a Pubkey, Vec<AccountInfo<'a
                 is_signer,
                 is_writable,
                 is_executable,
-            });
+            };
+            accounts.push(account);
         } else {
             offset += 7; // padding
 
@@ -152,13 +302,48 @@ pub unsafe fn deserialize<'a>(input: *mut u8) -> (&'a Pubkey, Vec<AccountInfo<'a
     (program_id, accounts, instruction_data)
 }
 
+/// Declares the program entrypoint, which deserializes the input and calls
+/// `$process_instruction`, along with the heap allocator and panic handler.
+///
+/// `heap_size = <bytes>` declares the heap the program needs beyond the
+/// default `HEAP_LENGTH`, up to `MAX_HEAP_LENGTH` in multiples of
+/// `HEAP_FRAME_GRANULARITY`, and exports it as the `ARCH_HEAP_FRAME_LENGTH`
+/// symbol. No runtime reads that symbol yet: they map `HEAP_LENGTH` bytes, so
+/// the allocator uses [`usable_heap_length`](crate::entrypoint::usable_heap_length)
+/// of the requested size. `allocator = free_list` replaces the default bump allocator, which
+/// never frees, with [`FreeListAllocator`](crate::entrypoint::FreeListAllocator).
+///
+/// ```ignore
+/// entrypoint!(process_instruction, heap_size = 128 * 1024, allocator = free_list);
+/// ```
 #[macro_export]
 macro_rules! entrypoint {
+    ($process_instruction:ident, heap_size = $heap_size:expr) => {
+        $crate::entrypoint!(@entrypoint $process_instruction);
+        $crate::custom_heap!($heap_size, BumpAllocator);
+        $crate::custom_panic_default!();
+    };
+    ($process_instruction:ident, heap_size = $heap_size:expr, allocator = free_list) => {
+        $crate::entrypoint!(@entrypoint $process_instruction);
+        $crate::custom_heap!($heap_size, FreeListAllocator);
+        $crate::custom_panic_default!();
+    };
+    ($process_instruction:ident, allocator = free_list) => {
+        $crate::entrypoint!(
+            $process_instruction,
+            heap_size = $crate::entrypoint::HEAP_LENGTH,
+            allocator = free_list
+        );
+    };
     ($process_instruction:ident) => {
+        $crate::entrypoint!(@entrypoint $process_instruction);
+        $crate::custom_heap_default!();
+        $crate::custom_panic_default!();
+    };
+    (@entrypoint $process_instruction:ident) => {
         /// # Safety
         #[no_mangle]
         pub unsafe extern "C" fn entrypoint(input: *mut u8) -> u64 {
-            use std::collections::HashMap;
             let (program_id, utxos, instruction_data) =
                 unsafe { $crate::entrypoint::deserialize(input) };
             match $process_instruction(&program_id, &utxos, &instruction_data) {
@@ -166,8 +351,23 @@ macro_rules! entrypoint {
                 Err(error) => error.into(),
             }
         }
-        $crate::custom_heap_default!();
-        $crate::custom_panic_default!();
+    };
+}
+
+/// Global allocator over the part of a `$heap_size` bytes heap the runtime
+/// maps, and the symbol recording the requested size
+#[macro_export]
+macro_rules! custom_heap {
+    ($heap_size:expr, $allocator:ident) => {
+        #[global_allocator]
+        static A: $crate::entrypoint::$allocator = $crate::entrypoint::$allocator {
+            start: $crate::entrypoint::HEAP_START_ADDRESS as usize,
+            len: $crate::entrypoint::usable_heap_length($heap_size),
+        };
+
+        #[no_mangle]
+        #[used]
+        pub static ARCH_HEAP_FRAME_LENGTH: u32 = $crate::entrypoint::heap_length($heap_size) as u32;
     };
 }
 
@@ -192,3 +392,208 @@ macro_rules! custom_panic_default {
         }
     };
 }
+
+/// Lays out the program input the way the runtime does, for tests running
+/// code that depends on it such as `AccountInfo::realloc`
+#[cfg(test)]
+pub(crate) mod test_input {
+    use super::{BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER};
+    use crate::{pubkey::Pubkey, utxo::UtxoMeta};
+
+    #[derive(Clone)]
+    pub struct TestAccount {
+        pub key: Pubkey,
+        pub owner: Pubkey,
+        pub data: Vec<u8>,
+        pub is_signer: bool,
+        pub is_writable: bool,
+        pub is_executable: bool,
+    }
+
+    /// An account of the input, or the index of an earlier one it repeats
+    #[derive(Clone)]
+    pub enum TestEntry {
+        Account(TestAccount),
+        Duplicate(u8),
+    }
+
+    /// Input to pass to `deserialize`, as a `u64` buffer to keep it aligned
+    pub fn serialize(
+        accounts: &[TestAccount],
+        instruction_data: &[u8],
+        program_id: &Pubkey,
+    ) -> Vec<u64> {
+        let entries: Vec<TestEntry> = accounts.iter().cloned().map(TestEntry::Account).collect();
+        serialize_entries(&entries, instruction_data, program_id)
+    }
+
+    pub fn serialize_entries(
+        entries: &[TestEntry],
+        instruction_data: &[u8],
+        program_id: &Pubkey,
+    ) -> Vec<u64> {
+        let mut input = vec![];
+        input.extend((entries.len() as u64).to_le_bytes());
+        for entry in entries {
+            let account = match entry {
+                TestEntry::Account(account) => account,
+                TestEntry::Duplicate(index) => {
+                    input.push(*index);
+                    input.extend([0; 7]);
+                    continue;
+                }
+            };
+            input.push(NON_DUP_MARKER);
+            input.extend([0; 4]);
+            input.push(account.is_signer as u8);
+            input.push(account.is_writable as u8);
+            input.push(account.is_executable as u8);
+            input.extend(account.key.serialize());
+            input.extend((account.data.len() as u64).to_le_bytes());
+            input.extend((account.data.len() as u64).to_le_bytes());
+            input.extend(&account.data);
+            input.resize(input.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+            input.resize(input.len().next_multiple_of(BPF_ALIGN_OF_U128), 0);
+            input.extend(account.owner.serialize());
+            input.extend(UtxoMeta::from([0; 32], 0).serialize());
+            input.extend([0; 4]);
+        }
+        input.extend((instruction_data.len() as u64).to_le_bytes());
+        input.extend(instruction_data);
+        input.extend(program_id.serialize());
+
+        input
+            .chunks(8)
+            .map(|chunk| {
+                let mut word = [0; 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+                u64::from_ne_bytes(word)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::alloc::GlobalAlloc;
+    use proptest::prelude::*;
+
+    /// Zeroed, aligned memory standing in for the heap region
+    fn heap(len: usize) -> Vec<u64> {
+        vec![0; len / 8]
+    }
+
+    #[test]
+    fn test_bump_allocator_exhaustion() {
+        let mut memory = heap(1024);
+        let allocator = BumpAllocator {
+            start: memory.as_mut_ptr() as usize,
+            len: 1024,
+        };
+        unsafe {
+            assert!(!allocator
+                .alloc(Layout::from_size_align(1000, 8).unwrap())
+                .is_null());
+            assert!(allocator
+                .alloc(Layout::from_size_align(64, 8).unwrap())
+                .is_null());
+        }
+    }
+
+    #[test]
+    fn test_free_list_reuses_blocks() {
+        let mut memory = heap(4096);
+        let start = memory.as_mut_ptr() as usize;
+        let allocator = FreeListAllocator { start, len: 4096 };
+        let small = Layout::from_size_align(20, 4).unwrap();
+        let large = Layout::from_size_align(1000, 8).unwrap();
+        unsafe {
+            let a = allocator.alloc(small);
+            let b = allocator.alloc(small);
+            assert_eq!(a as usize % 32, 0);
+            assert_ne!(a, b);
+            allocator.dealloc(a, small);
+            assert_eq!(allocator.alloc(Layout::from_size_align(32, 1).unwrap()), a);
+
+            let c = allocator.alloc(large);
+            assert!(!c.is_null());
+            allocator.dealloc(c, large);
+            assert_ne!(allocator.alloc(large), c);
+            assert!(!allocator.alloc(large).is_null());
+            assert!(allocator.alloc(large).is_null());
+            assert!(c as usize >= start + size_of::<FreeLists>());
+        }
+    }
+
+    #[test]
+    fn test_heap_length() {
+        assert_eq!(heap_length(HEAP_LENGTH), HEAP_LENGTH);
+        assert_eq!(heap_length(MAX_HEAP_LENGTH), MAX_HEAP_LENGTH);
+        for len in [
+            0,
+            HEAP_LENGTH - 1024,
+            HEAP_LENGTH + 1,
+            MAX_HEAP_LENGTH + 1024,
+        ] {
+            assert!(std::panic::catch_unwind(|| heap_length(len)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_heap_size_above_heap_length() {
+        let len = usable_heap_length(MAX_HEAP_LENGTH);
+        assert_eq!(len, HEAP_LENGTH);
+
+        // only `HEAP_LENGTH` bytes are mapped
+        let mut memory = heap(HEAP_LENGTH);
+        let start = memory.as_mut_ptr() as usize;
+        let allocators: [&dyn GlobalAlloc; 2] = [
+            &BumpAllocator { start, len },
+            &FreeListAllocator { start, len },
+        ];
+        let layout = Layout::from_size_align(1024, 8).unwrap();
+        for allocator in allocators {
+            memory.fill(0);
+            let mut allocated = 0;
+            loop {
+                let ptr = unsafe { allocator.alloc(layout) } as usize;
+                if ptr == 0 {
+                    break;
+                }
+                assert!(ptr >= start && ptr + layout.size() <= start + HEAP_LENGTH);
+                allocated += 1;
+            }
+            assert_eq!(allocated, HEAP_LENGTH / layout.size() - 1);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_free_list_blocks_never_overlap(
+            ops in prop::collection::vec((1..600usize, 0..4u32, any::<bool>()), 1..64)
+        ) {
+            let len = 64 * 1024;
+            let mut memory = heap(len);
+            let start = memory.as_mut_ptr() as usize;
+            let allocator = FreeListAllocator { start, len };
+            let mut live: Vec<(usize, Layout)> = vec![];
+            for (size, align, free) in ops {
+                if free && !live.is_empty() {
+                    let (ptr, layout) = live.swap_remove(0);
+                    unsafe { allocator.dealloc(ptr as *mut u8, layout) };
+                    continue;
+                }
+                let layout = Layout::from_size_align(size, 1 << align).unwrap();
+                let ptr = unsafe { allocator.alloc(layout) } as usize;
+                prop_assert!(ptr != 0);
+                prop_assert_eq!(ptr % layout.align(), 0);
+                prop_assert!(ptr >= start + size_of::<FreeLists>() && ptr + size <= start + len);
+                for (other, other_layout) in &live {
+                    prop_assert!(ptr + size <= *other || other + other_layout.size() <= ptr);
+                }
+                live.push((ptr, layout));
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Structured events logged by programs.
+//!
+//! An event is a borsh serializable struct deriving [`Event`]. [`emit!`]
+//! logs it through [`sol_log_data`](crate::log::sol_log_data) as its 8 byte
+//! discriminator, `sha256("event:<Name>")[..8]`, followed by its borsh
+//! encoding. Clients find events in the `Program data:` lines of a
+//! transaction's logs and decode them with [`decode_event`], so that they
+//! react to typed events rather than parse free text logs.
+//!
+//! ```ignore
+//! #[derive(BorshSerialize, BorshDeserialize, Event)]
+//! pub struct Minted {
+//!     pub mint: Pubkey,
+//!     pub amount: u64,
+//! }
+//!
+//! emit!(Minted { mint: *accounts.mint.key, amount });
+//! ```
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::account_state::Discriminator;
+use crate::prelude::*;
+
+pub use arch_program_macros::Event;
+
+/// An event a program logs with [`emit!`]. Derive it with
+/// `#[derive(Event)]`.
+pub trait Event: Discriminator + BorshSerialize {
+    /// The logged bytes: the discriminator, then the borsh encoding
+    fn data(&self) -> Vec<u8> {
+        let mut data = Self::DISCRIMINATOR.to_vec();
+        self.serialize(&mut data)
+            .expect("writing to a vec does not fail");
+        data
+    }
+}
+
+/// Logs `event`, see [`emit!`]
+pub fn emit<E: Event>(event: &E) {
+    crate::log::sol_log_data(&[&event.data()]);
+}
+
+/// The event of type `E` logged as `data`, `None` when `data` holds another
+/// event or does not decode
+pub fn decode_event<E: Event + BorshDeserialize>(data: &[u8]) -> Option<E> {
+    let data = data.strip_prefix(&E::DISCRIMINATOR)?;
+    E::try_from_slice(data).ok()
+}
+
+/// Logs an [`Event`] as program data.
+///
+/// ```ignore
+/// emit!(Transferred { from, to, amount });
+/// ```
+#[macro_export]
+macro_rules! emit {
+    ($event:expr) => {
+        $crate::event::emit(&$event)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_event, Event};
+    use crate::account_state::Discriminator;
+    use crate::log::{format_data_log, parse_data_log};
+    use crate::pubkey::Pubkey;
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use proptest::prelude::*;
+
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize, Event)]
+    struct Minted {
+        mint: Pubkey,
+        amount: u64,
+    }
+
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize, Event)]
+    #[event(name = "Minted")]
+    struct MintedV2 {
+        mint: Pubkey,
+        amount: u64,
+    }
+
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize, Event)]
+    struct Burned {
+        mint: Pubkey,
+        amount: u64,
+    }
+
+    #[test]
+    fn test_discriminator() {
+        let digest = hex::decode(&sha256::digest("event:Minted")[..16]).unwrap();
+        assert_eq!(Minted::DISCRIMINATOR[..], digest[..]);
+        assert_eq!(MintedV2::DISCRIMINATOR, Minted::DISCRIMINATOR);
+        assert_ne!(Burned::DISCRIMINATOR, Minted::DISCRIMINATOR);
+    }
+
+    #[test]
+    fn test_emit() {
+        super::emit(&Minted {
+            mint: Pubkey([1; 32]),
+            amount: 5,
+        });
+        emit!(Burned {
+            mint: Pubkey([1; 32]),
+            amount: 5,
+        });
+    }
+
+    #[test]
+    fn test_parse_data_log() {
+        assert_eq!(
+            parse_data_log("Program data: AQI= Aw=="),
+            Some(vec![vec![1, 2], vec![3]])
+        );
+        assert_eq!(parse_data_log("Program data: "), Some(vec![]));
+        assert_eq!(parse_data_log("Program data: !"), None);
+        assert_eq!(parse_data_log("circulating_supply: 5"), None);
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_event_roundtrip(mint in any::<[u8; 32]>(), amount in any::<u64>()) {
+            let event = Minted { mint: Pubkey(mint), amount };
+            let log = format_data_log(&[&event.data()]);
+            let fields = parse_data_log(&log).unwrap();
+            prop_assert_eq!(fields.len(), 1);
+            prop_assert_eq!(decode_event::<Minted>(&fields[0]), Some(Minted { mint: Pubkey(mint), amount }));
+            prop_assert_eq!(decode_event::<Burned>(&fields[0]), None);
+        }
+
+        #[test]
+        fn fuzz_data_log_roundtrip(data in prop::collection::vec(prop::collection::vec(any::<u8>(), 1..64), 0..4)) {
+            let slices: Vec<&[u8]> = data.iter().map(Vec::as_slice).collect();
+            prop_assert_eq!(parse_data_log(&format_data_log(&slices)), Some(data));
+        }
+    }
+}
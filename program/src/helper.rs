@@ -1,15 +1,17 @@
-use std::str::FromStr;
+use core::str::FromStr;
 
 use bitcoin::{
     absolute::LockTime, transaction::Version, OutPoint, ScriptBuf, Sequence, Transaction, TxIn,
     TxOut, Txid, Witness,
 };
 
+use crate::prelude::*;
 use crate::{
     account::AccountInfo,
     program::{get_account_script_pubkey, get_bitcoin_tx},
 };
 
+#[deprecated(note = "use `StateTransitionBuilder`, which returns errors instead of panicking")]
 pub fn get_state_transition_tx(accounts: &[AccountInfo]) -> Transaction {
     Transaction {
         version: Version::TWO,
@@ -47,6 +49,7 @@ pub fn get_state_transition_tx(accounts: &[AccountInfo]) -> Transaction {
     }
 }
 
+#[deprecated(note = "use `StateTransitionBuilder`, which returns errors instead of panicking")]
 pub fn add_state_transition(transaction: &mut Transaction, account: &AccountInfo) {
     assert!(account.is_writable);
     transaction.input.push(TxIn {
@@ -0,0 +1,548 @@
+//! Machine readable description of a program's interface (IDL).
+//!
+//! A program crate builds its [`Idl`] from its own types: instructions with
+//! their discriminator, accounts and borsh encoded arguments, the layout of
+//! the account data it owns and its custom errors. The sdk turns an IDL into a
+//! typed client module, so that clients no longer mirror program types by hand.
+//!
+//! Instructions are described by the derives: `#[derive(Accounts)]` lists
+//! the accounts of a struct with [`IdlAccounts`], and an enum deriving
+//! `ProgramInstruction` with `#[instruction(idl)]` lists its variants with
+//! [`IdlInstructions`], so that the IDL cannot drift from the program.
+//!
+//! ```ignore
+//! #[derive(BorshSerialize, BorshDeserialize, IdlBuild)]
+//! pub struct MintInput {
+//!     pub amount: u64,
+//! }
+//!
+//! #[derive(ProgramInstruction)]
+//! #[instruction(idl)]
+//! pub enum TokenInstruction {
+//!     #[instruction(discriminator = 2, accounts = MintAccounts)]
+//!     Mint(MintInput),
+//! }
+//!
+//! pub fn idl() -> Idl {
+//!     Idl::new("token", env!("CARGO_PKG_VERSION")).instructions::<TokenInstruction>()
+//! }
+//! ```
+
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+use crate::program_error::CustomError;
+use crate::pubkey::Pubkey;
+use crate::utxo::UtxoMeta;
+
+pub use arch_program_macros::IdlBuild;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Idl {
+    pub name: String,
+    pub version: String,
+    pub instructions: Vec<IdlInstruction>,
+    /// Layouts of the account data owned by the program
+    pub accounts: Vec<IdlAccountDef>,
+    /// Every user defined type referred to by instructions and accounts
+    pub types: Vec<IdlTypeDef>,
+    pub errors: Vec<IdlError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    /// Leading bytes of the instruction data selecting this instruction
+    pub discriminator: Vec<u8>,
+    pub accounts: Vec<IdlAccountMeta>,
+    /// Borsh encoded one after the other, after the discriminator
+    pub args: Vec<IdlField>,
+    #[serde(skip)]
+    type_defs: Vec<IdlTypeDef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdlAccountMeta {
+    pub name: String,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdlAccountDef {
+    pub name: String,
+    /// Leading bytes of the account data identifying its type, may be empty
+    pub discriminator: Vec<u8>,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdlError {
+    /// Value carried by `ProgramError::Custom`
+    pub code: u32,
+    pub name: String,
+    pub msg: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+/// Borsh encoded type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdlType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    String,
+    Pubkey,
+    UtxoMeta,
+    Option(Box<IdlType>),
+    Vec(Box<IdlType>),
+    Array(Box<IdlType>, usize),
+    Tuple(Vec<IdlType>),
+    Map(Box<IdlType>, Box<IdlType>),
+    /// A type listed in [`Idl::types`]
+    Defined(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdlTypeDef {
+    pub name: String,
+    pub kind: IdlTypeDefKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdlTypeDefKind {
+    Struct(IdlFields),
+    /// Borsh encodes the variant index as a `u8`
+    Enum(Vec<IdlEnumVariant>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdlFields {
+    Unit,
+    Named(Vec<IdlField>),
+    Tuple(Vec<IdlType>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdlEnumVariant {
+    pub name: String,
+    pub fields: IdlFields,
+}
+
+/// Accounts expected by an instruction, in order. Derived by `#[derive(Accounts)]`.
+pub trait IdlAccounts {
+    fn idl_accounts() -> Vec<IdlAccountMeta>;
+}
+
+/// Every instruction of a program. Derived by `#[derive(ProgramInstruction)]`
+/// on an enum with the `#[instruction(idl)]` attribute.
+pub trait IdlInstructions {
+    fn idl_instructions() -> Vec<IdlInstruction>;
+}
+
+/// Describes how a type is borsh encoded. Derive it with `#[derive(IdlBuild)]`.
+pub trait IdlBuild {
+    fn idl_type() -> IdlType;
+
+    /// Adds the definition of this type, and of the types it refers to, to `defs`
+    fn idl_type_defs(_defs: &mut Vec<IdlTypeDef>) {}
+}
+
+macro_rules! impl_idl_build {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl IdlBuild for $ty {
+                fn idl_type() -> IdlType {
+                    IdlType::$variant
+                }
+            }
+        )*
+    };
+}
+
+impl_idl_build!(
+    bool => Bool,
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+    u128 => U128,
+    i8 => I8,
+    i16 => I16,
+    i32 => I32,
+    i64 => I64,
+    i128 => I128,
+    String => String,
+    Pubkey => Pubkey,
+    UtxoMeta => UtxoMeta,
+);
+
+impl<T: IdlBuild> IdlBuild for Option<T> {
+    fn idl_type() -> IdlType {
+        IdlType::Option(Box::new(T::idl_type()))
+    }
+
+    fn idl_type_defs(defs: &mut Vec<IdlTypeDef>) {
+        T::idl_type_defs(defs)
+    }
+}
+
+impl<T: IdlBuild> IdlBuild for Vec<T> {
+    fn idl_type() -> IdlType {
+        IdlType::Vec(Box::new(T::idl_type()))
+    }
+
+    fn idl_type_defs(defs: &mut Vec<IdlTypeDef>) {
+        T::idl_type_defs(defs)
+    }
+}
+
+impl<T: IdlBuild> IdlBuild for Box<T> {
+    fn idl_type() -> IdlType {
+        T::idl_type()
+    }
+
+    fn idl_type_defs(defs: &mut Vec<IdlTypeDef>) {
+        T::idl_type_defs(defs)
+    }
+}
+
+impl<T: IdlBuild, const N: usize> IdlBuild for [T; N] {
+    fn idl_type() -> IdlType {
+        IdlType::Array(Box::new(T::idl_type()), N)
+    }
+
+    fn idl_type_defs(defs: &mut Vec<IdlTypeDef>) {
+        T::idl_type_defs(defs)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: IdlBuild, V: IdlBuild, S> IdlBuild for HashMap<K, V, S> {
+    fn idl_type() -> IdlType {
+        IdlType::Map(Box::new(K::idl_type()), Box::new(V::idl_type()))
+    }
+
+    fn idl_type_defs(defs: &mut Vec<IdlTypeDef>) {
+        K::idl_type_defs(defs);
+        V::idl_type_defs(defs);
+    }
+}
+
+impl<K: IdlBuild, V: IdlBuild> IdlBuild for BTreeMap<K, V> {
+    fn idl_type() -> IdlType {
+        IdlType::Map(Box::new(K::idl_type()), Box::new(V::idl_type()))
+    }
+
+    fn idl_type_defs(defs: &mut Vec<IdlTypeDef>) {
+        K::idl_type_defs(defs);
+        V::idl_type_defs(defs);
+    }
+}
+
+macro_rules! impl_idl_build_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: IdlBuild),+> IdlBuild for ($($name,)+) {
+            fn idl_type() -> IdlType {
+                IdlType::Tuple(vec![$($name::idl_type()),+])
+            }
+
+            fn idl_type_defs(defs: &mut Vec<IdlTypeDef>) {
+                $($name::idl_type_defs(defs);)+
+            }
+        }
+    };
+}
+
+impl_idl_build_tuple!(A);
+impl_idl_build_tuple!(A, B);
+impl_idl_build_tuple!(A, B, C);
+impl_idl_build_tuple!(A, B, C, D);
+
+fn add_type_defs(types: &mut Vec<IdlTypeDef>, defs: Vec<IdlTypeDef>) {
+    for def in defs {
+        if !types.iter().any(|ty| ty.name == def.name) {
+            types.push(def);
+        }
+    }
+}
+
+impl Idl {
+    pub fn new(name: &str, version: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn instruction(mut self, mut instruction: IdlInstruction) -> Self {
+        add_type_defs(&mut self.types, core::mem::take(&mut instruction.type_defs));
+        self.instructions.push(instruction);
+        self
+    }
+
+    /// Adds every instruction of a `#[derive(ProgramInstruction)]` enum
+    pub fn instructions<I: IdlInstructions>(self) -> Self {
+        I::idl_instructions()
+            .into_iter()
+            .fold(self, Self::instruction)
+    }
+
+    /// Declares an account data layout, `discriminator` being the bytes the data starts with
+    pub fn account<T: IdlBuild>(mut self, name: &str, discriminator: &[u8]) -> Self {
+        let mut defs = vec![];
+        T::idl_type_defs(&mut defs);
+        add_type_defs(&mut self.types, defs);
+
+        self.accounts.push(IdlAccountDef {
+            name: name.to_string(),
+            discriminator: discriminator.to_vec(),
+            ty: T::idl_type(),
+        });
+        self
+    }
+
+    pub fn error(mut self, code: u32, name: &str, msg: Option<&str>) -> Self {
+        self.errors.push(IdlError {
+            code,
+            name: name.to_string(),
+            msg: msg.map(str::to_string),
+        });
+        self
+    }
+
+    /// Adds every error of a `#[derive(CustomError)]` enum
+    pub fn errors<E: CustomError>(self) -> Self {
+        E::ERRORS.iter().fold(self, |idl, (code, name, msg)| {
+            idl.error(*code, name, Some(msg))
+        })
+    }
+}
+
+impl IdlInstruction {
+    pub fn new(name: &str, discriminator: &[u8]) -> Self {
+        Self {
+            name: name.to_string(),
+            discriminator: discriminator.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    pub fn account(mut self, name: &str, is_signer: bool, is_writable: bool) -> Self {
+        self.accounts.push(IdlAccountMeta {
+            name: name.to_string(),
+            is_signer,
+            is_writable,
+        });
+        self
+    }
+
+    /// Adds the accounts of a `#[derive(Accounts)]` struct
+    pub fn accounts<A: IdlAccounts>(mut self) -> Self {
+        self.accounts.extend(A::idl_accounts());
+        self
+    }
+
+    pub fn arg<T: IdlBuild>(mut self, name: &str) -> Self {
+        T::idl_type_defs(&mut self.type_defs);
+        self.args.push(IdlField {
+            name: name.to_string(),
+            ty: T::idl_type(),
+        });
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Idl, IdlBuild, IdlEnumVariant, IdlField, IdlFields, IdlInstruction, IdlType, IdlTypeDefKind,
+    };
+    use crate::account::AccountInfo;
+    use crate::accounts::Accounts;
+    use crate::program_error::CustomError;
+    use crate::program_instruction::ProgramInstruction;
+    use crate::pubkey::Pubkey;
+    use std::collections::HashMap;
+
+    #[derive(IdlBuild)]
+    #[allow(dead_code)]
+    struct Input {
+        amount: u64,
+        recipient: Pubkey,
+        kind: Kind,
+    }
+
+    #[derive(IdlBuild)]
+    #[allow(dead_code)]
+    enum Kind {
+        Simple,
+        Memo(String),
+        Split {
+            parts: Vec<u16>,
+            metadata: HashMap<String, [u8; 32]>,
+        },
+    }
+
+    #[derive(IdlBuild)]
+    #[allow(dead_code)]
+    struct State(Option<Kind>, (u8, bool));
+
+    #[derive(Accounts)]
+    #[allow(dead_code)]
+    struct Transfer<'a> {
+        #[account(signer)]
+        owner: AccountInfo<'a>,
+        #[account(mut, owner = program)]
+        balance: AccountInfo<'a>,
+    }
+
+    #[derive(ProgramInstruction)]
+    #[instruction(idl)]
+    #[allow(dead_code)]
+    enum Instruction {
+        #[instruction(discriminator = 3, accounts = Transfer)]
+        Transfer(u64),
+        #[instruction(discriminator = 4)]
+        SetFees { fee: u16, recipient: Pubkey },
+    }
+
+    #[derive(CustomError)]
+    #[allow(dead_code)]
+    enum Error {
+        #[msg("Mint is over")]
+        MintOver = 600,
+        Frozen,
+    }
+
+    #[test]
+    fn test_derive_idl_build() {
+        assert_eq!(Input::idl_type(), IdlType::Defined("Input".to_string()));
+
+        let mut defs = vec![];
+        Input::idl_type_defs(&mut defs);
+        let names = defs.iter().map(|def| def.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Input", "Kind"]);
+
+        assert_eq!(
+            defs[0].kind,
+            IdlTypeDefKind::Struct(IdlFields::Named(vec![
+                IdlField {
+                    name: "amount".to_string(),
+                    ty: IdlType::U64
+                },
+                IdlField {
+                    name: "recipient".to_string(),
+                    ty: IdlType::Pubkey
+                },
+                IdlField {
+                    name: "kind".to_string(),
+                    ty: IdlType::Defined("Kind".to_string())
+                },
+            ]))
+        );
+
+        let IdlTypeDefKind::Enum(variants) = &defs[1].kind else {
+            panic!("Kind should be an enum");
+        };
+        assert_eq!(
+            variants[1],
+            IdlEnumVariant {
+                name: "Memo".to_string(),
+                fields: IdlFields::Tuple(vec![IdlType::String])
+            }
+        );
+        assert_eq!(
+            variants[2].fields,
+            IdlFields::Named(vec![
+                IdlField {
+                    name: "parts".to_string(),
+                    ty: IdlType::Vec(Box::new(IdlType::U16))
+                },
+                IdlField {
+                    name: "metadata".to_string(),
+                    ty: IdlType::Map(
+                        Box::new(IdlType::String),
+                        Box::new(IdlType::Array(Box::new(IdlType::U8), 32))
+                    )
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_idl_builder() {
+        let idl = Idl::new("test", "0.1.0")
+            .instruction(
+                IdlInstruction::new("transfer", &[3])
+                    .account("owner", true, false)
+                    .account("mint", false, true)
+                    .arg::<Input>("input")
+                    .arg::<u8>("bump"),
+            )
+            .account::<State>("state", &[])
+            .error(501, "InsufficientBalance", Some("Not enough tokens"))
+            .errors::<Error>();
+
+        let types = idl
+            .types
+            .iter()
+            .map(|def| def.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec!["Input", "Kind", "State"]);
+
+        assert_eq!(idl.instructions[0].discriminator, vec![3]);
+        assert_eq!(idl.instructions[0].args[1].ty, IdlType::U8);
+        assert!(idl.instructions[0].accounts[0].is_signer);
+        assert_eq!(idl.accounts[0].ty, IdlType::Defined("State".to_string()));
+        assert_eq!(idl.errors[0].code, 501);
+        assert_eq!(
+            (idl.errors[1].code, idl.errors[1].msg.as_deref()),
+            (600, Some("Mint is over"))
+        );
+        assert_eq!(
+            (idl.errors[2].code, idl.errors[2].name.as_str()),
+            (601, "Frozen")
+        );
+    }
+
+    #[test]
+    fn test_instructions_from_derives() {
+        let idl = Idl::new("test", "0.1.0").instructions::<Instruction>();
+        assert_eq!(
+            idl.instructions,
+            vec![
+                IdlInstruction::new("transfer", &[3])
+                    .account("owner", true, false)
+                    .account("balance", false, true)
+                    .arg::<u64>("args"),
+                IdlInstruction::new("set_fees", &[4])
+                    .arg::<u16>("fee")
+                    .arg::<Pubkey>("recipient"),
+            ]
+        );
+    }
+}
@@ -1,7 +1,133 @@
+use bitcoin::hashes::Hash;
+use bitcoin::taproot::{TapLeafHash, TAPROOT_ANNEX_PREFIX};
+use bitcoin::TapSighashType;
+
+use crate::prelude::*;
+use crate::program_error::ProgramError;
 use crate::pubkey::Pubkey;
 
-#[derive(Clone, Debug, Eq, PartialEq, Copy)]
+/// An input of a [`TransactionToSign`](crate::transaction_to_sign::TransactionToSign)
+/// the runtime signs for `signer`
+///
+/// By default the signature is a key path spend with `SIGHASH_DEFAULT`.
+/// Programs sign partially with another [`TapSighashType`], spend a tapscript
+/// leaf with [`Self::with_leaf_hash`] and commit to an annex with
+/// [`Self::with_annex`].
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct InputToSign {
     pub index: u32,
     pub signer: Pubkey,
+    pub sighash_type: TapSighashType,
+    /// Leaf spent by a script path signature, `None` for a key path spend
+    pub leaf_hash: Option<TapLeafHash>,
+    /// Annex the signature commits to, starting with [`TAPROOT_ANNEX_PREFIX`]
+    pub annex: Option<Vec<u8>>,
 }
+
+impl InputToSign {
+    /// Key path signature of input `index` by `signer` with `SIGHASH_DEFAULT`
+    pub fn new(index: u32, signer: Pubkey) -> Self {
+        Self {
+            index,
+            signer,
+            sighash_type: TapSighashType::Default,
+            leaf_hash: None,
+            annex: None,
+        }
+    }
+
+    pub fn with_sighash_type(mut self, sighash_type: TapSighashType) -> Self {
+        self.sighash_type = sighash_type;
+        self
+    }
+
+    pub fn with_leaf_hash(mut self, leaf_hash: TapLeafHash) -> Self {
+        self.leaf_hash = Some(leaf_hash);
+        self
+    }
+
+    /// Fails with [`ProgramError::InvalidArgument`] when `annex` does not
+    /// start with [`TAPROOT_ANNEX_PREFIX`]
+    pub fn with_annex(mut self, annex: Vec<u8>) -> Result<Self, ProgramError> {
+        if annex.first() != Some(&TAPROOT_ANNEX_PREFIX) {
+            return Err(ProgramError::InvalidArgument);
+        }
+        self.annex = Some(annex);
+        Ok(self)
+    }
+
+    /// Whether the input serialises in the original format, which only
+    /// holds key path `SIGHASH_DEFAULT` signatures
+    pub fn is_legacy(&self) -> bool {
+        self.sighash_type == TapSighashType::Default
+            && self.leaf_hash.is_none()
+            && self.annex.is_none()
+    }
+
+    /// Length of [`Self::encode`] in serialisation `version`
+    pub(crate) fn serialised_len(&self, version: u8) -> usize {
+        if version == 0 {
+            return 4 + 32;
+        }
+        4 + 32
+            + 1
+            + 1
+            + self.leaf_hash.map_or(0, |_| 32)
+            + self.annex.as_ref().map_or(0, |annex| 4 + annex.len())
+    }
+
+    /// Writes the input in serialisation `version`: the index and signer,
+    /// then from version 1 the sighash type, a flags byte telling which of
+    /// the leaf hash and annex follow, and those.
+    pub(crate) fn encode(&self, version: u8, write: &mut impl FnMut(&[u8])) {
+        write(&self.index.to_le_bytes());
+        write(&self.signer.serialize());
+        if version == 0 {
+            return;
+        }
+        let flags = self.leaf_hash.map_or(0, |_| HAS_LEAF_HASH)
+            | self.annex.as_ref().map_or(0, |_| HAS_ANNEX);
+        write(&[self.sighash_type as u8, flags]);
+        if let Some(leaf_hash) = self.leaf_hash {
+            write(leaf_hash.as_byte_array());
+        }
+        if let Some(annex) = &self.annex {
+            write(&(annex.len() as u32).to_le_bytes());
+            write(annex);
+        }
+    }
+
+    /// Reads an input in serialisation `version` at `data[*size..]` and
+    /// advances `size` past it
+    ///
+    /// Panics if the input is truncated or malformed.
+    pub(crate) fn decode(data: &[u8], size: &mut usize, version: u8) -> Self {
+        let mut read = |len: usize| {
+            let bytes = &data[*size..*size + len];
+            *size += len;
+            bytes
+        };
+
+        let index = u32::from_le_bytes(read(4).try_into().unwrap());
+        let mut input_to_sign = Self::new(index, Pubkey::from_slice(read(32)));
+        if version == 0 {
+            return input_to_sign;
+        }
+
+        let [sighash_type, flags] = read(2).try_into().unwrap();
+        input_to_sign.sighash_type =
+            TapSighashType::from_consensus_u8(sighash_type).expect("invalid sighash type");
+        if flags & HAS_LEAF_HASH != 0 {
+            input_to_sign.leaf_hash =
+                Some(TapLeafHash::from_byte_array(read(32).try_into().unwrap()));
+        }
+        if flags & HAS_ANNEX != 0 {
+            let len = u32::from_le_bytes(read(4).try_into().unwrap()) as usize;
+            input_to_sign.annex = Some(read(len).to_vec());
+        }
+        input_to_sign
+    }
+}
+
+const HAS_LEAF_HASH: u8 = 1;
+const HAS_ANNEX: u8 = 1 << 1;
@@ -1,14 +1,15 @@
-use std::mem::size_of;
+use core::mem::size_of;
 
+use bitcoin::hashes::{sha256, Hash, HashEngine};
 use thiserror::Error;
 
+use crate::prelude::*;
 use crate::program_error::*;
 use crate::pubkey::Pubkey;
 use crate::{account::AccountMeta, program_error::ProgramError};
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
-use sha256::digest;
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 pub struct Instruction {
@@ -19,19 +20,37 @@ pub struct Instruction {
 
 impl Instruction {
     pub fn serialize(&self) -> Vec<u8> {
-        let mut serilized = vec![];
+        let mut serialized = Vec::with_capacity(self.serialized_len());
+        self.encode(&mut |bytes| serialized.extend_from_slice(bytes));
+        serialized
+    }
+
+    /// Length of [`Self::serialize`]
+    pub fn serialized_len(&self) -> usize {
+        32 + 1 + self.accounts.len() * size_of::<AccountMeta>() + size_of::<u64>() + self.data.len()
+    }
+
+    /// Writes [`Self::serialize`] at the start of `buf` without allocating
+    /// and returns its length
+    ///
+    /// Panics if `buf` is shorter than [`Self::serialized_len`].
+    pub fn serialize_into(&self, buf: &mut [u8]) -> usize {
+        let mut len = 0;
+        self.encode(&mut |bytes| {
+            buf[len..len + bytes.len()].copy_from_slice(bytes);
+            len += bytes.len();
+        });
+        len
+    }
 
-        serilized.extend(self.program_id.serialize());
-        // accounts length should fit in a u8
-        serilized.push(self.accounts.len() as u8);
+    pub(crate) fn encode(&self, write: &mut impl FnMut(&[u8])) {
+        write(&self.program_id.serialize());
+        write(&[self.accounts.len() as u8]);
         for meta in self.accounts.iter() {
-            serilized.extend(&meta.serialize());
+            write(&meta.serialize());
         }
-        // data length should fit in a u64
-        serilized.extend(self.data.len().to_le_bytes());
-        serilized.extend(&self.data);
-
-        serilized
+        write(&(self.data.len() as u64).to_le_bytes());
+        write(&self.data);
     }
 
     pub fn from_slice(data: &[u8]) -> Self {
@@ -54,8 +73,34 @@ impl Instruction {
     }
 
     pub fn hash(&self) -> String {
-        digest(digest(self.serialize()))
+        let hash = self.hash_hex();
+        String::from(core::str::from_utf8(&hash).expect("hex is ascii"))
+    }
+
+    /// [`Self::hash`] without allocating, as ASCII hex
+    pub fn hash_hex(&self) -> [u8; 64] {
+        let mut engine = sha256::Hash::engine();
+        self.encode(&mut |bytes| engine.input(bytes));
+        hex_double_digest(sha256::Hash::from_engine(engine))
+    }
+}
+
+/// The sha256 of the hex of `first`, itself in hex
+///
+/// Instructions and messages are hashed this way, the second hash running
+/// over the ASCII hex of the first.
+pub(crate) fn hex_double_digest(first: sha256::Hash) -> [u8; 64] {
+    hex_digest(&sha256::Hash::hash(&hex_digest(&first.to_byte_array())).to_byte_array())
+}
+
+fn hex_digest(bytes: &[u8; 32]) -> [u8; 64] {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut hex = [0; 64];
+    for (i, byte) in bytes.iter().enumerate() {
+        hex[2 * i] = HEX[(byte >> 4) as usize];
+        hex[2 * i + 1] = HEX[(byte & 0xf) as usize];
     }
+    hex
 }
 
 #[derive(Debug, Error, PartialEq, Eq, Clone)]
@@ -290,6 +335,26 @@ pub enum InstructionError {
     /// Invalid transaction to sign
     #[error("Invalid transaction to sign")]
     InvalidTxToSign,
+
+    /// Bitcoin transaction not found
+    #[error("Bitcoin transaction not found")]
+    BitcoinTxNotFound,
+
+    /// Bitcoin transaction truncated
+    #[error("Bitcoin transaction truncated")]
+    BitcoinTxTruncated,
+
+    /// Invalid SPV proof
+    #[error("Invalid SPV proof")]
+    InvalidSpvProof,
+
+    /// Return data too large
+    #[error("Return data too large")]
+    ReturnDataTooLarge,
+
+    /// Missing return data
+    #[error("Missing return data")]
+    MissingReturnData,
     // Note: For any new error added here an equivalent ProgramError and its
     // conversions must also be added
 }
@@ -325,6 +390,12 @@ impl From<u64> for InstructionError {
             ARITHMETIC_OVERFLOW => Self::ArithmeticOverflow,
             IMMUTABLE => Self::Immutable,
             INCORRECT_AUTHORITY => Self::IncorrectAuthority,
+            INVALID_TX_TO_SIGN => Self::InvalidTxToSign,
+            BITCOIN_TX_NOT_FOUND => Self::BitcoinTxNotFound,
+            BITCOIN_TX_TRUNCATED => Self::BitcoinTxTruncated,
+            INVALID_SPV_PROOF => Self::InvalidSpvProof,
+            RETURN_DATA_TOO_LARGE => Self::ReturnDataTooLarge,
+            MISSING_RETURN_DATA => Self::MissingReturnData,
             _ => {
                 // A valid custom error has no bits set in the upper 32
                 if value >> BUILTIN_BIT_SHIFT == 0 {
@@ -411,6 +482,14 @@ mod tests {
             let deserialized = Instruction::from_slice(&serialized);
 
             assert_eq!(instruction, deserialized);
+
+            let mut buf = vec![0; instruction.serialized_len()];
+            assert_eq!(instruction.serialize_into(&mut buf), buf.len());
+            assert_eq!(buf, serialized);
+            assert_eq!(
+                instruction.hash(),
+                ::sha256::digest(::sha256::digest(serialized))
+            );
         }
     }
 }
@@ -0,0 +1,156 @@
+//! Instructions of the executing message.
+//!
+//! A program sees only its own instruction through its entrypoint. These
+//! functions load the others, decoded from the [`Instruction`] wire format,
+//! so that programs guard how they are called:
+//!
+//! ```ignore
+//! // a mint must follow a payment to the treasury
+//! let index = get_current_instruction_index();
+//! let payment = load_relative_instruction(-1)?;
+//! if index == 0 || payment.program_id != PAYMENT_PROGRAM_ID {
+//!     return Err(ProgramError::InvalidInstructionData);
+//! }
+//!
+//! // only callable as the last instruction
+//! if index + 1 != get_instruction_count() {
+//!     return Err(ProgramError::InvalidInstructionData);
+//! }
+//! ```
+//!
+//! On the host the message comes from [`set_instructions`].
+
+use crate::instruction::Instruction;
+use crate::prelude::*;
+use crate::program_error::ProgramError;
+
+/// Index in the message of the instruction being executed
+pub fn get_current_instruction_index() -> usize {
+    #[cfg(target_os = "solana")]
+    let index = unsafe { crate::syscalls::arch_get_current_instruction_index() };
+
+    #[cfg(not(target_os = "solana"))]
+    let index = crate::program_stubs::arch_get_current_instruction_index();
+
+    index as usize
+}
+
+/// Number of instructions in the message
+pub fn get_instruction_count() -> usize {
+    #[cfg(target_os = "solana")]
+    let count = unsafe { crate::syscalls::arch_get_instruction_count() };
+
+    #[cfg(not(target_os = "solana"))]
+    let count = crate::program_stubs::arch_get_instruction_count();
+
+    count as usize
+}
+
+/// Instruction `index` of the message
+///
+/// Fails with [`ProgramError::InvalidArgument`] when the message has no
+/// instruction `index`.
+pub fn load_instruction(index: usize) -> Result<Instruction, ProgramError> {
+    let mut buf = [0u8; 1024];
+    match instruction(index, &mut buf) {
+        0 => Err(ProgramError::InvalidArgument),
+        len if len <= buf.len() => Ok(Instruction::from_slice(&buf[..len])),
+        len => {
+            let mut buf = vec![0; len];
+            instruction(index, &mut buf);
+            Ok(Instruction::from_slice(&buf))
+        }
+    }
+}
+
+/// Instruction `offset` places from the one being executed, -1 being the
+/// previous one
+///
+/// Fails with [`ProgramError::InvalidArgument`] when it falls outside the
+/// message.
+pub fn load_relative_instruction(offset: isize) -> Result<Instruction, ProgramError> {
+    let index = get_current_instruction_index()
+        .checked_add_signed(offset)
+        .ok_or(ProgramError::InvalidArgument)?;
+    load_instruction(index)
+}
+
+/// Makes the functions of this module see a message of `instructions`
+/// executing instruction `current`, so that programs unit test their guards
+/// on the host. Only the calling thread sees the message.
+#[cfg(all(not(target_os = "solana"), feature = "std"))]
+pub fn set_instructions(instructions: &[Instruction], current: usize) {
+    crate::program_stubs::set_instructions(instructions, current)
+}
+
+fn instruction(index: usize, buf: &mut [u8]) -> usize {
+    #[cfg(target_os = "solana")]
+    let len = unsafe {
+        crate::syscalls::arch_get_instruction(index as u64, buf.as_mut_ptr(), buf.len() as u64)
+    };
+
+    #[cfg(not(target_os = "solana"))]
+    let len = crate::program_stubs::arch_get_instruction(index, buf);
+
+    len as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::AccountMeta;
+    use crate::pubkey::Pubkey;
+    use proptest::prelude::*;
+
+    fn instruction(program: u8, data: Vec<u8>) -> Instruction {
+        Instruction {
+            program_id: Pubkey([program; 32]),
+            accounts: vec![AccountMeta {
+                pubkey: Pubkey([9; 32]),
+                is_signer: true,
+                is_writable: false,
+            }],
+            data,
+        }
+    }
+
+    #[test]
+    fn test_introspection() {
+        assert_eq!(get_instruction_count(), 0);
+        assert_eq!(load_instruction(0), Err(ProgramError::InvalidArgument));
+
+        let instructions = [
+            instruction(1, vec![1]),
+            // larger than the first read
+            instruction(2, vec![2; 2000]),
+            instruction(3, vec![]),
+        ];
+        set_instructions(&instructions, 1);
+
+        assert_eq!(get_current_instruction_index(), 1);
+        assert_eq!(get_instruction_count(), 3);
+        assert_eq!(load_instruction(1).as_ref(), Ok(&instructions[1]));
+        assert_eq!(load_relative_instruction(-1).as_ref(), Ok(&instructions[0]));
+        assert_eq!(load_relative_instruction(1).as_ref(), Ok(&instructions[2]));
+        assert_eq!(
+            load_relative_instruction(-2),
+            Err(ProgramError::InvalidArgument)
+        );
+        assert_eq!(load_instruction(3), Err(ProgramError::InvalidArgument));
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_load_instruction(data in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..1500), 1..5)) {
+            let instructions: Vec<Instruction> = data
+                .into_iter()
+                .enumerate()
+                .map(|(program, data)| instruction(program as u8, data))
+                .collect();
+            set_instructions(&instructions, 0);
+            for (index, expected) in instructions.iter().enumerate() {
+                prop_assert_eq!(&load_instruction(index).unwrap(), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,437 @@
+//! Entrypoint reading the program input on demand.
+//!
+//! [`entrypoint!`](crate::entrypoint!) deserializes every account into an
+//! [`AccountInfo`](crate::account::AccountInfo) up front, allocating for each
+//! of them. [`lazy_entrypoint!`](crate::lazy_entrypoint!) instead passes the
+//! program an [`InstructionContext`], a cursor over the input from which
+//! accounts are pulled one at a time as [`AccountView`]s, plain pointers into
+//! the input. Nothing is allocated, which keeps programs taking many accounts
+//! within their heap and compute limits.
+//!
+//! ```ignore
+//! lazy_entrypoint!(process_instruction);
+//!
+//! pub fn process_instruction(mut context: InstructionContext) -> ProgramResult {
+//!     let instruction_data = context.instruction_data();
+//!     let MaybeAccount::Account(counter) = context.next_account()? else {
+//!         return Err(ProgramError::InvalidArgument);
+//!     };
+//!     let mut data = counter.try_borrow_mut_data()?;
+//!     data[0] = data[0].wrapping_add(instruction_data[0]);
+//!     Ok(())
+//! }
+//! ```
+
+use core::marker::PhantomData;
+use core::mem::size_of;
+use core::ops::{Deref, DerefMut};
+use core::slice::{from_raw_parts, from_raw_parts_mut};
+
+use crate::entrypoint::{BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER};
+use crate::program_error::ProgramError;
+use crate::pubkey::Pubkey;
+use crate::utxo::UtxoMeta;
+
+// offsets in the serialized header of an account
+const BORROW_STATE: usize = 1;
+const IS_SIGNER: usize = 5;
+const IS_WRITABLE: usize = 6;
+const IS_EXECUTABLE: usize = 7;
+const KEY: usize = 8;
+const ORIGINAL_DATA_LEN: usize = KEY + size_of::<Pubkey>();
+const DATA_LEN: usize = ORIGINAL_DATA_LEN + size_of::<u64>();
+const DATA: usize = DATA_LEN + size_of::<u64>();
+
+/// Size of a duplicate entry, its index followed by padding
+const DUPLICATE_LEN: usize = 8;
+
+/// `BORROW_STATE` of mutably borrowed data, other values counting shared
+/// borrows
+const MUT_BORROWED: u8 = u8::MAX;
+
+/// Cursor over the program input, created by `lazy_entrypoint!`
+pub struct InstructionContext<'a> {
+    input: *mut u8,
+    /// Offset of the next account
+    offset: usize,
+    remaining: u64,
+    _input: PhantomData<&'a mut [u8]>,
+}
+
+/// An account of the input, or the index of an earlier account it repeats
+pub enum MaybeAccount<'a> {
+    Account(AccountView<'a>),
+    Duplicated(u8),
+}
+
+impl<'a> InstructionContext<'a> {
+    /// # Safety
+    ///
+    /// `input` must point to the program input as serialized by the runtime,
+    /// valid and not otherwise accessed for `'a`.
+    pub unsafe fn new(input: *mut u8) -> Self {
+        Self {
+            input,
+            offset: size_of::<u64>(),
+            remaining: *(input as *const u64),
+            _input: PhantomData,
+        }
+    }
+
+    /// Number of accounts not pulled yet
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Pulls the next account, failing with `NotEnoughAccountKeys` after the
+    /// last one
+    pub fn next_account(&mut self) -> Result<MaybeAccount<'a>, ProgramError> {
+        if self.remaining == 0 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        self.remaining -= 1;
+        // SAFETY: the offset is the start of an entry of the input
+        unsafe {
+            let entry = self.input.add(self.offset);
+            self.offset += entry_len(entry);
+            if *entry == NON_DUP_MARKER {
+                Ok(MaybeAccount::Account(AccountView {
+                    raw: entry,
+                    _input: PhantomData,
+                }))
+            } else {
+                Ok(MaybeAccount::Duplicated(*entry))
+            }
+        }
+    }
+
+    /// Offset of the instruction data length, past the remaining accounts
+    fn accounts_end(&self) -> usize {
+        let mut offset = self.offset;
+        for _ in 0..self.remaining {
+            // SAFETY: as in `next_account`
+            offset += unsafe { entry_len(self.input.add(offset)) };
+        }
+        offset
+    }
+
+    /// The instruction data, read without pulling the remaining accounts
+    pub fn instruction_data(&self) -> &'a [u8] {
+        let offset = self.accounts_end();
+        // SAFETY: the instruction data follows the accounts, prefixed by its length
+        unsafe {
+            let len = *(self.input.add(offset) as *const u64) as usize;
+            from_raw_parts(self.input.add(offset + size_of::<u64>()), len)
+        }
+    }
+
+    pub fn program_id(&self) -> &'a Pubkey {
+        let instruction_data = self.instruction_data();
+        // SAFETY: the program id follows the instruction data
+        unsafe { &*(instruction_data.as_ptr().add(instruction_data.len()) as *const Pubkey) }
+    }
+}
+
+/// Length of the input entry at `entry`
+unsafe fn entry_len(entry: *const u8) -> usize {
+    if *entry != NON_DUP_MARKER {
+        return DUPLICATE_LEN;
+    }
+    let data_len = *(entry.add(ORIGINAL_DATA_LEN) as *const u64) as usize;
+    let data_end = DATA + data_len + MAX_PERMITTED_DATA_INCREASE;
+    data_end.next_multiple_of(BPF_ALIGN_OF_U128) + size_of::<Pubkey>() + size_of::<UtxoMeta>() + 4
+}
+
+/// An account of the input, read in place
+///
+/// The borrows of its data are tracked in the padding of the serialized
+/// account, so that views of the same account share them.
+#[derive(Clone, Copy)]
+pub struct AccountView<'a> {
+    raw: *mut u8,
+    _input: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> AccountView<'a> {
+    pub fn key(&self) -> &'a Pubkey {
+        unsafe { &*(self.raw.add(KEY) as *const Pubkey) }
+    }
+
+    pub fn is_signer(&self) -> bool {
+        unsafe { *self.raw.add(IS_SIGNER) != 0 }
+    }
+
+    pub fn is_writable(&self) -> bool {
+        unsafe { *self.raw.add(IS_WRITABLE) != 0 }
+    }
+
+    pub fn is_executable(&self) -> bool {
+        unsafe { *self.raw.add(IS_EXECUTABLE) != 0 }
+    }
+
+    pub fn original_data_len(&self) -> usize {
+        unsafe { *(self.raw.add(ORIGINAL_DATA_LEN) as *const u64) as usize }
+    }
+
+    pub fn data_len(&self) -> usize {
+        unsafe { *(self.raw.add(DATA_LEN) as *const u64) as usize }
+    }
+
+    pub fn data_is_empty(&self) -> bool {
+        self.data_len() == 0
+    }
+
+    /// Start of the data, for telling which `AccountInfo` the view is of
+    pub(crate) fn data_ptr(&self) -> *const u8 {
+        unsafe { self.raw.add(DATA) }
+    }
+
+    /// Offset of the owner, after the data and its spare bytes
+    fn owner_offset(&self) -> usize {
+        (DATA + self.original_data_len() + MAX_PERMITTED_DATA_INCREASE)
+            .next_multiple_of(BPF_ALIGN_OF_U128)
+    }
+
+    pub fn owner(&self) -> &'a Pubkey {
+        unsafe { &*(self.raw.add(self.owner_offset()) as *const Pubkey) }
+    }
+
+    pub fn utxo(&self) -> &'a UtxoMeta {
+        let offset = self.owner_offset() + size_of::<Pubkey>();
+        unsafe { &*(self.raw.add(offset) as *const UtxoMeta) }
+    }
+
+    fn borrow_state(&self) -> *mut u8 {
+        unsafe { self.raw.add(BORROW_STATE) }
+    }
+
+    pub fn try_borrow_data(&self) -> Result<DataRef<'_>, ProgramError> {
+        // SAFETY: the borrow state is a padding byte of the account header
+        unsafe {
+            let state = self.borrow_state();
+            if *state >= MUT_BORROWED - 1 {
+                return Err(ProgramError::AccountBorrowFailed);
+            }
+            *state += 1;
+            Ok(DataRef {
+                data: from_raw_parts(self.raw.add(DATA), self.data_len()),
+                state,
+            })
+        }
+    }
+
+    pub fn try_borrow_mut_data(&self) -> Result<DataRefMut<'_>, ProgramError> {
+        // SAFETY: as in `try_borrow_data`, the data is not otherwise borrowed
+        unsafe {
+            let state = self.borrow_state();
+            if *state != 0 {
+                return Err(ProgramError::AccountBorrowFailed);
+            }
+            *state = MUT_BORROWED;
+            Ok(DataRefMut {
+                data: from_raw_parts_mut(self.raw.add(DATA), self.data_len()),
+                state,
+            })
+        }
+    }
+}
+
+/// Shared borrow of the data of an [`AccountView`]
+pub struct DataRef<'b> {
+    data: &'b [u8],
+    state: *mut u8,
+}
+
+impl Deref for DataRef<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.data
+    }
+}
+
+impl Drop for DataRef<'_> {
+    fn drop(&mut self) {
+        unsafe { *self.state -= 1 }
+    }
+}
+
+/// Mutable borrow of the data of an [`AccountView`]
+pub struct DataRefMut<'b> {
+    data: &'b mut [u8],
+    state: *mut u8,
+}
+
+impl Deref for DataRefMut<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.data
+    }
+}
+
+impl DerefMut for DataRefMut<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+}
+
+impl Drop for DataRefMut<'_> {
+    fn drop(&mut self) {
+        unsafe { *self.state = 0 }
+    }
+}
+
+/// Declares the program entrypoint, passing `$process_instruction` an
+/// [`InstructionContext`] over the input instead of deserialized accounts
+#[macro_export]
+macro_rules! lazy_entrypoint {
+    ($process_instruction:ident) => {
+        /// # Safety
+        #[no_mangle]
+        pub unsafe extern "C" fn entrypoint(input: *mut u8) -> u64 {
+            let context = unsafe { $crate::lazy_entrypoint::InstructionContext::new(input) };
+            match $process_instruction(context) {
+                Ok(()) => $crate::entrypoint::SUCCESS,
+                Err(error) => error.into(),
+            }
+        }
+        $crate::custom_heap_default!();
+        $crate::custom_panic_default!();
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entrypoint::{
+        deserialize,
+        test_input::{serialize_entries, TestAccount, TestEntry},
+    };
+    use proptest::prelude::*;
+
+    fn account(key: u8, data: Vec<u8>, is_signer: bool, is_writable: bool) -> TestEntry {
+        TestEntry::Account(TestAccount {
+            key: Pubkey([key; 32]),
+            owner: Pubkey([key + 100; 32]),
+            data,
+            is_signer,
+            is_writable,
+            is_executable: false,
+        })
+    }
+
+    fn view(account: MaybeAccount) -> AccountView {
+        match account {
+            MaybeAccount::Account(view) => view,
+            MaybeAccount::Duplicated(index) => panic!("duplicate of {}", index),
+        }
+    }
+
+    #[test]
+    fn test_cursor() {
+        let entries = [
+            account(1, vec![1, 2, 3], true, false),
+            account(2, vec![], false, true),
+            TestEntry::Duplicate(0),
+            account(3, vec![9; 40], false, false),
+        ];
+        let mut input = serialize_entries(&entries, &[5, 6], &Pubkey([7; 32]));
+        let mut context = unsafe { InstructionContext::new(input.as_mut_ptr() as *mut u8) };
+
+        assert_eq!(context.remaining(), 4);
+        assert_eq!(context.instruction_data(), &[5, 6]);
+        assert_eq!(context.program_id(), &Pubkey([7; 32]));
+
+        let first = view(context.next_account().unwrap());
+        assert_eq!(first.key(), &Pubkey([1; 32]));
+        assert_eq!(first.owner(), &Pubkey([101; 32]));
+        assert!(first.is_signer() && !first.is_writable());
+        assert_eq!(&*first.try_borrow_data().unwrap(), &[1, 2, 3]);
+
+        let second = view(context.next_account().unwrap());
+        assert!(second.data_is_empty() && second.is_writable());
+        assert!(matches!(
+            context.next_account(),
+            Ok(MaybeAccount::Duplicated(0))
+        ));
+        assert_eq!(context.instruction_data(), &[5, 6]);
+
+        let last = view(context.next_account().unwrap());
+        assert_eq!((last.key(), last.data_len()), (&Pubkey([3; 32]), 40));
+        assert_eq!(context.remaining(), 0);
+        assert!(matches!(
+            context.next_account(),
+            Err(ProgramError::NotEnoughAccountKeys)
+        ));
+        assert_eq!(context.program_id(), &Pubkey([7; 32]));
+    }
+
+    #[test]
+    fn test_data_borrows() {
+        let entries = [account(1, vec![0; 8], false, true)];
+        let mut input = serialize_entries(&entries, &[], &Pubkey([7; 32]));
+        let mut context = unsafe { InstructionContext::new(input.as_mut_ptr() as *mut u8) };
+        let account = view(context.next_account().unwrap());
+        let copy = account;
+
+        {
+            let mut data = account.try_borrow_mut_data().unwrap();
+            data[0] = 42;
+            assert!(copy.try_borrow_data().is_err());
+            assert!(copy.try_borrow_mut_data().is_err());
+        }
+        {
+            let (a, b) = (
+                account.try_borrow_data().unwrap(),
+                copy.try_borrow_data().unwrap(),
+            );
+            assert_eq!((a[0], b[0]), (42, 42));
+            assert!(account.try_borrow_mut_data().is_err());
+        }
+        assert!(account.try_borrow_mut_data().is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_matches_deserialize(
+            accounts in prop::collection::vec(
+                (prop::collection::vec(any::<u8>(), 0..100), any::<bool>(), any::<bool>(), any::<bool>()),
+                1..8,
+            ),
+            instruction_data in prop::collection::vec(any::<u8>(), 0..64),
+        ) {
+            let entries: Vec<TestEntry> = accounts
+                .into_iter()
+                .enumerate()
+                .map(|(i, (data, is_signer, is_writable, duplicate))| {
+                    if duplicate && i > 0 {
+                        TestEntry::Duplicate(0)
+                    } else {
+                        account(i as u8, data, is_signer, is_writable)
+                    }
+                })
+                .collect();
+            let mut input = serialize_entries(&entries, &instruction_data, &Pubkey([7; 32]));
+            let mut expected = input.clone();
+            let (program_id, infos, data) = unsafe { deserialize(expected.as_mut_ptr() as *mut u8) };
+
+            let mut context = unsafe { InstructionContext::new(input.as_mut_ptr() as *mut u8) };
+            prop_assert_eq!(context.instruction_data(), data);
+            let mut views = vec![];
+            for info in &infos {
+                let view = match context.next_account().unwrap() {
+                    MaybeAccount::Account(view) => view,
+                    MaybeAccount::Duplicated(index) => views[index as usize],
+                };
+                prop_assert_eq!(view.key(), info.key);
+                prop_assert_eq!(view.owner(), info.owner);
+                prop_assert_eq!(view.utxo(), info.utxo);
+                prop_assert_eq!((view.is_signer(), view.is_writable()), (info.is_signer, info.is_writable));
+                prop_assert_eq!(&*view.try_borrow_data().unwrap(), &**info.data.borrow());
+                views.push(view);
+            }
+            prop_assert_eq!(context.program_id(), program_id);
+        }
+    }
+}
@@ -1,29 +1,71 @@
+//! Types and syscall bindings for writing Arch programs.
+//!
+//! The crate builds without `std`, on `core` and `alloc` only, when its
+//! default `std` feature is turned off. Host-only conveniences, such as the
+//! syscall stubs printing logs, then do nothing.
+
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+
+extern crate alloc;
+
 use account::AccountInfo;
 use program_error::ProgramError;
 
 pub use bitcoin;
 
+// lets the derive macros refer to `::arch_program` from within this crate
+extern crate self as arch_program;
+
 pub mod account;
+pub mod account_state;
+pub mod accounts;
 pub mod atomic_u64;
 pub mod clock;
+pub mod crypto;
 pub mod debug_account_data;
 pub mod entrypoint;
+pub mod event;
 pub mod helper;
+pub mod idl;
 pub mod input_to_sign;
 pub mod instruction;
+pub mod introspection;
+pub mod lazy_entrypoint;
+pub mod loader_instruction;
 pub mod log;
 pub mod message;
 pub mod program;
 pub mod program_error;
+pub mod program_instruction;
 pub mod program_stubs;
 pub mod pubkey;
 pub mod sanitized;
+pub mod spv;
 pub mod stable_layout;
+pub mod state_transition;
 pub mod syscalls;
 pub mod system_instruction;
 pub mod transaction_to_sign;
 pub mod utxo;
 
+/// The `alloc` items missing from the `core` prelude
+mod prelude {
+    pub use alloc::{
+        boxed::Box,
+        format,
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+}
+
+/// `alloc` items for the code the crate's macros expand to, so that the
+/// macros also work in `no_std` crates
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::{format, string::String, vec, vec::Vec};
+}
+
 pub const MAX_BTC_TX_SIZE: usize = 1024;
 
 // Helper Funtions
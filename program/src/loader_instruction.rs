@@ -0,0 +1,403 @@
+//! Instructions and account state of the upgradeable program loader.
+//!
+//! Programs deployed through the loader record an upgrade authority. New code
+//! is first written into a staging buffer account and only becomes live when
+//! the authority sends `Deploy` (first deployment) or `Upgrade`. The authority
+//! can be transferred with `SetAuthority`, and `MakeImmutable` removes it for
+//! good.
+//!
+//! Buffer and program accounts owned by the loader start with a
+//! [`LoaderState`] header of [`LoaderState::LEN`] bytes, followed by the ELF.
+//!
+//! This is a client side proposal: no loader processor exists in this
+//! repository, and nodes do not yet handle [`Pubkey::loader_program`], this
+//! wire format or this account layout. Until a node implements them,
+//! transactions built from these instructions fail and the authority and
+//! immutability they describe are not enforced.
+
+use crate::account::AccountMeta;
+use crate::instruction::Instruction;
+use crate::prelude::*;
+use crate::program_error::ProgramError;
+use crate::pubkey::Pubkey;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LoaderInstruction {
+    /// Initializes a staging buffer owned by `authority`
+    ///
+    /// Accounts: `[buffer (signer, writable), authority (signer)]`
+    InitializeBuffer,
+    /// Writes `bytes` at `offset` of the buffer's ELF (after the header)
+    ///
+    /// Accounts: `[buffer (writable), authority (signer)]`
+    Write { offset: u32, bytes: Vec<u8> },
+    /// Deploys the buffer into an uninitialized program account, records the
+    /// buffer's authority as upgrade authority and makes the program executable
+    ///
+    /// Accounts: `[program (signer, writable), buffer (writable), authority (signer)]`
+    Deploy,
+    /// Replaces the code of an already deployed program with the buffer's content
+    ///
+    /// Accounts: `[program (writable), buffer (writable), authority (signer)]`
+    Upgrade,
+    /// Transfers the authority of a buffer or program to a new key
+    ///
+    /// Accounts: `[buffer or program (writable), authority (signer)]`
+    SetAuthority(Pubkey),
+    /// Removes the upgrade authority of a program, this cannot be undone
+    ///
+    /// Accounts: `[program (writable), authority (signer)]`
+    MakeImmutable,
+}
+
+impl LoaderInstruction {
+    pub fn serialise(&self) -> Vec<u8> {
+        let mut serialized = vec![];
+
+        match self {
+            Self::InitializeBuffer => serialized.push(0),
+            Self::Write { offset, bytes } => {
+                serialized.push(1);
+                serialized.extend(offset.to_le_bytes());
+                serialized.extend(bytes);
+            }
+            Self::Deploy => serialized.push(2),
+            Self::Upgrade => serialized.push(3),
+            Self::SetAuthority(new_authority) => {
+                serialized.push(4);
+                serialized.extend(new_authority.serialize());
+            }
+            Self::MakeImmutable => serialized.push(5),
+        }
+
+        serialized
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<Self, ProgramError> {
+        let (tag, rest) = data
+            .split_first()
+            .ok_or(ProgramError::InvalidInstructionData)?;
+
+        match tag {
+            0 => Ok(Self::InitializeBuffer),
+            1 => {
+                if rest.len() < 4 {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                Ok(Self::Write {
+                    offset: u32::from_le_bytes(rest[..4].try_into().unwrap()),
+                    bytes: rest[4..].to_vec(),
+                })
+            }
+            2 => Ok(Self::Deploy),
+            3 => Ok(Self::Upgrade),
+            4 => {
+                if rest.len() != 32 {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                Ok(Self::SetAuthority(Pubkey::from_slice(rest)))
+            }
+            5 => Ok(Self::MakeImmutable),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+
+    pub fn new_initialize_buffer_instruction(buffer: Pubkey, authority: Pubkey) -> Instruction {
+        Instruction {
+            program_id: Pubkey::loader_program(),
+            accounts: vec![
+                AccountMeta {
+                    pubkey: buffer,
+                    is_signer: true,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: authority,
+                    is_signer: true,
+                    is_writable: false,
+                },
+            ],
+            data: LoaderInstruction::InitializeBuffer.serialise(),
+        }
+    }
+
+    pub fn new_write_instruction(
+        buffer: Pubkey,
+        authority: Pubkey,
+        offset: u32,
+        bytes: Vec<u8>,
+    ) -> Instruction {
+        Instruction {
+            program_id: Pubkey::loader_program(),
+            accounts: vec![
+                AccountMeta {
+                    pubkey: buffer,
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: authority,
+                    is_signer: true,
+                    is_writable: false,
+                },
+            ],
+            data: LoaderInstruction::Write { offset, bytes }.serialise(),
+        }
+    }
+
+    pub fn new_deploy_instruction(
+        program: Pubkey,
+        buffer: Pubkey,
+        authority: Pubkey,
+    ) -> Instruction {
+        Instruction {
+            program_id: Pubkey::loader_program(),
+            accounts: vec![
+                AccountMeta {
+                    pubkey: program,
+                    is_signer: true,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: buffer,
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: authority,
+                    is_signer: true,
+                    is_writable: false,
+                },
+            ],
+            data: LoaderInstruction::Deploy.serialise(),
+        }
+    }
+
+    pub fn new_upgrade_instruction(
+        program: Pubkey,
+        buffer: Pubkey,
+        authority: Pubkey,
+    ) -> Instruction {
+        Instruction {
+            program_id: Pubkey::loader_program(),
+            accounts: vec![
+                AccountMeta {
+                    pubkey: program,
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: buffer,
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: authority,
+                    is_signer: true,
+                    is_writable: false,
+                },
+            ],
+            data: LoaderInstruction::Upgrade.serialise(),
+        }
+    }
+
+    pub fn new_set_authority_instruction(
+        account: Pubkey,
+        authority: Pubkey,
+        new_authority: Pubkey,
+    ) -> Instruction {
+        Instruction {
+            program_id: Pubkey::loader_program(),
+            accounts: vec![
+                AccountMeta {
+                    pubkey: account,
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: authority,
+                    is_signer: true,
+                    is_writable: false,
+                },
+            ],
+            data: LoaderInstruction::SetAuthority(new_authority).serialise(),
+        }
+    }
+
+    pub fn new_make_immutable_instruction(program: Pubkey, authority: Pubkey) -> Instruction {
+        Instruction {
+            program_id: Pubkey::loader_program(),
+            accounts: vec![
+                AccountMeta {
+                    pubkey: program,
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: authority,
+                    is_signer: true,
+                    is_writable: false,
+                },
+            ],
+            data: LoaderInstruction::MakeImmutable.serialise(),
+        }
+    }
+}
+
+/// Header stored at the start of every loader owned account
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LoaderState {
+    Uninitialized,
+    /// A staging buffer, writable by `authority`
+    Buffer {
+        authority: Pubkey,
+    },
+    /// A deployed program, upgradeable by `authority` or immutable if `None`
+    Program {
+        authority: Option<Pubkey>,
+    },
+}
+
+impl LoaderState {
+    /// Size of the serialized header: tag, authority flag and authority key
+    pub const LEN: usize = 2 + 32;
+
+    pub fn serialize(&self) -> [u8; Self::LEN] {
+        let mut serialized = [0; Self::LEN];
+
+        let (tag, authority) = match self {
+            Self::Uninitialized => (0, None),
+            Self::Buffer { authority } => (1, Some(authority)),
+            Self::Program { authority } => (2, authority.as_ref()),
+        };
+        serialized[0] = tag;
+        if let Some(authority) = authority {
+            serialized[1] = 1;
+            serialized[2..].copy_from_slice(&authority.serialize());
+        }
+
+        serialized
+    }
+
+    /// Decodes the header of a loader owned account. Empty data is `Uninitialized`.
+    pub fn from_slice(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.is_empty() {
+            return Ok(Self::Uninitialized);
+        }
+        if data.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let authority = match data[1] {
+            0 => None,
+            1 => Some(Pubkey::from_slice(&data[2..Self::LEN])),
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        match (data[0], authority) {
+            (0, None) => Ok(Self::Uninitialized),
+            (1, Some(authority)) => Ok(Self::Buffer { authority }),
+            (2, authority) => Ok(Self::Program { authority }),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
+    pub fn authority(&self) -> Option<&Pubkey> {
+        match self {
+            Self::Uninitialized => None,
+            Self::Buffer { authority } => Some(authority),
+            Self::Program { authority } => authority.as_ref(),
+        }
+    }
+
+    /// Checks that `signer` is allowed to modify the account holding this state
+    pub fn check_authority(&self, signer: &Pubkey) -> Result<(), ProgramError> {
+        match self {
+            Self::Uninitialized => Err(ProgramError::UninitializedAccount),
+            Self::Program { authority: None } => Err(ProgramError::Immutable),
+            _ if self.authority() == Some(signer) => Ok(()),
+            _ => Err(ProgramError::IncorrectAuthority),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoaderInstruction, LoaderState};
+    use crate::{program_error::ProgramError, pubkey::Pubkey};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_check_authority() {
+        let authority = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+
+        let buffer = LoaderState::Buffer { authority };
+        assert_eq!(buffer.check_authority(&authority), Ok(()));
+        assert_eq!(
+            buffer.check_authority(&other),
+            Err(ProgramError::IncorrectAuthority)
+        );
+
+        let immutable = LoaderState::Program { authority: None };
+        assert_eq!(
+            immutable.check_authority(&authority),
+            Err(ProgramError::Immutable)
+        );
+
+        assert_eq!(
+            LoaderState::Uninitialized.check_authority(&authority),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    #[test]
+    fn test_invalid_instruction_data() {
+        assert!(LoaderInstruction::from_slice(&[]).is_err());
+        assert!(LoaderInstruction::from_slice(&[1, 0, 0]).is_err());
+        assert!(LoaderInstruction::from_slice(&[4, 0]).is_err());
+        assert!(LoaderInstruction::from_slice(&[6]).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_serialize_deserialize_loader_instruction(
+            offset in any::<u32>(),
+            bytes in prop::collection::vec(any::<u8>(), 0..1024),
+            new_authority in any::<[u8; 32]>(),
+        ) {
+            let instructions = [
+                LoaderInstruction::InitializeBuffer,
+                LoaderInstruction::Write { offset, bytes: bytes.clone() },
+                LoaderInstruction::Deploy,
+                LoaderInstruction::Upgrade,
+                LoaderInstruction::SetAuthority(Pubkey::from(new_authority)),
+                LoaderInstruction::MakeImmutable,
+            ];
+
+            for instruction in instructions {
+                let serialized = instruction.serialise();
+                let deserialized = LoaderInstruction::from_slice(&serialized).unwrap();
+
+                assert_eq!(instruction, deserialized);
+            }
+        }
+
+        #[test]
+        fn fuzz_serialize_deserialize_loader_state(
+            authority in any::<[u8; 32]>(),
+            immutable in any::<bool>(),
+        ) {
+            let authority = Pubkey::from(authority);
+            let states = [
+                LoaderState::Uninitialized,
+                LoaderState::Buffer { authority },
+                LoaderState::Program { authority: (!immutable).then_some(authority) },
+            ];
+
+            for state in states {
+                assert_eq!(state, LoaderState::from_slice(&state.serialize()).unwrap());
+            }
+        }
+    }
+}
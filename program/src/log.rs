@@ -33,7 +33,10 @@
 //! [`Pubkey`]: crate::pubkey::Pubkey
 //! [`Pubkey::log`]: crate::pubkey::Pubkey::log
 
+use base64::{prelude::BASE64_STANDARD, Engine};
+
 use crate::account::AccountInfo;
+use crate::prelude::*;
 
 /// Print a message to the log.
 #[macro_export]
@@ -85,7 +88,7 @@ macro_rules! msg {
     ($msg:expr) => {
         $crate::log::sol_log($msg)
     };
-    ($($arg:tt)*) => ($crate::log::sol_log(&format!($($arg)*)));
+    ($($arg:tt)*) => ($crate::log::sol_log(&$crate::__private::format!($($arg)*)));
 }
 
 /// Print a string to the log.
@@ -108,8 +111,41 @@ pub fn sol_log_64(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64) {
 }
 
 /// Print some slices as base64.
+///
+/// The runtime logs them as a single [`PROGRAM_DATA`] line, see
+/// [`format_data_log`].
 pub fn sol_log_data(data: &[&[u8]]) {
-    unsafe { crate::syscalls::sol_log_data(data as *const _ as *const u8, data.len() as u64) };
+    #[cfg(target_os = "solana")]
+    unsafe {
+        crate::syscalls::sol_log_data(data as *const _ as *const u8, data.len() as u64)
+    };
+    #[cfg(not(target_os = "solana"))]
+    crate::program_stubs::sol_log_data(data);
+}
+
+/// Prefix of the log lines written by [`sol_log_data`]
+pub const PROGRAM_DATA: &str = "Program data: ";
+
+/// The log line of [`sol_log_data`]: [`PROGRAM_DATA`] followed by the base64
+/// of each slice, separated by spaces
+pub fn format_data_log(data: &[&[u8]]) -> String {
+    let mut log = String::from(PROGRAM_DATA);
+    for (i, slice) in data.iter().enumerate() {
+        if i > 0 {
+            log.push(' ');
+        }
+        BASE64_STANDARD.encode_string(slice, &mut log);
+    }
+    log
+}
+
+/// Slices of a [`sol_log_data`] log line, `None` for any other log
+pub fn parse_data_log(log: &str) -> Option<Vec<Vec<u8>>> {
+    log.strip_prefix(PROGRAM_DATA)?
+        .split(' ')
+        .filter(|field| !field.is_empty())
+        .map(|field| BASE64_STANDARD.decode(field).ok())
+        .collect()
 }
 
 /// Print the hexadecimal representation of a slice.
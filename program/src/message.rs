@@ -1,9 +1,10 @@
-use crate::instruction::Instruction;
+use crate::instruction::{hex_double_digest, Instruction};
+use crate::prelude::*;
 use crate::pubkey::Pubkey;
 
+use bitcoin::hashes::{sha256, Hash, HashEngine};
 use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
-use sha256::digest;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 pub struct Message {
@@ -13,18 +14,43 @@ pub struct Message {
 
 impl Message {
     pub fn serialize(&self) -> Vec<u8> {
-        let mut serialized = vec![];
+        let mut serialized = Vec::with_capacity(self.serialized_len());
+        self.encode(&mut |bytes| serialized.extend_from_slice(bytes));
+        serialized
+    }
+
+    /// Length of [`Self::serialize`]
+    pub fn serialized_len(&self) -> usize {
+        2 + self.signers.len() * 32
+            + self
+                .instructions
+                .iter()
+                .map(Instruction::serialized_len)
+                .sum::<usize>()
+    }
+
+    /// Writes [`Self::serialize`] at the start of `buf` without allocating
+    /// and returns its length
+    ///
+    /// Panics if `buf` is shorter than [`Self::serialized_len`].
+    pub fn serialize_into(&self, buf: &mut [u8]) -> usize {
+        let mut len = 0;
+        self.encode(&mut |bytes| {
+            buf[len..len + bytes.len()].copy_from_slice(bytes);
+            len += bytes.len();
+        });
+        len
+    }
 
-        serialized.push(self.signers.len() as u8);
+    fn encode(&self, write: &mut impl FnMut(&[u8])) {
+        write(&[self.signers.len() as u8]);
         for signer in self.signers.iter() {
-            serialized.extend(&signer.serialize());
+            write(&signer.serialize());
         }
-        serialized.push(self.instructions.len() as u8);
+        write(&[self.instructions.len() as u8]);
         for instruction in self.instructions.iter() {
-            serialized.extend(&instruction.serialize());
+            instruction.encode(write);
         }
-
-        serialized
     }
 
     pub fn from_slice(data: &[u8]) -> Self {
@@ -43,7 +69,7 @@ impl Message {
         let mut instructions = Vec::with_capacity(instructions_len);
         for _ in 0..instructions_len {
             instructions.push(Instruction::from_slice(&data[size..]));
-            size += instructions.last().unwrap().serialize().len();
+            size += instructions.last().unwrap().serialized_len();
         }
 
         Self {
@@ -53,9 +79,14 @@ impl Message {
     }
 
     pub fn hash(&self) -> Vec<u8> {
-        let serialized_message = self.serialize();
-        let first_hash = digest(serialized_message);
-        digest(first_hash.as_bytes()).as_bytes().to_vec()
+        self.hash_hex().to_vec()
+    }
+
+    /// [`Self::hash`] without allocating
+    pub fn hash_hex(&self) -> [u8; 64] {
+        let mut engine = sha256::Hash::engine();
+        self.encode(&mut |bytes| engine.input(bytes));
+        hex_double_digest(sha256::Hash::from_engine(engine))
     }
 }
 
@@ -134,6 +165,12 @@ mod tests {
             let deserialized = Message::from_slice(&serialized);
 
             assert_eq!(message, deserialized);
+
+            let mut buf = vec![0; message.serialized_len()];
+            assert_eq!(message.serialize_into(&mut buf), buf.len());
+            assert_eq!(buf, serialized);
+            let first_hash = ::sha256::digest(serialized);
+            assert_eq!(message.hash(), ::sha256::digest(first_hash).into_bytes());
         }
     }
 }
@@ -1,14 +1,19 @@
-use bitcoin::Transaction;
+use bitcoin::block::Header;
+use bitcoin::hashes::Hash;
+use bitcoin::{BlockHash, Transaction, TxOut};
+use borsh::BorshDeserialize;
 
 use crate::instruction::Instruction;
+use crate::prelude::*;
 use crate::program_error::ProgramError;
+use crate::spv::{decode_header, HEADER_LEN};
 #[cfg(target_os = "solana")]
 use crate::stable_layout::stable_ins::StableInstruction;
 use crate::MAX_BTC_TX_SIZE;
 
 use crate::clock::Clock;
 use crate::transaction_to_sign::TransactionToSign;
-use crate::utxo::UtxoMeta;
+use crate::utxo::{UtxoMeta, UtxoStatus};
 use crate::{account::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
 
 pub fn invoke(instruction: &Instruction, account_infos: &[AccountInfo]) -> ProgramResult {
@@ -78,13 +83,7 @@ pub fn set_transaction_to_sign(
             for input in transaction_to_sign.inputs_to_sign {
                 if let Some(account) = accounts.iter().find(|account| *account.key == input.signer)
                 {
-                    account.set_utxo(&UtxoMeta::from(
-                        hex::decode(tx.compute_txid().to_string())
-                            .expect("failed to decode_hex")
-                            .try_into()
-                            .expect("failed to try_into"),
-                        input.index,
-                    ));
+                    account.set_utxo(&UtxoMeta::from_outpoint(tx.compute_txid(), input.index));
                 }
             }
             Ok(())
@@ -101,10 +100,23 @@ pub const MAX_RETURN_DATA: usize = 1024;
 /// Return data is a dedicated per-transaction buffer for data passed
 /// from cross-program invoked programs back to their caller.
 ///
-/// The maximum size of return data is [`MAX_RETURN_DATA`]. Return data is
-/// retrieved by the caller with [`get_return_data`].
-pub fn set_return_data(data: &[u8]) {
-    unsafe { crate::syscalls::sol_set_return_data(data.as_ptr(), data.len() as u64) };
+/// The maximum size of return data is [`MAX_RETURN_DATA`], larger data fails
+/// with [`ProgramError::ReturnDataTooLarge`]. Return data is retrieved by the
+/// caller with [`get_return_data`].
+pub fn set_return_data(data: &[u8]) -> ProgramResult {
+    if data.len() > MAX_RETURN_DATA {
+        return Err(ProgramError::ReturnDataTooLarge);
+    }
+
+    #[cfg(target_os = "solana")]
+    unsafe {
+        crate::syscalls::sol_set_return_data(data.as_ptr(), data.len() as u64)
+    };
+
+    #[cfg(not(target_os = "solana"))]
+    crate::program_stubs::sol_set_return_data(data);
+
+    Ok(())
 }
 
 /// Get the return data from an invoked program.
@@ -137,15 +149,19 @@ pub fn set_return_data(data: &[u8]) {
 ///
 /// [rdp]: https://docs.solanalabs.com/proposals/return-data
 pub fn get_return_data() -> Option<(Pubkey, Vec<u8>)> {
-    use std::cmp::min;
+    use core::cmp::min;
 
     let mut buf = [0u8; MAX_RETURN_DATA];
     let mut program_id = Pubkey::default();
 
+    #[cfg(target_os = "solana")]
     let size = unsafe {
         crate::syscalls::sol_get_return_data(buf.as_mut_ptr(), buf.len() as u64, &mut program_id)
     };
 
+    #[cfg(not(target_os = "solana"))]
+    let size = crate::program_stubs::sol_get_return_data(&mut buf, &mut program_id);
+
     if size == 0 {
         None
     } else {
@@ -154,24 +170,182 @@ pub fn get_return_data() -> Option<(Pubkey, Vec<u8>)> {
     }
 }
 
+/// Invokes `instruction` like [`invoke`] and decodes the return data the
+/// invoked program set
+///
+/// Fails with [`ProgramError::MissingReturnData`] when the last return data
+/// was not set by `instruction.program_id`, which includes return data left
+/// by programs it invoked in turn.
+pub fn invoke_with_return<T: BorshDeserialize>(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+) -> Result<T, ProgramError> {
+    invoke(instruction, account_infos)?;
+    match get_return_data() {
+        Some((program_id, data)) if program_id == instruction.program_id => {
+            T::try_from_slice(&data).map_err(|e| ProgramError::BorshIoError(e.to_string()))
+        }
+        _ => Err(ProgramError::MissingReturnData),
+    }
+}
+
+/// Makes [`invoke`] on the host leave `return_data` behind, as if the invoked
+/// program set it, so that programs unit test [`invoke_with_return`]. Only
+/// the calling thread sees it.
+#[cfg(all(not(target_os = "solana"), feature = "std"))]
+pub fn set_invoke_return_data(return_data: Option<(Pubkey, Vec<u8>)>) {
+    crate::program_stubs::set_invoke_return_data(return_data)
+}
+
+/// Bitcoin transaction `txid`, `None` when the node does not know it or it
+/// is larger than [`MAX_BTC_TX_SIZE`], see [`try_get_bitcoin_tx`]
 pub fn get_bitcoin_tx(txid: [u8; 32]) -> Option<Vec<u8>> {
-    use std::cmp::min;
+    try_get_bitcoin_tx(txid).ok()
+}
+
+/// Bitcoin transaction `txid`, read on the stack through
+/// `arch_get_bitcoin_tx`, which every node serves
+///
+/// Fails with [`ProgramError::BitcoinTxNotFound`] when the node does not know
+/// the transaction and [`ProgramError::BitcoinTxTruncated`] when it is larger
+/// than [`MAX_BTC_TX_SIZE`], which [`get_large_bitcoin_tx`] reads. On the host
+/// the transactions come from [`set_bitcoin_tx`].
+pub fn try_get_bitcoin_tx(txid: [u8; 32]) -> Result<Vec<u8>, ProgramError> {
+    let mut buf = [0u8; MAX_BTC_TX_SIZE];
+    match bitcoin_tx(&txid, &mut buf) as usize {
+        0 => Err(ProgramError::BitcoinTxNotFound),
+        len if len <= MAX_BTC_TX_SIZE => Ok(buf[..len].to_vec()),
+        _ => Err(ProgramError::BitcoinTxTruncated),
+    }
+}
+
+/// Bitcoin transaction `txid`, however large
+///
+/// The first [`MAX_BTC_TX_SIZE`] bytes are read on the stack and the rest,
+/// if any, through `arch_get_bitcoin_tx_chunk` in a second read, so only
+/// nodes serving that syscall load programs calling this function. Fails
+/// with [`ProgramError::BitcoinTxNotFound`] when the node does not know the
+/// transaction and [`ProgramError::BitcoinTxTruncated`] when its length
+/// changes between the reads.
+pub fn get_large_bitcoin_tx(txid: [u8; 32]) -> Result<Vec<u8>, ProgramError> {
+    read_bitcoin_tx(|offset, buf| match offset {
+        0 => bitcoin_tx(&txid, buf),
+        offset => bitcoin_tx_chunk(&txid, offset, buf),
+    })
+}
+
+/// Makes the bitcoin transaction functions report `tx` for `txid`, or not
+/// know it when `None`, so that programs unit test their handling of
+/// transactions on the host. Only the calling thread sees the transaction.
+#[cfg(all(not(target_os = "solana"), feature = "std"))]
+pub fn set_bitcoin_tx(txid: [u8; 32], tx: Option<Transaction>) {
+    crate::program_stubs::set_bitcoin_tx(txid, tx)
+}
+
+/// Length of bitcoin transaction `txid`, `None` when the node does not know
+/// it
+pub fn get_bitcoin_tx_len(txid: [u8; 32]) -> Option<usize> {
+    match bitcoin_tx(&txid, &mut []) {
+        0 => None,
+        len => Some(len as usize),
+    }
+}
+
+/// Copies the bytes of bitcoin transaction `txid` from `offset` on into
+/// `buf` and returns how many were copied, 0 past the end of the transaction
+///
+/// Needs a node serving the `arch_get_bitcoin_tx_chunk` syscall.
+pub fn get_bitcoin_tx_chunk(
+    txid: [u8; 32],
+    offset: usize,
+    buf: &mut [u8],
+) -> Result<usize, ProgramError> {
+    match bitcoin_tx_chunk(&txid, offset, buf) {
+        0 => Err(ProgramError::BitcoinTxNotFound),
+        len => Ok((len as usize).saturating_sub(offset).min(buf.len())),
+    }
+}
+
+/// Output `vout` of bitcoin transaction `txid`, without reading the whole
+/// transaction
+///
+/// Needs a node serving the `arch_get_bitcoin_tx_output` syscall. Fails with
+/// [`ProgramError::BitcoinTxNotFound`] when the node does not know the
+/// transaction or it has no output `vout`.
+pub fn get_bitcoin_tx_output(txid: [u8; 32], vout: u32) -> Result<TxOut, ProgramError> {
+    let mut buf = [0u8; 128];
+    let len = bitcoin_tx_output(&txid, vout, &mut buf);
+    let output = match len {
+        0 => return Err(ProgramError::BitcoinTxNotFound),
+        len if len <= buf.len() => bitcoin::consensus::deserialize(&buf[..len]),
+        len => {
+            let mut buf = vec![0; len];
+            if bitcoin_tx_output(&txid, vout, &mut buf) != len {
+                return Err(ProgramError::BitcoinTxTruncated);
+            }
+            bitcoin::consensus::deserialize(&buf)
+        }
+    };
+    output.map_err(|_| ProgramError::InvalidAccountData)
+}
 
+/// Reads a whole transaction with `read(offset, buf)`, which copies the
+/// bytes from `offset` on into `buf` and returns the length of the
+/// transaction, 0 when it is unknown
+fn read_bitcoin_tx(mut read: impl FnMut(usize, &mut [u8]) -> u64) -> Result<Vec<u8>, ProgramError> {
     let mut buf = [0u8; MAX_BTC_TX_SIZE];
+    let len = match read(0, &mut buf) {
+        0 => return Err(ProgramError::BitcoinTxNotFound),
+        len => len as usize,
+    };
+    if len <= MAX_BTC_TX_SIZE {
+        return Ok(buf[..len].to_vec());
+    }
+
+    let mut tx = vec![0; len];
+    tx[..MAX_BTC_TX_SIZE].copy_from_slice(&buf);
+    if read(MAX_BTC_TX_SIZE, &mut tx[MAX_BTC_TX_SIZE..]) as usize != len {
+        return Err(ProgramError::BitcoinTxTruncated);
+    }
+    Ok(tx)
+}
 
+fn bitcoin_tx(txid: &[u8; 32], buf: &mut [u8]) -> u64 {
     #[cfg(target_os = "solana")]
-    let size =
-        unsafe { crate::syscalls::arch_get_bitcoin_tx(buf.as_mut_ptr(), buf.len() as u64, &txid) };
+    unsafe {
+        crate::syscalls::arch_get_bitcoin_tx(buf.as_mut_ptr(), buf.len() as u64, txid)
+    }
 
     #[cfg(not(target_os = "solana"))]
-    let size = crate::program_stubs::arch_get_bitcoin_tx(buf.as_mut_ptr(), buf.len(), &txid);
+    crate::program_stubs::arch_get_bitcoin_tx(buf.as_mut_ptr(), buf.len(), txid)
+}
 
-    if size == 0 {
-        None
-    } else {
-        let size = min(size as usize, MAX_BTC_TX_SIZE);
-        Some(buf[..size as usize].to_vec())
+fn bitcoin_tx_chunk(txid: &[u8; 32], offset: usize, buf: &mut [u8]) -> u64 {
+    #[cfg(target_os = "solana")]
+    unsafe {
+        crate::syscalls::arch_get_bitcoin_tx_chunk(
+            buf.as_mut_ptr(),
+            buf.len() as u64,
+            txid,
+            offset as u64,
+        )
     }
+
+    #[cfg(not(target_os = "solana"))]
+    crate::program_stubs::arch_get_bitcoin_tx_chunk(buf.as_mut_ptr(), buf.len(), txid, offset)
+}
+
+fn bitcoin_tx_output(txid: &[u8; 32], vout: u32, buf: &mut [u8]) -> usize {
+    #[cfg(target_os = "solana")]
+    let len = unsafe {
+        crate::syscalls::arch_get_bitcoin_tx_output(buf.as_mut_ptr(), buf.len() as u64, txid, vout)
+    };
+
+    #[cfg(not(target_os = "solana"))]
+    let len =
+        crate::program_stubs::arch_get_bitcoin_tx_output(buf.as_mut_ptr(), buf.len(), txid, vout);
+
+    len as usize
 }
 
 pub fn get_network_xonly_pubkey() -> [u8; 32] {
@@ -191,6 +365,53 @@ pub fn validate_utxo_ownership(utxo: &UtxoMeta, owner: &Pubkey) -> bool {
         crate::program_stubs::arch_validate_utxo_ownership(utxo, owner) != 0
     }
 }
+/// State of `utxo` on the bitcoin chain: its value, script pubkey,
+/// confirmations and whether it was spent
+///
+/// ```ignore
+/// if !get_utxo_status(&deposit)?.is_confirmed(3) {
+///     return Err(ProgramError::Custom(NOT_CONFIRMED));
+/// }
+/// ```
+///
+/// Fails with [`ProgramError::BitcoinTxNotFound`] when the node does not know
+/// the utxo. On the host the statuses come from [`set_utxo_status`].
+pub fn get_utxo_status(utxo: &UtxoMeta) -> Result<UtxoStatus, ProgramError> {
+    let mut buf = [0u8; 128];
+    let len = utxo_status(utxo, &mut buf);
+    let status = match len {
+        0 => return Err(ProgramError::BitcoinTxNotFound),
+        len if len <= buf.len() => UtxoStatus::try_from_slice(&buf[..len]),
+        len => {
+            let mut buf = vec![0; len];
+            if utxo_status(utxo, &mut buf) != len {
+                return Err(ProgramError::BitcoinTxTruncated);
+            }
+            UtxoStatus::try_from_slice(&buf)
+        }
+    };
+    status.map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Makes [`get_utxo_status`] report `status` for `utxo`, or not know it when
+/// `None`, so that programs unit test their confirmation logic on the host.
+/// Only the calling thread sees the status.
+#[cfg(all(not(target_os = "solana"), feature = "std"))]
+pub fn set_utxo_status(utxo: &UtxoMeta, status: Option<UtxoStatus>) {
+    crate::program_stubs::set_utxo_status(utxo, status)
+}
+
+fn utxo_status(utxo: &UtxoMeta, buf: &mut [u8]) -> usize {
+    #[cfg(target_os = "solana")]
+    let len =
+        unsafe { crate::syscalls::arch_get_utxo_status(utxo, buf.as_mut_ptr(), buf.len() as u64) };
+
+    #[cfg(not(target_os = "solana"))]
+    let len = crate::program_stubs::arch_get_utxo_status(utxo, buf);
+
+    len as usize
+}
+
 pub fn get_account_script_pubkey(pubkey: &Pubkey) -> [u8; 34] {
     let mut buf = [0u8; 34];
 
@@ -206,8 +427,292 @@ pub fn get_bitcoin_block_height() -> u64 {
     unsafe { crate::syscalls::arch_get_bitcoin_block_height() }
 }
 
+/// Hash of the bitcoin block at `height` of the best chain, `None` when the
+/// node does not know it
+pub fn get_bitcoin_block_hash(height: u64) -> Option<BlockHash> {
+    let mut hash = [0u8; 32];
+
+    #[cfg(target_os = "solana")]
+    let len = unsafe { crate::syscalls::arch_get_bitcoin_block_hash(height, hash.as_mut_ptr()) };
+
+    #[cfg(not(target_os = "solana"))]
+    let len = crate::program_stubs::arch_get_bitcoin_block_hash(height, &mut hash);
+
+    (len == 32).then(|| BlockHash::from_byte_array(hash))
+}
+
+/// Header of the bitcoin block at `height` of the best chain, `None` when the
+/// node does not know it. Check it with [`crate::spv`] when the node is not
+/// to be trusted.
+pub fn get_bitcoin_block_header(height: u64) -> Option<Header> {
+    let mut header = [0u8; HEADER_LEN];
+
+    #[cfg(target_os = "solana")]
+    let len =
+        unsafe { crate::syscalls::arch_get_bitcoin_block_header(height, header.as_mut_ptr()) };
+
+    #[cfg(not(target_os = "solana"))]
+    let len = crate::program_stubs::arch_get_bitcoin_block_header(height, &mut header);
+
+    if len as usize != HEADER_LEN {
+        return None;
+    }
+    decode_header(&header).ok()
+}
+
+/// Median time past of the bitcoin tip: the median timestamp of its last 11
+/// blocks, which unlike the block time never decreases. Bitcoin checks
+/// timelocks against it, so programs enforcing deadlines should too.
+pub fn get_bitcoin_median_time_past() -> Option<u32> {
+    #[cfg(target_os = "solana")]
+    let time = unsafe { crate::syscalls::arch_get_bitcoin_median_time_past() };
+
+    #[cfg(not(target_os = "solana"))]
+    let time = crate::program_stubs::arch_get_bitcoin_median_time_past();
+
+    match time {
+        0 => None,
+        time => Some(time as u32),
+    }
+}
+
+/// Makes the block functions report `header` as the block at `height`, or
+/// no block when `None`, so that programs unit test their chain logic on the
+/// host. The median time past is that of the 11 highest blocks set. Only the
+/// calling thread sees the blocks.
+#[cfg(all(not(target_os = "solana"), feature = "std"))]
+pub fn set_bitcoin_block_header(height: u64, header: Option<Header>) {
+    crate::program_stubs::set_bitcoin_block_header(height, header)
+}
+
 pub fn get_clock() -> Clock {
     let mut clock = Clock::default();
     unsafe { crate::syscalls::arch_get_clock(&mut clock) };
     clock
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Reads `tx` like the node, optionally changing its length by `growth`
+    /// after the first read
+    fn reader(tx: &[u8], growth: usize) -> impl FnMut(usize, &mut [u8]) -> u64 + '_ {
+        let mut reads = 0;
+        move |offset, buf| {
+            let len = tx.len() + if reads > 0 { growth } else { 0 };
+            reads += 1;
+            let chunk = &tx[offset.min(tx.len())..];
+            let copied = chunk.len().min(buf.len());
+            buf[..copied].copy_from_slice(&chunk[..copied]);
+            len as u64
+        }
+    }
+
+    #[test]
+    fn test_bitcoin_tx_stubs() {
+        assert_eq!(get_bitcoin_tx([1; 32]), None);
+        assert_eq!(get_bitcoin_tx_len([1; 32]), None);
+        assert_eq!(
+            try_get_bitcoin_tx([1; 32]),
+            Err(ProgramError::BitcoinTxNotFound)
+        );
+        assert_eq!(
+            get_large_bitcoin_tx([1; 32]),
+            Err(ProgramError::BitcoinTxNotFound)
+        );
+        assert_eq!(
+            get_bitcoin_tx_chunk([1; 32], 0, &mut [0; 8]),
+            Err(ProgramError::BitcoinTxNotFound)
+        );
+        assert_eq!(
+            get_bitcoin_tx_output([1; 32], 0),
+            Err(ProgramError::BitcoinTxNotFound)
+        );
+    }
+
+    #[test]
+    fn test_bitcoin_tx_longer_than_first_read() {
+        use bitcoin::{absolute::LockTime, transaction::Version, Amount, ScriptBuf};
+
+        let output = |value, script_len| TxOut {
+            value: Amount::from_sat(value),
+            script_pubkey: ScriptBuf::from_bytes(vec![0x6a; script_len]),
+        };
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![],
+            output: vec![output(1_000, 34), output(2_000, 2 * MAX_BTC_TX_SIZE)],
+        };
+        let serialized = bitcoin::consensus::serialize(&tx);
+        assert!(serialized.len() > MAX_BTC_TX_SIZE);
+        set_bitcoin_tx([3; 32], Some(tx.clone()));
+
+        assert_eq!(get_bitcoin_tx([3; 32]), None);
+        assert_eq!(
+            try_get_bitcoin_tx([3; 32]),
+            Err(ProgramError::BitcoinTxTruncated)
+        );
+        assert_eq!(get_large_bitcoin_tx([3; 32]), Ok(serialized.clone()));
+        assert_eq!(get_bitcoin_tx_len([3; 32]), Some(serialized.len()));
+        let mut chunk = [0; 16];
+        assert_eq!(
+            get_bitcoin_tx_chunk([3; 32], MAX_BTC_TX_SIZE, &mut chunk),
+            Ok(16)
+        );
+        assert_eq!(chunk, serialized[MAX_BTC_TX_SIZE..MAX_BTC_TX_SIZE + 16]);
+        let small = Transaction {
+            output: vec![output(1_000, 34)],
+            ..tx.clone()
+        };
+        set_bitcoin_tx([4; 32], Some(small.clone()));
+        let serialized_small = bitcoin::consensus::serialize(&small);
+        assert_eq!(get_bitcoin_tx([4; 32]), Some(serialized_small.clone()));
+        assert_eq!(get_large_bitcoin_tx([4; 32]), Ok(serialized_small));
+
+        assert_eq!(get_bitcoin_tx_output([3; 32], 0), Ok(tx.output[0].clone()));
+        assert_eq!(get_bitcoin_tx_output([3; 32], 1), Ok(tx.output[1].clone()));
+        assert_eq!(
+            get_bitcoin_tx_output([3; 32], 2),
+            Err(ProgramError::BitcoinTxNotFound)
+        );
+
+        set_bitcoin_tx([3; 32], None);
+        assert_eq!(
+            get_large_bitcoin_tx([3; 32]),
+            Err(ProgramError::BitcoinTxNotFound)
+        );
+    }
+
+    #[test]
+    fn test_read_bitcoin_tx_truncated() {
+        let tx = vec![7; MAX_BTC_TX_SIZE + 1];
+        assert_eq!(read_bitcoin_tx(reader(&tx, 0)), Ok(tx.clone()));
+        assert_eq!(
+            read_bitcoin_tx(reader(&tx, 1)),
+            Err(ProgramError::BitcoinTxTruncated)
+        );
+        assert_eq!(
+            read_bitcoin_tx(reader(&[], 0)),
+            Err(ProgramError::BitcoinTxNotFound)
+        );
+    }
+
+    #[test]
+    fn test_utxo_status() {
+        let utxo = UtxoMeta::from([2; 32], 1);
+        assert_eq!(get_utxo_status(&utxo), Err(ProgramError::BitcoinTxNotFound));
+
+        let status = UtxoStatus {
+            value: 10_000,
+            script_pubkey: vec![0x51, 0x20, 7],
+            confirmations: 2,
+            spent: false,
+        };
+        set_utxo_status(&utxo, Some(status.clone()));
+        assert_eq!(get_utxo_status(&utxo), Ok(status.clone()));
+        assert!(!get_utxo_status(&utxo).unwrap().is_confirmed(3));
+
+        // a script pubkey too large for the first read
+        let large = UtxoStatus {
+            script_pubkey: vec![0x6a; 200],
+            ..status
+        };
+        set_utxo_status(&utxo, Some(large.clone()));
+        assert_eq!(get_utxo_status(&utxo), Ok(large));
+
+        set_utxo_status(&utxo, None);
+        assert_eq!(get_utxo_status(&utxo), Err(ProgramError::BitcoinTxNotFound));
+    }
+
+    #[test]
+    fn test_bitcoin_blocks() {
+        assert_eq!(get_bitcoin_block_hash(0), None);
+        assert_eq!(get_bitcoin_block_header(0), None);
+        assert_eq!(get_bitcoin_median_time_past(), None);
+
+        let header = |height: u64| Header {
+            version: bitcoin::block::Version::TWO,
+            prev_blockhash: BlockHash::all_zeros(),
+            merkle_root: bitcoin::TxMerkleNode::all_zeros(),
+            // out of order times, as miners may set them
+            time: 1_700_000_000 + (height as u32 % 4) * 600,
+            bits: bitcoin::CompactTarget::from_consensus(0x207fffff),
+            nonce: height as u32,
+        };
+        for height in 0..14 {
+            set_bitcoin_block_header(height, Some(header(height)));
+        }
+
+        assert_eq!(get_bitcoin_block_header(5), Some(header(5)));
+        assert_eq!(get_bitcoin_block_hash(5), Some(header(5).block_hash()));
+        assert_eq!(get_bitcoin_block_hash(14), None);
+        // heights 3 to 13 add 3 x 0, 3 x 600, 2 x 1200 and 3 x 1800
+        assert_eq!(get_bitcoin_median_time_past(), Some(1_700_000_000 + 600));
+
+        set_bitcoin_block_header(5, None);
+        assert_eq!(get_bitcoin_block_header(5), None);
+    }
+
+    #[test]
+    fn test_return_data() {
+        assert_eq!(get_return_data(), None);
+        assert_eq!(
+            set_return_data(&[1; MAX_RETURN_DATA + 1]),
+            Err(ProgramError::ReturnDataTooLarge)
+        );
+
+        let program_id = Pubkey([4; 32]);
+        crate::introspection::set_instructions(
+            &[Instruction {
+                program_id,
+                accounts: vec![],
+                data: vec![],
+            }],
+            0,
+        );
+        set_return_data(&[1; MAX_RETURN_DATA]).unwrap();
+        assert_eq!(
+            get_return_data(),
+            Some((program_id, vec![1; MAX_RETURN_DATA]))
+        );
+        set_return_data(&[]).unwrap();
+        assert_eq!(get_return_data(), None);
+    }
+
+    #[test]
+    fn test_invoke_with_return() {
+        let instruction = Instruction {
+            program_id: Pubkey([5; 32]),
+            accounts: vec![],
+            data: vec![],
+        };
+        assert_eq!(
+            invoke_with_return::<u64>(&instruction, &[]),
+            Err(ProgramError::MissingReturnData)
+        );
+
+        set_invoke_return_data(Some((Pubkey([5; 32]), 7u64.to_le_bytes().to_vec())));
+        assert_eq!(invoke_with_return::<u64>(&instruction, &[]), Ok(7));
+        assert!(matches!(
+            invoke_with_return::<u32>(&instruction, &[]),
+            Err(ProgramError::BorshIoError(_))
+        ));
+
+        // set by a program the callee invoked
+        set_invoke_return_data(Some((Pubkey([6; 32]), 7u64.to_le_bytes().to_vec())));
+        assert_eq!(
+            invoke_with_return::<u64>(&instruction, &[]),
+            Err(ProgramError::MissingReturnData)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_read_bitcoin_tx(tx in prop::collection::vec(any::<u8>(), 1..4 * MAX_BTC_TX_SIZE)) {
+            prop_assert_eq!(read_bitcoin_tx(reader(&tx, 0)), Ok(tx));
+        }
+    }
+}
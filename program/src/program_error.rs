@@ -1,5 +1,8 @@
+use crate::prelude::*;
 use thiserror::Error;
 
+pub use arch_program_macros::CustomError;
+
 /// Reasons the program may fail
 #[derive(Clone, Debug, Eq, Error, PartialEq)]
 pub enum ProgramError {
@@ -54,6 +57,53 @@ pub enum ProgramError {
     Immutable,
     #[error("Incorrect authority provided")]
     IncorrectAuthority,
+    #[error("Invalid transaction to sign")]
+    InvalidTxToSign,
+    #[error("Bitcoin transaction not found")]
+    BitcoinTxNotFound,
+    #[error("Bitcoin transaction truncated")]
+    BitcoinTxTruncated,
+    #[error("Invalid SPV proof")]
+    InvalidSpvProof,
+    #[error("Return data too large")]
+    ReturnDataTooLarge,
+    #[error("Missing return data")]
+    MissingReturnData,
+}
+
+/// A program specific error returned as `ProgramError::Custom(code)`. Derive
+/// it with `#[derive(CustomError)]` on a fieldless enum: a variant's code is
+/// its discriminant, so pin the codes with explicit discriminants, and its
+/// message is given by `#[msg("...")]`.
+///
+/// ```ignore
+/// #[derive(Debug, CustomError)]
+/// pub enum TokenError {
+///     #[msg("The mint has reached its supply")]
+///     MintFinished = 600,
+///     #[msg("Not enough tokens in the balance account")]
+///     InsufficientBalance,
+/// }
+///
+/// return Err(TokenError::MintFinished.into());
+/// ```
+pub trait CustomError: Sized {
+    /// Code, variant name and message of every error
+    const ERRORS: &'static [(u32, &'static str, &'static str)];
+
+    fn code(&self) -> u32;
+
+    fn from_code(code: u32) -> Option<Self>;
+
+    fn message(&self) -> &'static str;
+
+    /// The error behind `ProgramError::Custom`
+    fn from_program_error(error: &ProgramError) -> Option<Self> {
+        match error {
+            ProgramError::Custom(code) => Self::from_code(*code),
+            _ => None,
+        }
+    }
 }
 
 /// Builtin return values occupy the upper 32 bits
@@ -90,6 +140,12 @@ pub const INVALID_ACCOUNT_OWNER: u64 = to_builtin!(23);
 pub const ARITHMETIC_OVERFLOW: u64 = to_builtin!(24);
 pub const IMMUTABLE: u64 = to_builtin!(25);
 pub const INCORRECT_AUTHORITY: u64 = to_builtin!(26);
+pub const INVALID_TX_TO_SIGN: u64 = to_builtin!(27);
+pub const BITCOIN_TX_NOT_FOUND: u64 = to_builtin!(28);
+pub const BITCOIN_TX_TRUNCATED: u64 = to_builtin!(29);
+pub const INVALID_SPV_PROOF: u64 = to_builtin!(30);
+pub const RETURN_DATA_TOO_LARGE: u64 = to_builtin!(31);
+pub const MISSING_RETURN_DATA: u64 = to_builtin!(32);
 // Warning: Any new program errors added here must also be:
 // - Added to the below conversions
 // - Added as an equivalent to InstructionError
@@ -128,6 +184,12 @@ impl From<ProgramError> for u64 {
             ProgramError::ArithmeticOverflow => ARITHMETIC_OVERFLOW,
             ProgramError::Immutable => IMMUTABLE,
             ProgramError::IncorrectAuthority => INCORRECT_AUTHORITY,
+            ProgramError::InvalidTxToSign => INVALID_TX_TO_SIGN,
+            ProgramError::BitcoinTxNotFound => BITCOIN_TX_NOT_FOUND,
+            ProgramError::BitcoinTxTruncated => BITCOIN_TX_TRUNCATED,
+            ProgramError::InvalidSpvProof => INVALID_SPV_PROOF,
+            ProgramError::ReturnDataTooLarge => RETURN_DATA_TOO_LARGE,
+            ProgramError::MissingReturnData => MISSING_RETURN_DATA,
             ProgramError::Custom(error) => {
                 if error == 0 {
                     CUSTOM_ZERO
@@ -168,7 +230,75 @@ impl From<u64> for ProgramError {
             ARITHMETIC_OVERFLOW => Self::ArithmeticOverflow,
             IMMUTABLE => Self::Immutable,
             INCORRECT_AUTHORITY => Self::IncorrectAuthority,
+            INVALID_TX_TO_SIGN => Self::InvalidTxToSign,
+            BITCOIN_TX_NOT_FOUND => Self::BitcoinTxNotFound,
+            BITCOIN_TX_TRUNCATED => Self::BitcoinTxTruncated,
+            INVALID_SPV_PROOF => Self::InvalidSpvProof,
+            RETURN_DATA_TOO_LARGE => Self::ReturnDataTooLarge,
+            MISSING_RETURN_DATA => Self::MissingReturnData,
             _ => Self::Custom(error as u32),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, CustomError)]
+    enum TestError {
+        #[msg("first error")]
+        First,
+        Second,
+        #[msg("pinned error")]
+        Pinned = 600,
+        AfterPinned,
+    }
+
+    #[test]
+    fn test_custom_error_codes() {
+        assert_eq!(
+            TestError::ERRORS,
+            &[
+                (0, "First", "first error"),
+                (1, "Second", "Second"),
+                (600, "Pinned", "pinned error"),
+                (601, "AfterPinned", "AfterPinned"),
+            ]
+        );
+        assert_eq!(
+            ProgramError::from(TestError::Pinned),
+            ProgramError::Custom(600)
+        );
+        assert_eq!(TestError::Pinned.to_string(), "pinned error");
+        assert_eq!(
+            TestError::from_program_error(&ProgramError::Custom(601)),
+            Some(TestError::AfterPinned)
+        );
+        assert_eq!(
+            TestError::from_program_error(&ProgramError::InvalidArgument),
+            None
+        );
+        // custom code 0 survives the u64 round trip of the runtime
+        let code: u64 = ProgramError::from(TestError::First).into();
+        assert_eq!(
+            TestError::from_program_error(&ProgramError::from(code)),
+            Some(TestError::First)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_custom_error_round_trip(code in any::<u32>()) {
+            match TestError::from_code(code) {
+                Some(error) => {
+                    prop_assert_eq!(error.code(), code);
+                    let raw: u64 = ProgramError::from(error).into();
+                    prop_assert_eq!(TestError::from_program_error(&ProgramError::from(raw)), Some(error));
+                }
+                None => prop_assert!(![0, 1, 600, 601].contains(&code)),
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Instruction enums with stable discriminators.
+//!
+//! Deriving [`ProgramInstruction`] on an enum gives every variant a
+//! discriminator, prefixed to the borsh encoded variant fields to form the
+//! instruction data. The discriminator is `#[instruction(discriminator = ..)]`,
+//! one byte or a byte array, and defaults to the first 8 bytes of
+//! `sha256("instruction:<Variant>")`, so that adding or reordering variants
+//! never changes the encoding of the others.
+//!
+//! A variant may name the [`Accounts`](crate::accounts::Accounts) struct it
+//! expects with `accounts = <Type>` and its `handler = <path>`. The derive then
+//! adds:
+//!
+//! - `process(program_id, accounts, instruction_data)`, which decodes the
+//!   instruction, validates the accounts and calls the handler with them and
+//!   the variant fields. It requires a handler on every variant.
+//! - one `new_<variant>_instruction(program_id, keys, fields..)` builder per
+//!   variant, returning the client side [`Instruction`](crate::instruction::Instruction)
+//!   with the account metas of `accounts`, or the given ones if it has none.
+//! - with `#[instruction(idl)]` on the enum, an
+//!   [`IdlInstructions`](crate::idl::IdlInstructions) implementation describing
+//!   every variant, its accounts and its fields.
+//!
+//! ```ignore
+//! #[derive(ProgramInstruction)]
+//! pub enum TokenInstruction {
+//!     #[instruction(discriminator = 2, accounts = MintTokensAccounts, handler = mint_tokens)]
+//!     MintTokens(MintInput),
+//! }
+//!
+//! entrypoint!(process_instruction);
+//! pub fn process_instruction(
+//!     program_id: &Pubkey,
+//!     accounts: &[AccountInfo],
+//!     instruction_data: &[u8],
+//! ) -> Result<(), ProgramError> {
+//!     TokenInstruction::process(program_id, accounts, instruction_data)
+//! }
+//! ```
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::prelude::*;
+use crate::program_error::ProgramError;
+
+pub use arch_program_macros::ProgramInstruction;
+
+/// An instruction enum encoded as a discriminator followed by the borsh
+/// encoded variant fields. Derive it with `#[derive(ProgramInstruction)]`.
+pub trait ProgramInstruction: Sized {
+    /// Discriminator of every variant, in declaration order
+    const DISCRIMINATORS: &'static [&'static [u8]];
+
+    fn discriminator(&self) -> &'static [u8];
+
+    /// Fails with `ProgramError::InvalidInstructionData` on an unknown
+    /// discriminator, undecodable fields or trailing bytes
+    fn unpack(instruction_data: &[u8]) -> Result<Self, ProgramError>;
+
+    fn pack(&self) -> Vec<u8>;
+}
+
+/// Decodes the next variant field of the instruction data
+pub fn decode_field<T: BorshDeserialize>(data: &mut &[u8]) -> Result<T, ProgramError> {
+    T::deserialize(data).map_err(|_| ProgramError::InvalidInstructionData)
+}
+
+pub fn encode_field<T: BorshSerialize>(field: &T, data: &mut Vec<u8>) {
+    field
+        .serialize(data)
+        .expect("serializing into a vec does not fail")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{AccountInfo, AccountMeta};
+    use crate::accounts::Accounts;
+    use crate::pubkey::Pubkey;
+    use crate::utxo::UtxoMeta;
+    use proptest::prelude::*;
+
+    #[derive(Accounts)]
+    struct Transfer<'a> {
+        #[account(signer)]
+        owner: AccountInfo<'a>,
+        #[account(mut)]
+        receiver: AccountInfo<'a>,
+    }
+
+    #[derive(Debug, PartialEq, ProgramInstruction)]
+    enum TestInstruction {
+        #[instruction(discriminator = 0, accounts = Transfer, handler = transfer)]
+        Transfer(u64),
+        #[instruction(discriminator = [1, 1], handler = set)]
+        Set { key: String, value: Option<u32> },
+        #[instruction(handler = reset)]
+        Reset,
+    }
+
+    fn transfer(accounts: &Transfer, amount: u64) -> Result<(), ProgramError> {
+        assert_eq!(accounts.owner.key, &Pubkey([1; 32]));
+        assert_eq!(accounts.receiver.key, &Pubkey([2; 32]));
+        if amount == 0 {
+            return Err(ProgramError::InsufficientFunds);
+        }
+        Ok(())
+    }
+
+    fn set(
+        _program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        key: String,
+        value: Option<u32>,
+    ) -> Result<(), ProgramError> {
+        assert!(accounts.is_empty());
+        assert_eq!((key.as_str(), value), ("k", Some(3)));
+        Ok(())
+    }
+
+    fn reset(_program_id: &Pubkey, _accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+        Err(ProgramError::Custom(1))
+    }
+
+    fn process(instruction_data: &[u8], is_signer: bool) -> Result<(), ProgramError> {
+        let keys = [Pubkey([1; 32]), Pubkey([2; 32])];
+        let utxo = UtxoMeta::from([0; 32], 0);
+        let (mut data0, mut data1) = (vec![], vec![]);
+        let accounts = [
+            AccountInfo::new(
+                &keys[0], &mut data0, &keys[1], &utxo, is_signer, false, false,
+            ),
+            AccountInfo::new(&keys[1], &mut data1, &keys[1], &utxo, false, true, false),
+        ];
+        let accounts = if instruction_data.first() == Some(&0) {
+            &accounts[..]
+        } else {
+            &[]
+        };
+        TestInstruction::process(&Pubkey([9; 32]), accounts, instruction_data)
+    }
+
+    #[test]
+    fn test_discriminators() {
+        let reset = hex::decode(&sha256::digest("instruction:Reset")[..16]).unwrap();
+        assert_eq!(
+            TestInstruction::DISCRIMINATORS,
+            &[&[0][..], &[1, 1], &reset[..]]
+        );
+        assert_eq!(TestInstruction::Transfer(5).discriminator(), &[0]);
+        assert_eq!(TestInstruction::Reset.discriminator(), &reset[..]);
+        assert_eq!(TestInstruction::Reset.pack(), reset);
+    }
+
+    #[test]
+    fn test_unpack_errors() {
+        for data in [
+            &[][..],
+            &[2],
+            &[1],
+            &[1, 1, 1, 0, 0, 0, b'k'],
+            &[0, 1, 0, 0],
+        ] {
+            assert_eq!(
+                TestInstruction::unpack(data),
+                Err(ProgramError::InvalidInstructionData),
+                "{:?}",
+                data
+            );
+        }
+        let mut trailing = TestInstruction::Transfer(1).pack();
+        trailing.push(0);
+        assert_eq!(
+            TestInstruction::unpack(&trailing),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+
+    #[test]
+    fn test_process() {
+        let transfer = TestInstruction::Transfer(5).pack();
+        assert_eq!(process(&transfer, true), Ok(()));
+        assert_eq!(
+            process(&transfer, false),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        assert_eq!(
+            process(&TestInstruction::Transfer(0).pack(), true),
+            Err(ProgramError::InsufficientFunds)
+        );
+        let set = TestInstruction::Set {
+            key: "k".to_string(),
+            value: Some(3),
+        };
+        assert_eq!(process(&set.pack(), true), Ok(()));
+        assert_eq!(
+            process(&TestInstruction::Reset.pack(), true),
+            Err(ProgramError::Custom(1))
+        );
+        assert_eq!(
+            process(&[], true),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+
+    #[test]
+    fn test_instruction_builders() {
+        let program_id = Pubkey([9; 32]);
+        let instruction = TestInstruction::new_transfer_instruction(
+            program_id,
+            TransferKeys {
+                owner: Pubkey([1; 32]),
+                receiver: Pubkey([2; 32]),
+            },
+            7,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(
+            instruction.accounts,
+            vec![
+                AccountMeta {
+                    pubkey: Pubkey([1; 32]),
+                    is_signer: true,
+                    is_writable: false,
+                },
+                AccountMeta {
+                    pubkey: Pubkey([2; 32]),
+                    is_signer: false,
+                    is_writable: true,
+                },
+            ]
+        );
+        assert_eq!(
+            TestInstruction::unpack(&instruction.data),
+            Ok(TestInstruction::Transfer(7))
+        );
+
+        let instruction =
+            TestInstruction::new_set_instruction(program_id, vec![], "k".to_string(), None);
+        assert!(instruction.accounts.is_empty());
+        assert_eq!(
+            TestInstruction::unpack(&instruction.data),
+            Ok(TestInstruction::Set {
+                key: "k".to_string(),
+                value: None
+            })
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_pack_unpack(amount in any::<u64>(), key in ".*", value in any::<Option<u32>>()) {
+            for instruction in [
+                TestInstruction::Transfer(amount),
+                TestInstruction::Set { key: key.clone(), value },
+                TestInstruction::Reset,
+            ] {
+                prop_assert_eq!(TestInstruction::unpack(&instruction.pack()), Ok(instruction));
+            }
+        }
+
+        #[test]
+        fn fuzz_unpack_never_panics(data in prop::collection::vec(any::<u8>(), 0..64)) {
+            if let Ok(instruction) = TestInstruction::unpack(&data) {
+                prop_assert_eq!(instruction.pack(), data);
+            }
+        }
+    }
+}
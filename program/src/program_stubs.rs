@@ -4,46 +4,404 @@
 #![allow(dead_code)]
 
 pub const UNIMPLEMENTED: u64 = 0;
+use crate::prelude::*;
 use crate::{
     account::AccountInfo, entrypoint::ProgramResult, instruction::Instruction, pubkey::Pubkey,
     utxo::UtxoMeta,
 };
 
 pub(crate) fn sol_log(message: &str) {
-    println!("{message}");
+    #[cfg(feature = "std")]
+    std::println!("{message}");
+    #[cfg(not(feature = "std"))]
+    let _ = message;
 }
 pub(crate) fn sol_log_64_(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64) {
     sol_log(&format!("{arg1:?}, {arg2:?},{arg3:?},{arg4:?},{arg5:?}"))
 }
-pub(crate) fn sol_set_return_data(_data: *const u8, _length: u64) {
-    sol_log("UNAVAILABLE");
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// Return data of the transaction on this thread and the program that set
+    /// it
+    static RETURN_DATA: core::cell::RefCell<Option<(Pubkey, Vec<u8>)>> =
+        const { core::cell::RefCell::new(None) };
+    /// Return data [`sol_invoke_signed_rust`] leaves behind on this thread, as
+    /// if set by the invoked program, set by
+    /// [`crate::program::set_invoke_return_data`]
+    static INVOKE_RETURN_DATA: core::cell::RefCell<Option<(Pubkey, Vec<u8>)>> =
+        const { core::cell::RefCell::new(None) };
+}
+#[cfg(feature = "std")]
+pub(crate) fn set_invoke_return_data(return_data: Option<(Pubkey, Vec<u8>)>) {
+    INVOKE_RETURN_DATA.set(return_data);
+}
+/// Sets the return data for the program of the instruction executing in
+/// [`INSTRUCTIONS`], the default key when none is
+pub(crate) fn sol_set_return_data(data: &[u8]) {
+    #[cfg(feature = "std")]
+    {
+        let program_id = INSTRUCTIONS.with_borrow(|(instructions, current)| {
+            instructions
+                .get(*current)
+                .map_or(Pubkey::default(), |instruction| {
+                    Instruction::from_slice(instruction).program_id
+                })
+        });
+        RETURN_DATA.set((!data.is_empty()).then(|| (program_id, data.to_vec())));
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        let _ = data;
+        sol_log("UNAVAILABLE");
+    }
 }
 pub(crate) fn sol_log_pubkey(_pubkey_addr: *const u8) {
     sol_log("UNAVAILABLE");
 }
-pub(crate) fn sol_log_data(_data: *const u8, _data_len: u64) {
-    sol_log("UNAVAILABLE");
+pub(crate) fn sol_log_data(data: &[&[u8]]) {
+    sol_log(&crate::log::format_data_log(data));
+}
+pub(crate) fn sol_get_return_data(buf: &mut [u8], program_id: &mut Pubkey) -> u64 {
+    #[cfg(feature = "std")]
+    if let Some((setter, data)) = RETURN_DATA.with_borrow(Option::clone) {
+        let copied = data.len().min(buf.len());
+        buf[..copied].copy_from_slice(&data[..copied]);
+        *program_id = setter;
+        return data.len() as u64;
+    }
+    let _ = (buf, program_id);
+    0
 }
-pub(crate) fn sol_get_return_data(_data: *mut u8, _length: u64, _program_id: *mut Pubkey) -> u64 {
+pub(crate) fn arch_set_transaction_to_sign(_transaction_to_sign: *const u8, _length: usize) -> u64 {
     sol_log("UNAVAILABLE");
     UNIMPLEMENTED
 }
-pub(crate) fn arch_set_transaction_to_sign(_transaction_to_sign: *const u8, _length: usize) -> u64 {
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// Transactions by txid the bitcoin transaction syscalls report on this
+    /// thread, set by [`crate::program::set_bitcoin_tx`]
+    static BITCOIN_TXS: core::cell::RefCell<alloc::collections::BTreeMap<[u8; 32], bitcoin::Transaction>> =
+        const { core::cell::RefCell::new(alloc::collections::BTreeMap::new()) };
+}
+#[cfg(feature = "std")]
+pub(crate) fn set_bitcoin_tx(txid: [u8; 32], tx: Option<bitcoin::Transaction>) {
+    BITCOIN_TXS.with_borrow_mut(|txs| match tx {
+        Some(tx) => txs.insert(txid, tx),
+        None => txs.remove(&txid),
+    });
+}
+fn bitcoin_tx(txid: &[u8; 32]) -> Option<bitcoin::Transaction> {
+    #[cfg(feature = "std")]
+    return BITCOIN_TXS.with_borrow(|txs| txs.get(txid).cloned());
+    #[cfg(not(feature = "std"))]
+    {
+        let _ = txid;
+        None
+    }
+}
+/// Copies `bytes` from `offset` on into the buffer and returns their length,
+/// like the node does
+fn copy_bytes(bytes: &[u8], offset: usize, buf: *mut u8, buf_len: usize) -> u64 {
+    let chunk = &bytes[offset.min(bytes.len())..];
+    let copied = chunk.len().min(buf_len);
+    unsafe { core::ptr::copy_nonoverlapping(chunk.as_ptr(), buf, copied) };
+    bytes.len() as u64
+}
+pub(crate) fn arch_get_bitcoin_tx(buf: *mut u8, buf_len: usize, txid: &[u8; 32]) -> u64 {
+    arch_get_bitcoin_tx_chunk(buf, buf_len, txid, 0)
+}
+pub(crate) fn arch_get_bitcoin_tx_chunk(
+    buf: *mut u8,
+    buf_len: usize,
+    txid: &[u8; 32],
+    offset: usize,
+) -> u64 {
+    match bitcoin_tx(txid) {
+        Some(tx) => copy_bytes(&bitcoin::consensus::serialize(&tx), offset, buf, buf_len),
+        None => {
+            sol_log("UNAVAILABLE");
+            UNIMPLEMENTED
+        }
+    }
+}
+pub(crate) fn arch_get_bitcoin_tx_output(
+    buf: *mut u8,
+    buf_len: usize,
+    txid: &[u8; 32],
+    vout: u32,
+) -> u64 {
+    let output = bitcoin_tx(txid).and_then(|tx| tx.output.get(vout as usize).cloned());
+    match output {
+        Some(output) => copy_bytes(&bitcoin::consensus::serialize(&output), 0, buf, buf_len),
+        None => {
+            sol_log("UNAVAILABLE");
+            UNIMPLEMENTED
+        }
+    }
+}
+pub(crate) fn arch_get_network_xonly_pubkey(_data: *mut u8) -> u64 {
     sol_log("UNAVAILABLE");
     UNIMPLEMENTED
 }
-pub(crate) fn arch_get_bitcoin_tx(_buf: *const u8, _buf_len: usize, _txid: &[u8; 32]) -> u64 {
+pub(crate) fn arch_validate_utxo_ownership(_utxo: *const UtxoMeta, _owner: *const Pubkey) -> u64 {
     sol_log("UNAVAILABLE");
     UNIMPLEMENTED
 }
-pub(crate) fn arch_get_network_xonly_pubkey(_data: *mut u8) -> u64 {
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// Statuses [`arch_get_utxo_status`] reports on this thread, set by
+    /// [`crate::program::set_utxo_status`]
+    static UTXO_STATUSES: core::cell::RefCell<alloc::collections::BTreeMap<[u8; 36], crate::utxo::UtxoStatus>> =
+        const { core::cell::RefCell::new(alloc::collections::BTreeMap::new()) };
+}
+#[cfg(feature = "std")]
+pub(crate) fn set_utxo_status(utxo: &UtxoMeta, status: Option<crate::utxo::UtxoStatus>) {
+    UTXO_STATUSES.with_borrow_mut(|statuses| match status {
+        Some(status) => statuses.insert(utxo.serialize(), status),
+        None => statuses.remove(&utxo.serialize()),
+    });
+}
+pub(crate) fn arch_get_utxo_status(utxo: &UtxoMeta, buf: &mut [u8]) -> u64 {
+    #[cfg(feature = "std")]
+    if let Some(status) =
+        UTXO_STATUSES.with_borrow(|statuses| statuses.get(&utxo.serialize()).cloned())
+    {
+        let status = borsh::to_vec(&status).expect("writing to a vec does not fail");
+        let copied = status.len().min(buf.len());
+        buf[..copied].copy_from_slice(&status[..copied]);
+        return status.len() as u64;
+    }
+    let _ = (utxo, buf);
     sol_log("UNAVAILABLE");
     UNIMPLEMENTED
 }
-pub(crate) fn arch_validate_utxo_ownership(_utxo: *const UtxoMeta, _owner: *const Pubkey) -> u64 {
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// Block headers by height the block syscalls report on this thread, set
+    /// by [`crate::program::set_bitcoin_block_header`]
+    static BLOCK_HEADERS: core::cell::RefCell<alloc::collections::BTreeMap<u64, bitcoin::block::Header>> =
+        const { core::cell::RefCell::new(alloc::collections::BTreeMap::new()) };
+}
+#[cfg(feature = "std")]
+pub(crate) fn set_bitcoin_block_header(height: u64, header: Option<bitcoin::block::Header>) {
+    BLOCK_HEADERS.with_borrow_mut(|headers| match header {
+        Some(header) => headers.insert(height, header),
+        None => headers.remove(&height),
+    });
+}
+fn block_header(height: u64) -> Option<bitcoin::block::Header> {
+    #[cfg(feature = "std")]
+    return BLOCK_HEADERS.with_borrow(|headers| headers.get(&height).copied());
+    #[cfg(not(feature = "std"))]
+    {
+        let _ = height;
+        None
+    }
+}
+pub(crate) fn arch_get_bitcoin_block_hash(height: u64, hash: &mut [u8; 32]) -> u64 {
+    use bitcoin::hashes::Hash;
+
+    match block_header(height) {
+        Some(header) => {
+            *hash = header.block_hash().to_byte_array();
+            32
+        }
+        None => {
+            sol_log("UNAVAILABLE");
+            UNIMPLEMENTED
+        }
+    }
+}
+pub(crate) fn arch_get_bitcoin_block_header(height: u64, buf: &mut [u8; 80]) -> u64 {
+    use bitcoin::consensus::Encodable;
+
+    match block_header(height) {
+        Some(header) => header
+            .consensus_encode(&mut &mut buf[..])
+            .expect("a header is 80 bytes") as u64,
+        None => {
+            sol_log("UNAVAILABLE");
+            UNIMPLEMENTED
+        }
+    }
+}
+/// Median time of the 11 highest headers set, as bitcoin computes it for the
+/// tip
+pub(crate) fn arch_get_bitcoin_median_time_past() -> u64 {
+    #[cfg(feature = "std")]
+    {
+        let mut times: Vec<u32> = BLOCK_HEADERS.with_borrow(|headers| {
+            headers
+                .values()
+                .rev()
+                .take(11)
+                .map(|header| header.time)
+                .collect()
+        });
+        if !times.is_empty() {
+            times.sort_unstable();
+            return times[times.len() / 2] as u64;
+        }
+    }
     sol_log("UNAVAILABLE");
     UNIMPLEMENTED
 }
+pub(crate) fn arch_sha256(vals: &[&[u8]], hash: &mut [u8; 32]) -> u64 {
+    use bitcoin::hashes::{sha256, Hash, HashEngine};
+
+    let mut engine = sha256::Hash::engine();
+    vals.iter().for_each(|val| engine.input(val));
+    *hash = sha256::Hash::from_engine(engine).to_byte_array();
+    0
+}
+pub(crate) fn arch_double_sha256(vals: &[&[u8]], hash: &mut [u8; 32]) -> u64 {
+    use bitcoin::hashes::{sha256d, Hash, HashEngine};
+
+    let mut engine = sha256d::Hash::engine();
+    vals.iter().for_each(|val| engine.input(val));
+    *hash = sha256d::Hash::from_engine(engine).to_byte_array();
+    0
+}
+pub(crate) fn arch_tagged_hash(tag: &[u8], vals: &[&[u8]], hash: &mut [u8; 32]) -> u64 {
+    let mut tag_hash = [0u8; 32];
+    arch_sha256(&[tag], &mut tag_hash);
+    let mut tagged = vec![&tag_hash[..], &tag_hash[..]];
+    tagged.extend_from_slice(vals);
+    arch_sha256(&tagged, hash)
+}
+pub(crate) fn arch_verify_schnorr(
+    signature: &[u8; 64],
+    message: &[u8; 32],
+    pubkey: &[u8; 32],
+) -> u64 {
+    use bitcoin::secp256k1::{schnorr::Signature, Message, Secp256k1, XOnlyPublicKey};
+
+    let (Ok(signature), Ok(pubkey)) = (
+        Signature::from_slice(signature),
+        XOnlyPublicKey::from_slice(pubkey),
+    ) else {
+        return 0;
+    };
+    Secp256k1::verification_only()
+        .verify_schnorr(&signature, &Message::from_digest(*message), &pubkey)
+        .is_ok() as u64
+}
+/// BIP-322 simple verification of a taproot key path signature, the
+/// `to_spend` and `to_sign` transactions built as the `bip322` crate does
+pub(crate) fn arch_verify_bip322(
+    message: &[u8],
+    pubkey: &[u8; 32],
+    signature: &[u8; 64],
+    uses_sighash_all: bool,
+) -> u64 {
+    use bitcoin::hashes::Hash;
+    use bitcoin::key::TapTweak;
+    use bitcoin::secp256k1::{Secp256k1, XOnlyPublicKey};
+    use bitcoin::sighash::{Prevouts, SighashCache};
+    use bitcoin::{
+        absolute::LockTime, opcodes, script, transaction::Version, Amount, OutPoint, ScriptBuf,
+        Sequence, TapSighashType, Transaction, TxIn, TxOut, Txid, Witness,
+    };
+
+    let Ok(pubkey) = XOnlyPublicKey::from_slice(pubkey) else {
+        return 0;
+    };
+    let secp = Secp256k1::verification_only();
+
+    let mut message_hash = [0u8; 32];
+    arch_tagged_hash(b"BIP0322-signed-message", &[message], &mut message_hash);
+    let to_spend = Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(Txid::all_zeros(), 0xFFFFFFFF),
+            script_sig: script::Builder::new()
+                .push_int(0)
+                .push_slice(message_hash)
+                .into_script(),
+            sequence: Sequence(0),
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: ScriptBuf::new_p2tr(&secp, pubkey, None),
+        }],
+    };
+    let to_sign = Transaction {
+        version: Version(0),
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(to_spend.compute_txid(), 0),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence(0),
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: script::Builder::new()
+                .push_opcode(opcodes::all::OP_RETURN)
+                .into_script(),
+        }],
+    };
+
+    let sighash_type = if uses_sighash_all {
+        TapSighashType::All
+    } else {
+        TapSighashType::Default
+    };
+    let Ok(sighash) = SighashCache::new(&to_sign).taproot_key_spend_signature_hash(
+        0,
+        &Prevouts::All(&to_spend.output),
+        sighash_type,
+    ) else {
+        return 0;
+    };
+    let tweaked = pubkey
+        .tap_tweak(&secp, None)
+        .0
+        .to_x_only_public_key()
+        .serialize();
+    arch_verify_schnorr(signature, &sighash.to_byte_array(), &tweaked)
+}
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// Serialised instructions of the message the introspection syscalls
+    /// report on this thread and the index of the one executing, set by
+    /// [`crate::introspection::set_instructions`]
+    static INSTRUCTIONS: core::cell::RefCell<(Vec<Vec<u8>>, usize)> =
+        const { core::cell::RefCell::new((Vec::new(), 0)) };
+}
+#[cfg(feature = "std")]
+pub(crate) fn set_instructions(instructions: &[Instruction], current: usize) {
+    INSTRUCTIONS.set((
+        instructions.iter().map(Instruction::serialize).collect(),
+        current,
+    ));
+}
+pub(crate) fn arch_get_current_instruction_index() -> u64 {
+    #[cfg(feature = "std")]
+    return INSTRUCTIONS.with_borrow(|(_, current)| *current as u64);
+    #[cfg(not(feature = "std"))]
+    0
+}
+pub(crate) fn arch_get_instruction_count() -> u64 {
+    #[cfg(feature = "std")]
+    return INSTRUCTIONS.with_borrow(|(instructions, _)| instructions.len() as u64);
+    #[cfg(not(feature = "std"))]
+    0
+}
+pub(crate) fn arch_get_instruction(index: usize, buf: &mut [u8]) -> u64 {
+    #[cfg(feature = "std")]
+    if let Some(instruction) =
+        INSTRUCTIONS.with_borrow(|(instructions, _)| instructions.get(index).cloned())
+    {
+        let copied = instruction.len().min(buf.len());
+        buf[..copied].copy_from_slice(&instruction[..copied]);
+        return instruction.len() as u64;
+    }
+    let _ = (index, buf);
+    UNIMPLEMENTED
+}
 pub(crate) fn arch_get_account_script_pubkey(_buf: &mut [u8; 34], _pubkey: &Pubkey) {}
 
 pub(crate) fn sol_invoke_signed_rust(
@@ -51,5 +409,7 @@ pub(crate) fn sol_invoke_signed_rust(
     _account_infos: &[AccountInfo],
 ) -> ProgramResult {
     sol_log("SyscallStubs: sol_invoke_signed() not available");
+    #[cfg(feature = "std")]
+    RETURN_DATA.set(INVOKE_RETURN_DATA.with_borrow(Option::clone));
     Ok(())
 }
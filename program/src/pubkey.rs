@@ -42,6 +42,19 @@ impl Pubkey {
         self.0 == tmp
     }
 
+    /// Id proposed for the upgradeable loader of
+    /// [`loader_instruction`](crate::loader_instruction), which nodes do not
+    /// run yet
+    pub fn loader_program() -> Self {
+        let mut tmp = [0u8; 32];
+        tmp[31] = 2;
+        Self(tmp)
+    }
+
+    pub fn is_loader_program(&self) -> bool {
+        *self == Self::loader_program()
+    }
+
     /// unique Pubkey for tests and benchmarks.
     pub fn new_unique() -> Self {
         use crate::atomic_u64::AtomicU64;
@@ -61,8 +74,8 @@ impl Pubkey {
     }
 }
 
-impl std::fmt::LowerHex for Pubkey {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl core::fmt::LowerHex for Pubkey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         let ser = self.serialize();
         for ch in &ser[..] {
             write!(f, "{:02x}", *ch)?;
@@ -1,3 +1,4 @@
+use crate::prelude::*;
 use crate::pubkey::Pubkey;
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,345 @@
+//! Simplified payment verification of bitcoin facts users bring to programs.
+//!
+//! [`get_bitcoin_tx`](crate::program::get_bitcoin_tx) only knows what the
+//! node tracks. With SPV a program accepts a payment to any address: the
+//! user submits the block headers and a merkle proof of the transaction, the
+//! program checks that the headers carry enough proof of work and chain
+//! together, then that the transaction is in the block.
+//!
+//! ```ignore
+//! let work = verify_header_chain(&headers, Network::Bitcoin)?;
+//! if work < min_work || headers[0].prev_blockhash != checkpoint {
+//!     return Err(ProgramError::InvalidArgument);
+//! }
+//! verify_tx_inclusion(&tx, &proof, &headers[0])?;
+//! ```
+//!
+//! Nothing here allocates. The checks do not follow difficulty adjustments:
+//! programs anchor the chain to a header they trust and require enough
+//! cumulative work on top of it.
+
+use bitcoin::block::Header;
+use bitcoin::consensus::Decodable;
+use bitcoin::hashes::{sha256d, Hash};
+use bitcoin::params::Params;
+use bitcoin::{BlockHash, Transaction, TxMerkleNode, Txid, Work};
+use thiserror::Error;
+
+use crate::program_error::ProgramError;
+
+/// Length of a serialised block header
+pub const HEADER_LEN: usize = 80;
+
+/// Reasons an SPV proof is rejected
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+pub enum SpvError {
+    #[error("Block header does not decode")]
+    InvalidHeader,
+    #[error("Block header target is above the network's limit")]
+    TargetAboveLimit,
+    #[error("Block hash does not meet the header's target")]
+    InvalidProofOfWork,
+    #[error("Block header does not build on the previous one")]
+    BrokenChain,
+    #[error("Header chain is empty")]
+    EmptyChain,
+    #[error("Merkle proof does not lead to the block's merkle root")]
+    InvalidMerkleProof,
+    #[error("64 byte transactions cannot be proven")]
+    AmbiguousTransaction,
+}
+
+impl From<SpvError> for ProgramError {
+    fn from(_: SpvError) -> Self {
+        ProgramError::InvalidSpvProof
+    }
+}
+
+/// Path from a transaction to the merkle root of its block
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MerkleProof<'a> {
+    /// Position of the transaction in the block
+    pub index: u32,
+    /// Hashes the transaction is combined with, from its sibling up to the
+    /// children of the root, in internal byte order
+    pub siblings: &'a [[u8; 32]],
+}
+
+/// Decodes a serialised block header
+pub fn decode_header(bytes: &[u8; HEADER_LEN]) -> Result<Header, SpvError> {
+    Header::consensus_decode(&mut &bytes[..]).map_err(|_| SpvError::InvalidHeader)
+}
+
+/// Checks that the hash of `header` meets its target and that the target is
+/// within the limit of the network of `params`, then returns the hash
+pub fn verify_header(header: &Header, params: impl AsRef<Params>) -> Result<BlockHash, SpvError> {
+    let target = header.target();
+    if target > params.as_ref().max_attainable_target {
+        return Err(SpvError::TargetAboveLimit);
+    }
+    header
+        .validate_pow(target)
+        .map_err(|_| SpvError::InvalidProofOfWork)
+}
+
+/// Checks each header with [`verify_header`] and that it builds on the one
+/// before, then returns the cumulative work of the chain
+pub fn verify_header_chain(
+    headers: &[Header],
+    params: impl AsRef<Params>,
+) -> Result<Work, SpvError> {
+    let (first, rest) = headers.split_first().ok_or(SpvError::EmptyChain)?;
+    let mut hash = verify_header(first, params.as_ref())?;
+    let mut work = first.work();
+    for header in rest {
+        if header.prev_blockhash != hash {
+            return Err(SpvError::BrokenChain);
+        }
+        hash = verify_header(header, params.as_ref())?;
+        work = work + header.work();
+    }
+    Ok(work)
+}
+
+/// Checks that `proof` leads from `txid` to `merkle_root`
+pub fn verify_merkle_proof(
+    txid: Txid,
+    proof: &MerkleProof,
+    merkle_root: TxMerkleNode,
+) -> Result<(), SpvError> {
+    let mut index = proof.index;
+    let mut node = txid.to_byte_array();
+    let mut pair = [0u8; 64];
+    for sibling in proof.siblings {
+        let (left, right) = if index & 1 == 0 {
+            (&node, sibling)
+        } else {
+            // a right node is never its left sibling duplicated, which only
+            // happens to the last node of a level
+            if sibling == &node {
+                return Err(SpvError::InvalidMerkleProof);
+            }
+            (sibling, &node)
+        };
+        pair[..32].copy_from_slice(left);
+        pair[32..].copy_from_slice(right);
+        node = sha256d::Hash::hash(&pair).to_byte_array();
+        index >>= 1;
+    }
+
+    if index != 0 || TxMerkleNode::from_byte_array(node) != merkle_root {
+        return Err(SpvError::InvalidMerkleProof);
+    }
+    Ok(())
+}
+
+/// Checks that `tx` is in the block of `header`
+///
+/// 64 byte transactions are rejected: they can pass for inner nodes of the
+/// merkle tree.
+pub fn verify_tx_inclusion(
+    tx: &Transaction,
+    proof: &MerkleProof,
+    header: &Header,
+) -> Result<(), SpvError> {
+    if tx.base_size() == 64 {
+        return Err(SpvError::AmbiguousTransaction);
+    }
+    verify_merkle_proof(tx.compute_txid(), proof, header.merkle_root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use bitcoin::block::Version;
+    use bitcoin::{merkle_tree, CompactTarget, Network};
+    use proptest::prelude::*;
+
+    /// Mines a regtest header on top of `prev_blockhash`
+    fn mine(prev_blockhash: BlockHash, merkle_root: TxMerkleNode) -> Header {
+        let mut header = Header {
+            version: Version::TWO,
+            prev_blockhash,
+            merkle_root,
+            time: 1_700_000_000,
+            bits: CompactTarget::from_consensus(0x207fffff),
+            nonce: 0,
+        };
+        while header.validate_pow(header.target()).is_err() {
+            header.nonce += 1;
+        }
+        header
+    }
+
+    fn chain(len: usize) -> Vec<Header> {
+        let mut headers: Vec<Header> = vec![];
+        for _ in 0..len {
+            let prev = headers
+                .last()
+                .map_or(BlockHash::all_zeros(), Header::block_hash);
+            headers.push(mine(prev, TxMerkleNode::all_zeros()));
+        }
+        headers
+    }
+
+    /// Siblings of leaf `index` in the merkle tree of `txids`
+    fn merkle_proof(txids: &[Txid], mut index: usize) -> Vec<[u8; 32]> {
+        let mut level: Vec<[u8; 32]> = txids.iter().map(|txid| txid.to_byte_array()).collect();
+        let mut siblings = vec![];
+        while level.len() > 1 {
+            if level.len() % 2 == 1 {
+                level.push(*level.last().unwrap());
+            }
+            siblings.push(level[index ^ 1]);
+            level = level
+                .chunks(2)
+                .map(|pair| sha256d::Hash::hash(&[pair[0], pair[1]].concat()).to_byte_array())
+                .collect();
+            index /= 2;
+        }
+        siblings
+    }
+
+    fn txids(count: usize) -> Vec<Txid> {
+        (0..count)
+            .map(|n| Txid::from_byte_array([n as u8 + 1; 32]))
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_header_chain() {
+        let headers = chain(4);
+        let work = verify_header_chain(&headers, Network::Regtest).unwrap();
+        assert_eq!(
+            work,
+            headers[0].work() + headers[0].work() + headers[0].work() + headers[0].work()
+        );
+
+        let bytes: [u8; HEADER_LEN] = bitcoin::consensus::serialize(&headers[1])
+            .try_into()
+            .unwrap();
+        assert_eq!(decode_header(&bytes), Ok(headers[1]));
+
+        // regtest targets are far above the mainnet limit
+        assert_eq!(
+            verify_header(&headers[0], Network::Bitcoin),
+            Err(SpvError::TargetAboveLimit)
+        );
+
+        let mut broken = headers.clone();
+        broken.swap(1, 2);
+        assert_eq!(
+            verify_header_chain(&broken, Network::Regtest),
+            Err(SpvError::BrokenChain)
+        );
+
+        let mut unmined = headers.clone();
+        while unmined[3].validate_pow(unmined[3].target()).is_ok() {
+            unmined[3].nonce += 1;
+        }
+        assert_eq!(
+            verify_header_chain(&unmined, Network::Regtest),
+            Err(SpvError::InvalidProofOfWork)
+        );
+
+        assert_eq!(
+            verify_header_chain(&[], Network::Regtest),
+            Err(SpvError::EmptyChain)
+        );
+    }
+
+    #[test]
+    fn test_verify_merkle_proof_errors() {
+        let txids = txids(3);
+        let root = merkle_tree::calculate_root(txids.iter().copied())
+            .map(|root: Txid| TxMerkleNode::from_byte_array(root.to_byte_array()))
+            .unwrap();
+        let siblings = merkle_proof(&txids, 2);
+        let proof = MerkleProof {
+            index: 2,
+            siblings: &siblings,
+        };
+        assert_eq!(verify_merkle_proof(txids[2], &proof, root), Ok(()));
+
+        // the duplicated last node does not prove a fourth transaction
+        let proof = MerkleProof {
+            index: 3,
+            siblings: &siblings,
+        };
+        assert_eq!(
+            verify_merkle_proof(txids[2], &proof, root),
+            Err(SpvError::InvalidMerkleProof)
+        );
+
+        let proof = MerkleProof {
+            index: 6,
+            siblings: &siblings,
+        };
+        assert_eq!(
+            verify_merkle_proof(txids[2], &proof, root),
+            Err(SpvError::InvalidMerkleProof)
+        );
+        assert_eq!(
+            ProgramError::from(SpvError::InvalidMerkleProof),
+            ProgramError::InvalidSpvProof
+        );
+    }
+
+    #[test]
+    fn test_verify_tx_inclusion() {
+        let tx = Transaction {
+            version: bitcoin::transaction::Version::TWO,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: vec![],
+            output: vec![bitcoin::TxOut {
+                value: bitcoin::Amount::from_sat(1000),
+                script_pubkey: bitcoin::ScriptBuf::from_bytes(vec![0x51; 40]),
+            }],
+        };
+        let txids = [Txid::from_byte_array([9; 32]), tx.compute_txid()];
+        let root = merkle_tree::calculate_root(txids.iter().copied())
+            .map(|root: Txid| TxMerkleNode::from_byte_array(root.to_byte_array()))
+            .unwrap();
+        let header = mine(BlockHash::all_zeros(), root);
+        let siblings = merkle_proof(&txids, 1);
+        let proof = MerkleProof {
+            index: 1,
+            siblings: &siblings,
+        };
+        assert_eq!(verify_tx_inclusion(&tx, &proof, &header), Ok(()));
+        let proof = MerkleProof {
+            index: 0,
+            siblings: &siblings,
+        };
+        assert_eq!(
+            verify_tx_inclusion(&tx, &proof, &header),
+            Err(SpvError::InvalidMerkleProof)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_merkle_proof(count in 1..40usize, index in any::<prop::sample::Index>(), flip in any::<prop::sample::Index>()) {
+            let txids = txids(count);
+            let index = index.index(count);
+            let root = merkle_tree::calculate_root(txids.iter().copied())
+                .map(|root: Txid| TxMerkleNode::from_byte_array(root.to_byte_array()))
+                .unwrap();
+            let header = mine(BlockHash::all_zeros(), root);
+            let mut siblings = merkle_proof(&txids, index);
+            let proof = MerkleProof { index: index as u32, siblings: &siblings };
+            prop_assert_eq!(verify_merkle_proof(txids[index], &proof, header.merkle_root), Ok(()));
+
+            if !siblings.is_empty() {
+                let flip = flip.index(siblings.len());
+                siblings[flip][0] ^= 1;
+                let proof = MerkleProof { index: index as u32, siblings: &siblings };
+                prop_assert_eq!(
+                    verify_merkle_proof(txids[index], &proof, header.merkle_root),
+                    Err(SpvError::InvalidMerkleProof)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Bitcoin transactions moving the state of program accounts.
+//!
+//! Each account is anchored to a utxo paying its script pubkey. A program
+//! changing accounts builds a transaction spending their utxos and recreating
+//! them, has the runtime sign the account inputs and then points each account
+//! at its new utxo, see [`set_transaction_to_sign`].
+//!
+//! [`StateTransitionBuilder`] assembles that transaction: account inputs and
+//! outputs, inputs funding the fees that the caller signs itself, payouts and
+//! `OP_RETURN` data. It tracks which inputs the runtime signs for which
+//! account and checks that the result fits [`MAX_TRANSACTION_TO_SIGN`].
+//!
+//! ```ignore
+//! let mut builder = StateTransitionBuilder::new();
+//! builder
+//!     .add_fee_input(fee_outpoint)
+//!     .add_account(accounts.mint)?
+//!     .add_op_return(b"mint")?;
+//! set_transaction_to_sign(accounts, builder.build()?)?;
+//! ```
+//!
+//! [`set_transaction_to_sign`]: crate::program::set_transaction_to_sign
+
+use bitcoin::{
+    absolute::LockTime, script::PushBytesBuf, transaction::Version, Amount, OutPoint, ScriptBuf,
+    Sequence, Transaction, TxIn, TxOut, Witness,
+};
+
+use crate::account::AccountInfo;
+use crate::accounts::check_writable;
+use crate::input_to_sign::InputToSign;
+use crate::prelude::*;
+use crate::program::{get_account_script_pubkey, get_bitcoin_tx_output, MAX_TRANSACTION_TO_SIGN};
+use crate::program_error::ProgramError;
+use crate::transaction_to_sign::TransactionToSign;
+use crate::utxo::UtxoMeta;
+
+/// Largest `OP_RETURN` payload relayed by default by bitcoin nodes
+pub const MAX_OP_RETURN_DATA: usize = 80;
+
+/// Builds the transaction of a state transition, see the [module
+/// docs](self)
+#[derive(Clone, Debug)]
+pub struct StateTransitionBuilder {
+    transaction: Transaction,
+    inputs_to_sign: Vec<InputToSign>,
+    tx_bytes: Vec<u8>,
+}
+
+impl Default for StateTransitionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateTransitionBuilder {
+    pub fn new() -> Self {
+        Self {
+            transaction: Transaction {
+                version: Version::TWO,
+                lock_time: LockTime::ZERO,
+                input: vec![],
+                output: vec![],
+            },
+            inputs_to_sign: vec![],
+            tx_bytes: vec![],
+        }
+    }
+
+    /// Spends the utxo of `account` and pays its value back to the account
+    ///
+    /// The value is read from the transaction that created the utxo.
+    pub fn add_account(&mut self, account: &AccountInfo) -> Result<&mut Self, ProgramError> {
+        let value = previous_output(account.utxo)?.value;
+        self.add_account_with_value(account, value)
+    }
+
+    /// Spends the utxo of `account`, worth `value`, and pays `value` back to
+    /// the account
+    pub fn add_account_with_value(
+        &mut self,
+        account: &AccountInfo,
+        value: Amount,
+    ) -> Result<&mut Self, ProgramError> {
+        self.add_account_input(account)?;
+        Ok(self.add_account_output(account, value))
+    }
+
+    /// Spends the utxo of `account`, signed by the runtime for the account,
+    /// without paying the account back
+    pub fn add_account_input(&mut self, account: &AccountInfo) -> Result<&mut Self, ProgramError> {
+        check_writable(account)?;
+        let index = self.transaction.input.len() as u32;
+        self.transaction
+            .input
+            .push(spend(account.utxo.to_outpoint()));
+        self.inputs_to_sign
+            .push(InputToSign::new(index, *account.key));
+        Ok(self)
+    }
+
+    /// Pays `value` to the script pubkey of `account`
+    pub fn add_account_output(&mut self, account: &AccountInfo, value: Amount) -> &mut Self {
+        let script_pubkey = ScriptBuf::from_bytes(get_account_script_pubkey(account.key).to_vec());
+        self.add_output(value, script_pubkey)
+    }
+
+    /// Spends `outpoint` to fund the fees. The caller signs the input, the
+    /// runtime does not.
+    pub fn add_fee_input(&mut self, outpoint: OutPoint) -> &mut Self {
+        self.transaction.input.push(spend(outpoint));
+        self
+    }
+
+    /// Pays `value` to `script_pubkey`
+    pub fn add_output(&mut self, value: Amount, script_pubkey: ScriptBuf) -> &mut Self {
+        self.transaction.output.push(TxOut {
+            value,
+            script_pubkey,
+        });
+        self
+    }
+
+    /// Adds an unspendable output carrying `data`, of at most
+    /// [`MAX_OP_RETURN_DATA`] bytes
+    pub fn add_op_return(&mut self, data: &[u8]) -> Result<&mut Self, ProgramError> {
+        if data.len() > MAX_OP_RETURN_DATA {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let data =
+            PushBytesBuf::try_from(data.to_vec()).map_err(|_| ProgramError::InvalidArgument)?;
+        Ok(self.add_output(Amount::ZERO, ScriptBuf::new_op_return(data)))
+    }
+
+    pub fn transaction(&self) -> &Transaction {
+        &self.transaction
+    }
+
+    /// Inputs the runtime signs, by account
+    pub fn inputs_to_sign(&self) -> &[InputToSign] {
+        &self.inputs_to_sign
+    }
+
+    /// The transaction to hand to
+    /// [`set_transaction_to_sign`](crate::program::set_transaction_to_sign)
+    ///
+    /// Fails with [`ProgramError::InvalidTxToSign`] when it does not fit
+    /// [`MAX_TRANSACTION_TO_SIGN`].
+    pub fn build(&mut self) -> Result<TransactionToSign<'_>, ProgramError> {
+        self.tx_bytes = bitcoin::consensus::serialize(&self.transaction);
+        let transaction_to_sign = TransactionToSign {
+            tx_bytes: &self.tx_bytes,
+            inputs_to_sign: &self.inputs_to_sign,
+        };
+        if transaction_to_sign.serialised_len() > MAX_TRANSACTION_TO_SIGN {
+            return Err(ProgramError::InvalidTxToSign);
+        }
+        Ok(transaction_to_sign)
+    }
+}
+
+fn spend(previous_output: OutPoint) -> TxIn {
+    TxIn {
+        previous_output,
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence::MAX,
+        witness: Witness::new(),
+    }
+}
+
+/// The output `utxo` points to, read from the transaction that created it
+pub fn previous_output(utxo: &UtxoMeta) -> Result<TxOut, ProgramError> {
+    let txid = utxo.txid().try_into().expect("utxo meta unreachable");
+    get_bitcoin_tx_output(txid, utxo.vout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pubkey::Pubkey;
+    use bitcoin::hashes::Hash;
+    use bitcoin::Txid;
+    use proptest::prelude::*;
+
+    fn utxo(n: u8) -> UtxoMeta {
+        UtxoMeta::from([n; 32], n as u32)
+    }
+
+    #[test]
+    fn test_build() {
+        let (keys, owner) = ([Pubkey([1; 32]), Pubkey([2; 32])], Pubkey::system_program());
+        let utxos = [utxo(1), utxo(2)];
+        let (mut data0, mut data1) = ([0u8; 4], [0u8; 4]);
+        let first = AccountInfo::new(&keys[0], &mut data0, &owner, &utxos[0], false, true, false);
+        let second = AccountInfo::new(&keys[1], &mut data1, &owner, &utxos[1], false, true, false);
+        let fee = OutPoint::new(Txid::from_byte_array([9; 32]), 3);
+
+        let mut builder = StateTransitionBuilder::new();
+        builder
+            .add_fee_input(fee)
+            .add_account_with_value(&first, Amount::from_sat(1000))
+            .unwrap()
+            .add_account_input(&second)
+            .unwrap()
+            .add_output(Amount::from_sat(500), ScriptBuf::new())
+            .add_op_return(b"transition")
+            .unwrap();
+
+        let tx = builder.transaction().clone();
+        assert_eq!(tx.input.len(), 3);
+        assert_eq!(tx.input[0].previous_output, fee);
+        assert_eq!(tx.input[1].previous_output, utxos[0].to_outpoint());
+        assert_eq!(tx.input[2].previous_output, utxos[1].to_outpoint());
+        assert_eq!(tx.output.len(), 3);
+        assert_eq!(tx.output[0].value, Amount::from_sat(1000));
+        assert!(tx.output[2].script_pubkey.is_op_return());
+
+        let transaction_to_sign = builder.build().unwrap();
+        assert_eq!(
+            transaction_to_sign.inputs_to_sign,
+            &[InputToSign::new(1, keys[0]), InputToSign::new(2, keys[1])]
+        );
+        let decoded: Transaction =
+            bitcoin::consensus::deserialize(transaction_to_sign.tx_bytes).unwrap();
+        assert_eq!(decoded, tx);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (key, owner, utxo) = (Pubkey([1; 32]), Pubkey::system_program(), utxo(1));
+        let mut data = [0u8; 4];
+        let read_only = AccountInfo::new(&key, &mut data, &owner, &utxo, false, false, false);
+        let mut builder = StateTransitionBuilder::new();
+        assert_eq!(
+            builder.add_account_input(&read_only).unwrap_err(),
+            ProgramError::Immutable
+        );
+        assert!(builder.transaction().input.is_empty());
+
+        assert_eq!(
+            builder
+                .add_op_return(&[0; MAX_OP_RETURN_DATA + 1])
+                .unwrap_err(),
+            ProgramError::InvalidArgument
+        );
+        builder.add_op_return(&[0; MAX_OP_RETURN_DATA]).unwrap();
+
+        // the host has no bitcoin transactions to read values from
+        let mut data = [0u8; 4];
+        let writable = AccountInfo::new(&key, &mut data, &owner, &utxo, false, true, false);
+        assert_eq!(
+            builder.add_account(&writable).unwrap_err(),
+            ProgramError::BitcoinTxNotFound
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_build_size_limit(outputs in 0..150usize) {
+            let mut builder = StateTransitionBuilder::new();
+            for _ in 0..outputs {
+                builder.add_output(Amount::from_sat(1), ScriptBuf::from_bytes(vec![0; 32]));
+            }
+            let tx_len = bitcoin::consensus::serialize(builder.transaction()).len();
+            match builder.build() {
+                Ok(transaction_to_sign) => {
+                    prop_assert!(transaction_to_sign.serialised_len() <= MAX_TRANSACTION_TO_SIGN)
+                }
+                Err(error) => {
+                    prop_assert_eq!(error, ProgramError::InvalidTxToSign);
+                    prop_assert!(tx_len + 8 > MAX_TRANSACTION_TO_SIGN);
+                }
+            }
+        }
+    }
+}
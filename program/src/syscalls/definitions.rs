@@ -2,30 +2,82 @@
 
 use crate::{clock::Clock, pubkey::Pubkey, utxo::UtxoMeta};
 
-macro_rules! define_syscall {
-	(fn $name:ident($($arg:ident: $typ:ty),*) -> $ret:ty) => {
+/// Declares each syscall and lists its name in `SYSCALLS`, or in
+/// `PROPOSED_SYSCALLS` when marked `#[proposed]`, so that the lists the ELF
+/// validator checks imports against cannot fall behind the declarations
+macro_rules! define_syscalls {
+	(@list [$($served:ident)*] [$($proposed:ident)*]) => {
+		/// Names of the syscalls nodes serve, as they appear in a program's ELF imports
+		pub const SYSCALLS: &[&str] = &[$(stringify!($served)),*];
+
+		/// Names of the syscalls declared for proposed node features. No node
+		/// serves them yet, so programs importing them fail to load.
+		pub const PROPOSED_SYSCALLS: &[&str] = &[$(stringify!($proposed)),*];
+	};
+	(@list [$($served:ident)*] [$($proposed:ident)*]
+		#[proposed] fn $name:ident($($arg:ident: $typ:ty),*) $(-> $ret:ty)?; $($rest:tt)*) => {
 		extern "C" {
-			pub fn $name($($arg: $typ),*) -> $ret;
+			pub fn $name($($arg: $typ),*) $(-> $ret)?;
 		}
+		define_syscalls!(@list [$($served)*] [$($proposed)* $name] $($rest)*);
+	};
+	(@list [$($served:ident)*] [$($proposed:ident)*]
+		fn $name:ident($($arg:ident: $typ:ty),*) $(-> $ret:ty)?; $($rest:tt)*) => {
+		extern "C" {
+			pub fn $name($($arg: $typ),*) $(-> $ret)?;
+		}
+		define_syscalls!(@list [$($served)* $name] [$($proposed)*] $($rest)*);
+	};
+	($($syscalls:tt)*) => {
+		define_syscalls!(@list [] [] $($syscalls)*);
 	};
-	(fn $name:ident($($arg:ident: $typ:ty),*)) => {
-		define_syscall!(fn $name($($arg: $typ),*) -> ());
-	}
 }
 
-define_syscall!(fn sol_invoke_signed_rust(instruction_addr: *const u8, account_infos_addr: *const u8, account_infos_len: u64) -> u64);
-define_syscall!(fn sol_set_return_data(data: *const u8, length: u64));
-define_syscall!(fn sol_get_return_data(data: *mut u8, length: u64, program_id: *mut Pubkey) -> u64);
+define_syscalls! {
+    fn sol_invoke_signed_rust(instruction_addr: *const u8, account_infos_addr: *const u8, account_infos_len: u64) -> u64;
+    fn sol_set_return_data(data: *const u8, length: u64);
+    fn sol_get_return_data(data: *mut u8, length: u64, program_id: *mut Pubkey) -> u64;
 
-define_syscall!(fn arch_set_transaction_to_sign(transaction_to_sign: *const u8, length: u64) -> u64);
-define_syscall!(fn arch_get_bitcoin_tx(data: *mut u8, length: u64, txid: &[u8; 32]) -> u64);
-define_syscall!(fn arch_get_network_xonly_pubkey(data: *mut u8) -> u64);
-define_syscall!(fn arch_validate_utxo_ownership(utxo: *const UtxoMeta, owner: *const Pubkey) -> u64);
-define_syscall!(fn arch_get_account_script_pubkey(script: *mut u8, pubkey: *const Pubkey) -> u64);
-define_syscall!(fn arch_get_bitcoin_block_height() -> u64);
-define_syscall!(fn arch_get_clock(clock: *mut Clock) -> u64);
-// logs
-define_syscall!(fn sol_log_(message: *const u8, len: u64));
-define_syscall!(fn sol_log_64_(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64));
-define_syscall!(fn sol_log_pubkey(pubkey_addr: *const u8));
-define_syscall!(fn sol_log_data(data: *const u8, data_len: u64));
+    fn arch_set_transaction_to_sign(transaction_to_sign: *const u8, length: u64) -> u64;
+    fn arch_get_bitcoin_tx(data: *mut u8, length: u64, txid: &[u8; 32]) -> u64;
+    #[proposed]
+    fn arch_get_bitcoin_tx_chunk(data: *mut u8, length: u64, txid: &[u8; 32], offset: u64) -> u64;
+    #[proposed]
+    fn arch_get_bitcoin_tx_output(data: *mut u8, length: u64, txid: &[u8; 32], vout: u32) -> u64;
+    fn arch_get_network_xonly_pubkey(data: *mut u8) -> u64;
+    fn arch_validate_utxo_ownership(utxo: *const UtxoMeta, owner: *const Pubkey) -> u64;
+    #[proposed]
+    fn arch_get_utxo_status(utxo: *const UtxoMeta, data: *mut u8, length: u64) -> u64;
+    fn arch_get_account_script_pubkey(script: *mut u8, pubkey: *const Pubkey) -> u64;
+    fn arch_get_bitcoin_block_height() -> u64;
+    #[proposed]
+    fn arch_get_bitcoin_block_hash(height: u64, hash: *mut u8) -> u64;
+    #[proposed]
+    fn arch_get_bitcoin_block_header(height: u64, header: *mut u8) -> u64;
+    #[proposed]
+    fn arch_get_bitcoin_median_time_past() -> u64;
+    fn arch_get_clock(clock: *mut Clock) -> u64;
+    // introspection
+    #[proposed]
+    fn arch_get_current_instruction_index() -> u64;
+    #[proposed]
+    fn arch_get_instruction_count() -> u64;
+    #[proposed]
+    fn arch_get_instruction(index: u64, data: *mut u8, length: u64) -> u64;
+    // crypto
+    #[proposed]
+    fn arch_sha256(vals: *const u8, vals_len: u64, hash: *mut u8) -> u64;
+    #[proposed]
+    fn arch_double_sha256(vals: *const u8, vals_len: u64, hash: *mut u8) -> u64;
+    #[proposed]
+    fn arch_tagged_hash(tag: *const u8, tag_len: u64, vals: *const u8, vals_len: u64, hash: *mut u8) -> u64;
+    #[proposed]
+    fn arch_verify_schnorr(signature: *const u8, message: *const u8, pubkey: *const u8) -> u64;
+    #[proposed]
+    fn arch_verify_bip322(message: *const u8, message_len: u64, pubkey: *const u8, signature: *const u8, uses_sighash_all: u64) -> u64;
+    // logs
+    fn sol_log_(message: *const u8, len: u64);
+    fn sol_log_64_(arg1: u64, arg2: u64, arg3: u64, arg4: u64, arg5: u64);
+    fn sol_log_pubkey(pubkey_addr: *const u8);
+    fn sol_log_data(data: *const u8, data_len: u64);
+}
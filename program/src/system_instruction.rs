@@ -1,5 +1,7 @@
 use crate::account::AccountMeta;
 use crate::instruction::Instruction;
+use crate::prelude::*;
+use crate::program_error::ProgramError;
 use crate::pubkey::Pubkey;
 use crate::utxo::UtxoMeta;
 
@@ -7,6 +9,8 @@ use crate::utxo::UtxoMeta;
 pub enum SystemInstruction {
     CreateAccount(UtxoMeta),
     ExtendBytes(Vec<u8>),
+    MakeExecutable,
+    AssignOwnership(Pubkey),
 }
 
 impl SystemInstruction {
@@ -22,18 +26,29 @@ impl SystemInstruction {
                 serialized.push(1);
                 serialized.extend(bytes);
             }
+            Self::MakeExecutable => serialized.push(2),
+            Self::AssignOwnership(owner) => {
+                serialized.push(3);
+                serialized.extend(owner.serialize());
+            }
         }
 
         serialized
     }
 
-    pub fn from_slice(data: &[u8]) -> Self {
-        match data[0] {
-            0 => Self::CreateAccount(UtxoMeta::from_slice(&data[1..])),
-            1 => Self::ExtendBytes(data[1..].to_vec()),
-            _ => {
-                unreachable!("error deserializing system instruction")
-            }
+    /// Decodes an instruction serialized by [`Self::serialise`], failing with
+    /// [`ProgramError::InvalidInstructionData`] on an unknown tag or
+    /// truncated data
+    pub fn from_slice(data: &[u8]) -> Result<Self, ProgramError> {
+        let (tag, rest) = data
+            .split_first()
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        match (tag, rest.len()) {
+            (0, 36..) => Ok(Self::CreateAccount(UtxoMeta::from_slice(rest))),
+            (1, _) => Ok(Self::ExtendBytes(rest.to_vec())),
+            (2, _) => Ok(Self::MakeExecutable),
+            (3, 32..) => Ok(Self::AssignOwnership(Pubkey::from_slice(&rest[..32]))),
+            _ => Err(ProgramError::InvalidInstructionData),
         }
     }
 
@@ -64,14 +79,53 @@ impl SystemInstruction {
             data: SystemInstruction::ExtendBytes(data).serialise(),
         }
     }
+
+    pub fn new_make_executable_instruction(pubkey: Pubkey) -> Instruction {
+        Instruction {
+            program_id: Pubkey::system_program(),
+            accounts: vec![AccountMeta {
+                pubkey,
+                is_signer: true,
+                is_writable: true,
+            }],
+            data: SystemInstruction::MakeExecutable.serialise(),
+        }
+    }
+
+    /// Hands `pubkey`'s account over to the program `owner`
+    pub fn new_assign_ownership_instruction(pubkey: Pubkey, owner: Pubkey) -> Instruction {
+        Instruction {
+            program_id: Pubkey::system_program(),
+            accounts: vec![AccountMeta {
+                pubkey,
+                is_signer: true,
+                is_writable: true,
+            }],
+            data: SystemInstruction::AssignOwnership(owner).serialise(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::SystemInstruction;
+    use crate::program_error::ProgramError;
+    use crate::pubkey::Pubkey;
     use crate::utxo::UtxoMeta;
     use proptest::prelude::*;
 
+    #[test]
+    fn test_from_slice_truncated() {
+        let create = SystemInstruction::CreateAccount(UtxoMeta::from([1; 32], 2)).serialise();
+        let assign = SystemInstruction::AssignOwnership(Pubkey([3; 32])).serialise();
+        for data in [&[][..], &[4], &create[..36], &assign[..1], &assign[..32]] {
+            assert_eq!(
+                SystemInstruction::from_slice(data),
+                Err(ProgramError::InvalidInstructionData)
+            );
+        }
+    }
+
     proptest! {
         #[test]
         fn fuzz_serialize_deserialize_system_instruction_create_account(
@@ -82,7 +136,7 @@ mod tests {
             let instruction = SystemInstruction::CreateAccount(UtxoMeta::from(txid, vout));
 
             let serialized = instruction.serialise();
-            let deserialized = SystemInstruction::from_slice(&serialized);
+            let deserialized = SystemInstruction::from_slice(&serialized).unwrap();
 
             assert_eq!(instruction, deserialized);
         }
@@ -96,9 +150,24 @@ mod tests {
             let instruction = SystemInstruction::ExtendBytes(random_bytes.clone());
 
             let serialized = instruction.serialise();
-            let deserialized = SystemInstruction::from_slice(&serialized);
+            let deserialized = SystemInstruction::from_slice(&serialized).unwrap();
 
             assert_eq!(instruction, deserialized);
         }
+
+        #[test]
+        fn fuzz_serialize_deserialize_system_instruction_assign_ownership(
+            owner in any::<[u8; 32]>(),
+        ) {
+            for instruction in [
+                SystemInstruction::MakeExecutable,
+                SystemInstruction::AssignOwnership(Pubkey::from(owner)),
+            ] {
+                let serialized = instruction.serialise();
+                let deserialized = SystemInstruction::from_slice(&serialized).unwrap();
+
+                assert_eq!(instruction, deserialized);
+            }
+        }
     }
 }
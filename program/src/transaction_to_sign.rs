@@ -1,5 +1,18 @@
+//! Transactions a program hands to the runtime to sign.
+//!
+//! A [`TransactionToSign`] serialises as the length of the transaction, the
+//! transaction, a `u32` holding the serialisation version in its top byte and
+//! the number of inputs to sign in the rest, then each [`InputToSign`].
+//! Version 0, the original format, only holds key path `SIGHASH_DEFAULT`
+//! signatures and is still written when every input is one, so that runtimes
+//! predating version 1 keep reading them. Version 1 adds the sighash type,
+//! tap leaf hash and annex of each input.
+
 use crate::input_to_sign::InputToSign;
-use crate::pubkey::Pubkey;
+use crate::prelude::*;
+
+/// Latest serialisation version of [`TransactionToSign`]
+pub const TRANSACTION_TO_SIGN_VERSION: u8 = 1;
 
 #[repr(C)]
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -9,45 +22,60 @@ pub struct TransactionToSign<'a> {
 }
 
 impl<'a> TransactionToSign<'a> {
-    pub fn serialise(&self) -> Vec<u8> {
-        let mut serialized = vec![];
-
-        serialized.extend_from_slice(&(self.tx_bytes.len() as u32).to_le_bytes());
-        serialized.extend_from_slice(self.tx_bytes);
-        serialized.extend_from_slice(&(self.inputs_to_sign.len() as u32).to_le_bytes());
-        for input_to_sign in self.inputs_to_sign.iter() {
-            serialized.extend_from_slice(&input_to_sign.index.to_le_bytes());
-            serialized.extend_from_slice(&input_to_sign.signer.serialize());
+    /// Serialisation version [`Self::serialise`] writes: 0 when every input
+    /// is [legacy](InputToSign::is_legacy), [`TRANSACTION_TO_SIGN_VERSION`]
+    /// otherwise
+    pub fn version(&self) -> u8 {
+        if self.inputs_to_sign.iter().all(InputToSign::is_legacy) {
+            0
+        } else {
+            TRANSACTION_TO_SIGN_VERSION
         }
+    }
 
+    pub fn serialise(&self) -> Vec<u8> {
+        let mut serialized = vec![0; self.serialised_len()];
+        self.serialise_into(&mut serialized);
         serialized
     }
 
-    pub fn from_slice(data: &'a [u8]) -> Self {
-        let mut size = 0;
-
-        let tx_bytes_len = u32::from_le_bytes(data[size..size + 4].try_into().unwrap()) as usize;
-        size += 4;
-
-        let tx_bytes = &data[size..(size + tx_bytes_len)];
-        size += tx_bytes_len;
-
-        let inputs_to_sign_len =
-            u32::from_le_bytes(data[size..size + 4].try_into().unwrap()) as usize;
-        size += 4;
-
-        let mut inputs_to_sign = Vec::with_capacity(inputs_to_sign_len);
-
-        for _ in 0..inputs_to_sign_len {
-            let index = u32::from_le_bytes(data[size..size + 4].try_into().unwrap());
-            size += 4;
-
-            let signer = Pubkey::from_slice(&data[size..size + 32]);
-            size += 32;
+    /// Length of [`Self::serialise`]
+    pub fn serialised_len(&self) -> usize {
+        let version = self.version();
+        4 + self.tx_bytes.len()
+            + 4
+            + self
+                .inputs_to_sign
+                .iter()
+                .map(|input_to_sign| input_to_sign.serialised_len(version))
+                .sum::<usize>()
+    }
 
-            inputs_to_sign.push(InputToSign { index, signer });
+    /// Writes [`Self::serialise`] at the start of `buf` without allocating
+    /// and returns its length
+    ///
+    /// Panics if `buf` is shorter than [`Self::serialised_len`].
+    pub fn serialise_into(&self, buf: &mut [u8]) -> usize {
+        let mut size = 0;
+        let mut write = |bytes: &[u8]| {
+            buf[size..size + bytes.len()].copy_from_slice(bytes);
+            size += bytes.len();
+        };
+
+        let version = self.version();
+        write(&(self.tx_bytes.len() as u32).to_le_bytes());
+        write(self.tx_bytes);
+        let inputs_to_sign_len = self.inputs_to_sign.len() as u32;
+        write(&((version as u32) << 24 | inputs_to_sign_len).to_le_bytes());
+        for input_to_sign in self.inputs_to_sign.iter() {
+            input_to_sign.encode(version, &mut write);
         }
 
+        size
+    }
+
+    pub fn from_slice(data: &'a [u8]) -> Self {
+        let (tx_bytes, inputs_to_sign) = deserialise_parts(data);
         TransactionToSign {
             tx_bytes,
             inputs_to_sign: inputs_to_sign.leak(),
@@ -55,27 +83,127 @@ impl<'a> TransactionToSign<'a> {
     }
 }
 
+/// The transaction and inputs to sign of a serialised [`TransactionToSign`]
+/// of any version
+///
+/// Panics if `data` is truncated or malformed.
+pub fn deserialise_parts(data: &[u8]) -> (&[u8], Vec<InputToSign>) {
+    let mut size = 0;
+
+    let tx_bytes_len = u32::from_le_bytes(data[size..size + 4].try_into().unwrap()) as usize;
+    size += 4;
+
+    let tx_bytes = &data[size..(size + tx_bytes_len)];
+    size += tx_bytes_len;
+
+    let header = u32::from_le_bytes(data[size..size + 4].try_into().unwrap());
+    size += 4;
+    let version = (header >> 24) as u8;
+    assert!(
+        version <= TRANSACTION_TO_SIGN_VERSION,
+        "unknown transaction to sign version {version}"
+    );
+    let inputs_to_sign_len = (header & 0x00ff_ffff) as usize;
+
+    let mut inputs_to_sign = Vec::with_capacity(inputs_to_sign_len);
+    for _ in 0..inputs_to_sign_len {
+        inputs_to_sign.push(InputToSign::decode(data, &mut size, version));
+    }
+
+    (tx_bytes, inputs_to_sign)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        input_to_sign::InputToSign, pubkey::Pubkey, transaction_to_sign::TransactionToSign,
+        input_to_sign::InputToSign, program_error::ProgramError, pubkey::Pubkey,
+        transaction_to_sign::TransactionToSign,
     };
+    use bitcoin::hashes::Hash;
+    use bitcoin::{TapLeafHash, TapSighashType};
     use proptest::prelude::*;
 
+    const SIGHASH_TYPES: [TapSighashType; 7] = [
+        TapSighashType::Default,
+        TapSighashType::All,
+        TapSighashType::None,
+        TapSighashType::Single,
+        TapSighashType::AllPlusAnyoneCanPay,
+        TapSighashType::NonePlusAnyoneCanPay,
+        TapSighashType::SinglePlusAnyoneCanPay,
+    ];
+
+    #[test]
+    fn test_legacy_format() {
+        let signer = Pubkey([7; 32]);
+        let mut legacy = vec![];
+        legacy.extend_from_slice(&2u32.to_le_bytes());
+        legacy.extend_from_slice(&[1, 2]);
+        legacy.extend_from_slice(&1u32.to_le_bytes());
+        legacy.extend_from_slice(&3u32.to_le_bytes());
+        legacy.extend_from_slice(&signer.serialize());
+
+        let inputs_to_sign = [InputToSign::new(3, signer)];
+        let transaction = TransactionToSign {
+            tx_bytes: &[1, 2],
+            inputs_to_sign: &inputs_to_sign,
+        };
+        assert_eq!(transaction.version(), 0);
+        assert_eq!(transaction.serialise(), legacy);
+        assert_eq!(TransactionToSign::from_slice(&legacy), transaction);
+    }
+
+    #[test]
+    fn test_versioned_format() {
+        let inputs_to_sign = [
+            InputToSign::new(0, Pubkey([1; 32])),
+            InputToSign::new(1, Pubkey([2; 32]))
+                .with_sighash_type(TapSighashType::SinglePlusAnyoneCanPay),
+        ];
+        let transaction = TransactionToSign {
+            tx_bytes: &[],
+            inputs_to_sign: &inputs_to_sign,
+        };
+        assert_eq!(transaction.version(), 1);
+        let serialized = transaction.serialise();
+        assert_eq!(serialized[4..8], [2, 0, 0, 1]);
+        assert_eq!(serialized.len(), transaction.serialised_len());
+        assert_eq!(TransactionToSign::from_slice(&serialized), transaction);
+
+        assert_eq!(
+            InputToSign::new(0, Pubkey([1; 32])).with_annex(vec![0x51]),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+
     proptest! {
         #[test]
         fn fuzz_serialize_deserialize_transaction_to_sign(
             tx_bytes in prop::collection::vec(any::<u8>(), 0..64),
-            input_indices in prop::collection::vec(any::<u32>(), 0..10),
-            input_pubkeys in prop::collection::vec(any::<[u8; 32]>(), 0..10)
+            inputs in prop::collection::vec(
+                (
+                    any::<u32>(),
+                    any::<[u8; 32]>(),
+                    0..SIGHASH_TYPES.len(),
+                    any::<Option<[u8; 32]>>(),
+                    prop::option::of(prop::collection::vec(any::<u8>(), 0..16)),
+                ),
+                0..10
+            )
         ) {
-            let inputs_to_sign: Vec<InputToSign> = input_indices.into_iter()
-                .zip(input_pubkeys.into_iter())
-                .map(|(index, pubkey_bytes)| {
-                    InputToSign {
-                        index,
-                        signer: Pubkey::from(pubkey_bytes),
+            let inputs_to_sign: Vec<InputToSign> = inputs
+                .into_iter()
+                .map(|(index, pubkey_bytes, sighash_type, leaf_hash, annex)| {
+                    let mut input_to_sign = InputToSign::new(index, Pubkey::from(pubkey_bytes))
+                        .with_sighash_type(SIGHASH_TYPES[sighash_type]);
+                    if let Some(leaf_hash) = leaf_hash {
+                        input_to_sign = input_to_sign.with_leaf_hash(TapLeafHash::from_byte_array(leaf_hash));
+                    }
+                    if let Some(mut annex) = annex {
+                        annex.insert(0, 0x50);
+                        input_to_sign = input_to_sign.with_annex(annex).unwrap();
                     }
+                    input_to_sign
                 })
                 .collect();
 
@@ -85,10 +213,11 @@ mod tests {
             };
 
             let serialized = transaction.serialise();
+            prop_assert_eq!(serialized.len(), transaction.serialised_len());
             let deserialized = TransactionToSign::from_slice(&serialized);
 
-            assert_eq!(transaction.tx_bytes, deserialized.tx_bytes);
-            assert_eq!(transaction.inputs_to_sign, deserialized.inputs_to_sign);
+            prop_assert_eq!(transaction.tx_bytes, deserialized.tx_bytes);
+            prop_assert_eq!(transaction.inputs_to_sign, deserialized.inputs_to_sign);
         }
     }
 }
@@ -13,20 +13,18 @@ impl UtxoMeta {
     }
 
     pub fn from_outpoint(txid: Txid, vout: u32) -> Self {
-        let mut data: [u8; 36] = [0; 36];
-        data[..32].copy_from_slice(
-            &bitcoin::consensus::serialize(&txid)
-                .into_iter()
-                .rev()
-                .collect::<Vec<u8>>(),
-        );
-        data[32..].copy_from_slice(&vout.to_le_bytes());
-        Self(data)
+        // the txid is kept in its displayed order, the reverse of its
+        // consensus encoding
+        let mut txid = txid.to_byte_array();
+        txid.reverse();
+        Self::from(txid, vout)
     }
 
     pub fn to_outpoint(&self) -> OutPoint {
+        let mut txid: [u8; 32] = self.txid().try_into().expect("utxo meta unreachable");
+        txid.reverse();
         OutPoint {
-            txid: Txid::from_str(&hex::encode(self.txid())).unwrap(),
+            txid: Txid::from_byte_array(txid),
             vout: self.vout(),
         }
     }
@@ -58,6 +56,8 @@ impl UtxoMeta {
 
 #[test]
 fn test_outpoint() {
+    use core::str::FromStr;
+
     assert_eq!(
         OutPoint::new(
             Txid::from_str("c5cc9251192330191366016c8dab0f67dc345bd024a206c313dbf26db0a66bb1")
@@ -75,13 +75,15 @@ fn test_outpoint() {
     );
 }
 
+use borsh::io::{Read, Result, Write};
 use core::fmt;
-use std::io::{Read, Result, Write};
-use std::str::FromStr;
 
+use bitcoin::hashes::Hash;
 use bitcoin::OutPoint;
 use bitcoin::Txid;
 
+use crate::prelude::*;
+
 /// TODO:
 ///  Change this in future according to the correct base implementation
 impl fmt::Display for UtxoMeta {
@@ -118,24 +120,50 @@ impl BorshSerialize for UtxoMeta {
 impl BorshDeserialize for UtxoMeta {
     #[inline]
     fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
-        if let Some(vec_bytes) = u8::vec_from_reader(36, reader)? {
-            Ok(UtxoMeta::from_slice(&vec_bytes))
-        } else {
-            // TODO(16): return capacity allocation when we can safely do that.
-            let mut result = Vec::with_capacity(36);
-            for _ in 0..36 {
-                result.push(u8::deserialize_reader(reader)?);
-            }
-            Ok(UtxoMeta::from_slice(&result))
-        }
+        <[u8; 36]>::deserialize_reader(reader).map(UtxoMeta)
+    }
+}
+
+/// State of a utxo on the bitcoin chain, see
+/// [`get_utxo_status`](crate::program::get_utxo_status)
+#[derive(Clone, Debug, Default, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct UtxoStatus {
+    /// Value in satoshis
+    pub value: u64,
+    pub script_pubkey: Vec<u8>,
+    /// Blocks including and built on the one confirming the utxo, 0 while it
+    /// is in the mempool
+    pub confirmations: u32,
+    pub spent: bool,
+}
+
+impl UtxoStatus {
+    /// Whether the utxo is unspent with at least `confirmations`
+    /// confirmations
+    pub fn is_confirmed(&self, confirmations: u32) -> bool {
+        !self.spent && self.confirmations >= confirmations
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::utxo::UtxoMeta;
+    use crate::utxo::{UtxoMeta, UtxoStatus};
     use proptest::prelude::*;
 
+    #[test]
+    fn test_is_confirmed() {
+        let mut status = UtxoStatus {
+            value: 1000,
+            script_pubkey: vec![0x51],
+            confirmations: 3,
+            spent: false,
+        };
+        assert!(status.is_confirmed(3));
+        assert!(!status.is_confirmed(4));
+        status.spent = true;
+        assert!(!status.is_confirmed(0));
+    }
+
     proptest! {
         #[test]
         fn fuzz_serialize_deserialize_utxo_meta(txid in any::<[u8; 32]>(), vout in any::<u32>()) {
@@ -16,6 +16,7 @@ pub const ASSIGN_AUTHORITY: &str = "assign_authority";
 pub const READ_ACCOUNT_INFO: &str = "read_account_info";
 pub const DEPLOY_PROGRAM: &str = "deploy_program";
 pub const SEND_TRANSACTION: &str = "send_transaction";
+pub const SEND_TRANSACTIONS: &str = "send_transactions";
 pub const GET_PROGRAM: &str = "get_program";
 pub const GET_BLOCK: &str = "get_block";
 pub const GET_BEST_BLOCK_HASH: &str = "get_best_block_hash";
@@ -31,3 +32,4 @@ pub const BITCOIN_NETWORK: bitcoin::Network = bitcoin::Network::Regtest;
 
 /// Hack for Error codes
 pub const TRANSACTION_NOT_FOUND_CODE: i64 = 404;
+pub const ACCOUNT_NOT_FOUND_CODE: i64 = 404;
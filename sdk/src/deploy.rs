@@ -0,0 +1,519 @@
+//! Resumable program deployment
+//!
+//! A program ELF is uploaded as a series of `ExtendBytes` system instructions,
+//! one runtime transaction per chunk. Before sending anything, the current
+//! content of the program account is read back so that chunks which already
+//! landed in a previous (possibly interrupted) deployment are skipped. Pending
+//! chunks are sent in bounded batches, failed chunks are retried, and the
+//! deployment only succeeds once the on-chain program data has the length of
+//! the local ELF and hashes to the same value.
+//!
+//! Chunk instructions only ever grow the account, so an ELF shorter than the
+//! program already in the account is refused rather than leaving the tail of
+//! the old program behind it.
+
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use bitcoin::key::UntweakedKeypair;
+use bitcoin::XOnlyPublicKey;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use sha256::digest;
+
+use crate::arch_program::pubkey::Pubkey;
+use crate::arch_program::system_instruction::SystemInstruction;
+use crate::constants::SEND_TRANSACTIONS;
+use crate::helper::{
+    build_signed_transaction, get_processed_transaction, process_result, read_account_info,
+    try_post_data,
+};
+use crate::processed_transaction::Status;
+use crate::runtime_transaction::{RuntimeTransaction, RUNTIME_TX_SIZE_LIMIT};
+
+/// Tuning knobs for [`deploy_program`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeployOptions {
+    /// Maximum number of chunk transactions in flight at the same time
+    pub max_parallel: usize,
+    /// Maximum number of passes over the pending chunks before giving up
+    pub max_attempts: usize,
+}
+
+impl Default for DeployOptions {
+    fn default() -> Self {
+        Self {
+            max_parallel: 8,
+            max_attempts: 3,
+        }
+    }
+}
+
+/// A slice of the ELF written at `offset` by a single `ExtendBytes` instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeployChunk<'a> {
+    pub index: usize,
+    pub offset: usize,
+    pub bytes: &'a [u8],
+}
+
+impl<'a> DeployChunk<'a> {
+    /// Returns true if the account data already holds the ELF bytes of this
+    /// chunk at its offset
+    ///
+    /// Chunks of zeros are never deployed: a pre-sized account holds zeros
+    /// that were not written, so they are sent again.
+    pub fn is_deployed(&self, on_chain_data: &[u8]) -> bool {
+        self.bytes.iter().any(|byte| *byte != 0)
+            && on_chain_data.get(self.offset..self.offset + self.bytes.len()) == Some(self.bytes)
+    }
+
+    /// Instruction data for `SystemInstruction::ExtendBytes`: offset, length, bytes
+    pub fn extend_bytes_data(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+
+        bytes.extend((self.offset as u32).to_le_bytes());
+        bytes.extend((self.bytes.len() as u32).to_le_bytes());
+        bytes.extend(self.bytes);
+
+        bytes
+    }
+}
+
+/// Splits the ELF into chunks of at most `chunk_size` bytes
+pub fn plan_chunks(elf: &[u8], chunk_size: usize) -> Vec<DeployChunk<'_>> {
+    elf.chunks(chunk_size)
+        .enumerate()
+        .map(|(index, bytes)| DeployChunk {
+            index,
+            offset: index * chunk_size,
+            bytes,
+        })
+        .collect()
+}
+
+/// Returns the chunks that are not yet present in `on_chain_data`
+pub fn pending_chunks<'a, 'b>(
+    chunks: &'b [DeployChunk<'a>],
+    on_chain_data: &[u8],
+) -> Vec<&'b DeployChunk<'a>> {
+    chunks
+        .iter()
+        .filter(|chunk| !chunk.is_deployed(on_chain_data))
+        .collect()
+}
+
+/// A chunk transaction that did not make it on-chain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChunkFailure {
+    pub chunk: usize,
+    pub attempt: usize,
+    pub txid: Option<String>,
+    pub reason: String,
+}
+
+/// Summary of a deployment run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeploymentReport {
+    pub program_id: Pubkey,
+    pub elf_size: usize,
+    pub elf_hash: String,
+    /// Length of the account data past its header
+    pub on_chain_size: usize,
+    /// Hash of the account data past its header
+    pub on_chain_hash: String,
+    pub total_chunks: usize,
+    /// Chunks that were already on-chain before this run started
+    pub skipped_chunks: usize,
+    /// Number of passes over the pending chunks
+    pub attempts: usize,
+    /// Txids of the chunk transactions that were processed successfully
+    pub txids: Vec<String>,
+    pub failures: Vec<ChunkFailure>,
+}
+
+impl DeploymentReport {
+    pub fn is_verified(&self) -> bool {
+        self.on_chain_size == self.elf_size && self.elf_hash == self.on_chain_hash
+    }
+}
+
+impl fmt::Display for DeploymentReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Program          : {:x}", self.program_id)?;
+        writeln!(f, "ELF size         : {} Bytes", self.elf_size)?;
+        writeln!(f, "ELF hash         : {}", self.elf_hash)?;
+        writeln!(f, "On-chain size    : {} Bytes", self.on_chain_size)?;
+        writeln!(f, "On-chain hash    : {}", self.on_chain_hash)?;
+        writeln!(
+            f,
+            "Chunks           : {} total, {} already deployed, {} sent",
+            self.total_chunks,
+            self.skipped_chunks,
+            self.txids.len()
+        )?;
+        writeln!(f, "Attempts         : {}", self.attempts)?;
+        writeln!(f, "Failed chunk txs : {}", self.failures.len())?;
+        write!(
+            f,
+            "Verified         : {}",
+            if self.is_verified() { "yes" } else { "NO" }
+        )
+    }
+}
+
+/// Fails if the account already holds more than `elf_len` bytes: chunks are
+/// written in place and never shrink the account, so the end of the previous
+/// program would remain after the new one
+pub fn check_redeploy_size(account: Pubkey, on_chain_len: usize, elf_len: usize) -> Result<()> {
+    if on_chain_len > elf_len {
+        return Err(anyhow!(
+            "Account {:x} holds {} bytes, more than the {} bytes of the ELF: \
+             use a new account",
+            account,
+            on_chain_len,
+            elf_len
+        ));
+    }
+    Ok(())
+}
+
+fn extend_bytes_transaction(
+    program_keypair: &UntweakedKeypair,
+    program_pubkey: Pubkey,
+    chunk: &DeployChunk,
+) -> RuntimeTransaction {
+    build_signed_transaction(
+        vec![SystemInstruction::new_extend_bytes_instruction(
+            chunk.extend_bytes_data(),
+            program_pubkey,
+        )],
+        &[*program_keypair],
+    )
+}
+
+/// Largest chunk the transactions built by `build_transaction` can carry
+/// within `RUNTIME_TX_SIZE_LIMIT`, measured on the transaction of an empty
+/// chunk as every chunk byte adds one byte to it
+pub(crate) fn max_chunk_len<F>(build_transaction: &F) -> usize
+where
+    F: Fn(&DeployChunk) -> RuntimeTransaction,
+{
+    let empty = DeployChunk {
+        index: 0,
+        offset: 0,
+        bytes: &[],
+    };
+    RUNTIME_TX_SIZE_LIMIT - build_transaction(&empty).serialize().len()
+}
+
+fn send_transactions(url: &str, txs: &[RuntimeTransaction]) -> Result<Vec<String>> {
+    process_result(try_post_data(url, SEND_TRANSACTIONS, txs)?)?
+        .as_array()
+        .ok_or_else(|| anyhow!("send_transactions did not return an array"))?
+        .iter()
+        .map(|txid| {
+            txid.as_str()
+                .map(|txid| txid.to_string())
+                .ok_or_else(|| anyhow!("send_transactions returned a non string txid"))
+        })
+        .collect()
+}
+
+/// Waits for every txid and returns, per txid, `None` on success or the failure reason
+fn wait_for_transactions(url: &str, txids: &[String]) -> Vec<Option<String>> {
+    std::thread::scope(|scope| {
+        let handles = txids
+            .iter()
+            .map(|txid| {
+                scope.spawn(move || match get_processed_transaction(url, txid.clone()) {
+                    Ok(processed_tx) => match processed_tx.status {
+                        Status::Failed(reason) => Some(reason),
+                        _ => None,
+                    },
+                    Err(err) => Some(err.to_string()),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Some("polling thread panicked".to_string()))
+            })
+            .collect()
+    })
+}
+
+fn on_chain_data(url: &str, pubkey: Pubkey, data_offset: usize) -> Result<Vec<u8>> {
+    read_account_info(url, pubkey)
+        .map(|account| account.data.get(data_offset..).unwrap_or_default().to_vec())
+        .map_err(|e| anyhow!("Unable to read account {:x}: {}", pubkey, e))
+}
+
+/// Uploads `elf` into the (already created) program account owned by `program_keypair`.
+///
+/// Only chunks whose bytes are not yet present on-chain are sent, so calling
+/// this again after an interrupted deployment resumes where it stopped.
+/// Returns an error before sending anything if the account holds a longer
+/// program, see [check_redeploy_size].
+/// Returns an error if the on-chain data still differs from `elf` once all
+/// attempts are exhausted.
+pub fn deploy_program(
+    url: &str,
+    program_keypair: UntweakedKeypair,
+    elf: &[u8],
+    options: DeployOptions,
+) -> Result<DeploymentReport> {
+    let program_pubkey =
+        Pubkey::from_slice(&XOnlyPublicKey::from_keypair(&program_keypair).0.serialize());
+
+    upload_chunks(url, program_pubkey, 0, elf, options, |chunk| {
+        extend_bytes_transaction(&program_keypair, program_pubkey, chunk)
+    })
+}
+
+/// Writes `elf` into the data of `account`, starting `data_offset` bytes into
+/// it, one transaction per chunk as built by `build_transaction`, with chunks
+/// as large as these transactions allow.
+pub(crate) fn upload_chunks<F>(
+    url: &str,
+    account: Pubkey,
+    data_offset: usize,
+    elf: &[u8],
+    options: DeployOptions,
+    build_transaction: F,
+) -> Result<DeploymentReport>
+where
+    F: Fn(&DeployChunk) -> RuntimeTransaction,
+{
+    let chunks = plan_chunks(elf, max_chunk_len(&build_transaction));
+
+    let mut report = DeploymentReport {
+        program_id: account,
+        elf_size: elf.len(),
+        elf_hash: digest(elf),
+        on_chain_size: 0,
+        on_chain_hash: String::new(),
+        total_chunks: chunks.len(),
+        skipped_chunks: 0,
+        attempts: 0,
+        txids: vec![],
+        failures: vec![],
+    };
+
+    let pb = ProgressBar::new(chunks.len() as u64);
+
+    pb.set_style(ProgressStyle::default_bar()
+        .progress_chars("#>-")
+        .template("{spinner:.green}[{elapsed_precise:.blue}] {msg:.blue} [{bar:100.green/blue}] {pos}/{len} ({eta})").unwrap());
+
+    pb.set_message("Successfully Processed Deployment Transactions :");
+
+    // chunks this run saw processed, zero chunks included
+    let mut confirmed = vec![false; chunks.len()];
+
+    for attempt in 1..=options.max_attempts.max(1) {
+        let data = on_chain_data(url, account, data_offset)?;
+        let mut pending = pending_chunks(&chunks, &data);
+        pending.retain(|chunk| !confirmed[chunk.index]);
+
+        if attempt == 1 {
+            check_redeploy_size(account, data.len(), elf.len())?;
+            report.skipped_chunks = chunks.len() - pending.len();
+            pb.inc(report.skipped_chunks as u64);
+        }
+
+        if pending.is_empty() {
+            break;
+        }
+
+        report.attempts = attempt;
+
+        for batch in pending.chunks(options.max_parallel.max(1)) {
+            let txs = batch
+                .iter()
+                .map(|chunk| build_transaction(chunk))
+                .collect::<Vec<RuntimeTransaction>>();
+
+            let txids = match send_transactions(url, &txs) {
+                Ok(txids) => txids,
+                Err(err) => {
+                    report
+                        .failures
+                        .extend(batch.iter().map(|chunk| ChunkFailure {
+                            chunk: chunk.index,
+                            attempt,
+                            txid: None,
+                            reason: err.to_string(),
+                        }));
+                    continue;
+                }
+            };
+
+            let outcomes = wait_for_transactions(url, &txids);
+
+            for ((chunk, txid), outcome) in batch.iter().zip(txids).zip(outcomes) {
+                match outcome {
+                    None => {
+                        pb.inc(1);
+                        confirmed[chunk.index] = true;
+                        report.txids.push(txid);
+                    }
+                    Some(reason) => report.failures.push(ChunkFailure {
+                        chunk: chunk.index,
+                        attempt,
+                        txid: Some(txid),
+                        reason,
+                    }),
+                }
+            }
+        }
+    }
+
+    pb.finish();
+
+    let data = on_chain_data(url, account, data_offset)?;
+    report.on_chain_size = data.len();
+    report.on_chain_hash = digest(&data);
+
+    if !report.is_verified() {
+        return Err(anyhow!(
+            "Program data on-chain does not match the local ELF\n{}",
+            report
+        ));
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_redeploy_size, extend_bytes_transaction, max_chunk_len, pending_chunks, plan_chunks,
+        DeployChunk, DeploymentReport,
+    };
+    use crate::arch_program::pubkey::Pubkey;
+    use crate::helper::extend_bytes_max_len;
+    use crate::runtime_transaction::RUNTIME_TX_SIZE_LIMIT;
+    use bitcoin::key::UntweakedKeypair;
+    use bitcoin::secp256k1::Secp256k1;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_extend_bytes_data_layout() {
+        let elf = [7u8; 10];
+        let chunks = plan_chunks(&elf, 4);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2].offset, 8);
+        assert_eq!(
+            chunks[2].extend_bytes_data(),
+            vec![8, 0, 0, 0, 2, 0, 0, 0, 7, 7]
+        );
+    }
+
+    #[test]
+    fn test_pending_chunks_after_partial_deploy() {
+        let elf = (0..100u8).collect::<Vec<u8>>();
+        let chunks = plan_chunks(&elf, 30);
+
+        // Second chunk landed, the rest did not
+        let mut on_chain = vec![0u8; 60];
+        on_chain[30..60].copy_from_slice(&elf[30..60]);
+
+        let pending = pending_chunks(&chunks, &on_chain)
+            .iter()
+            .map(|chunk| chunk.index)
+            .collect::<Vec<usize>>();
+
+        assert_eq!(pending, vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn test_pending_zero_chunks_of_presized_account() {
+        let mut elf = vec![1u8; 90];
+        elf[30..60].fill(0);
+        let chunks = plan_chunks(&elf, 30);
+
+        let pending = pending_chunks(&chunks, &[0u8; 90])
+            .iter()
+            .map(|chunk| chunk.index)
+            .collect::<Vec<usize>>();
+        assert_eq!(pending, vec![0, 1, 2]);
+
+        let pending = pending_chunks(&chunks, &elf)
+            .iter()
+            .map(|chunk| chunk.index)
+            .collect::<Vec<usize>>();
+        assert_eq!(pending, vec![1]);
+    }
+
+    #[test]
+    fn test_redeploy_shorter_elf() {
+        let old_elf = vec![7u8; 15];
+        let elf = vec![7u8; 10];
+        let report = |on_chain: &[u8]| DeploymentReport {
+            program_id: Pubkey::system_program(),
+            elf_size: elf.len(),
+            elf_hash: sha256::digest(&elf),
+            on_chain_size: on_chain.len(),
+            on_chain_hash: sha256::digest(on_chain),
+            total_chunks: 1,
+            skipped_chunks: 0,
+            attempts: 1,
+            txids: vec![],
+            failures: vec![],
+        };
+
+        // every chunk of the shorter ELF is already in place, yet the tail of
+        // the old program is still part of the account
+        assert!(pending_chunks(&plan_chunks(&elf, 4), &old_elf).is_empty());
+        assert!(!report(&old_elf).is_verified());
+        assert!(check_redeploy_size(Pubkey::system_program(), old_elf.len(), elf.len()).is_err());
+
+        assert!(report(&elf).is_verified());
+        assert!(!report(&elf[..9]).is_verified());
+        assert!(!report(&[vec![7u8; 9], vec![9]].concat()).is_verified());
+        assert!(check_redeploy_size(Pubkey::system_program(), elf.len(), elf.len()).is_ok());
+        assert!(check_redeploy_size(Pubkey::system_program(), 0, elf.len()).is_ok());
+    }
+
+    #[test]
+    fn test_max_size_extend_bytes_transaction_fits() {
+        let keypair = UntweakedKeypair::from_seckey_slice(&Secp256k1::new(), &[7; 32]).unwrap();
+        let program_pubkey = Pubkey([1; 32]);
+        let build_transaction =
+            |chunk: &DeployChunk| extend_bytes_transaction(&keypair, program_pubkey, chunk);
+
+        let chunk_len = max_chunk_len(&build_transaction);
+        assert_eq!(chunk_len, extend_bytes_max_len());
+
+        let elf = vec![7u8; chunk_len + 1];
+        let chunks = plan_chunks(&elf, chunk_len);
+        let tx = build_transaction(&chunks[0]);
+        assert_eq!(tx.serialize().len(), RUNTIME_TX_SIZE_LIMIT);
+        assert!(tx.check_tx_size_limit().is_ok());
+
+        let chunks = plan_chunks(&elf, chunk_len + 1);
+        assert!(build_transaction(&chunks[0]).check_tx_size_limit().is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_plan_chunks_covers_elf(
+            elf in prop::collection::vec(any::<u8>(), 0..4096),
+            chunk_size in 1..1024usize,
+        ) {
+            let chunks = plan_chunks(&elf, chunk_size);
+
+            let rebuilt = chunks.iter().flat_map(|chunk| chunk.bytes.to_vec()).collect::<Vec<u8>>();
+            assert_eq!(rebuilt, elf.clone());
+            assert!(pending_chunks(&chunks, &elf)
+                .iter()
+                .all(|chunk| chunk.bytes.iter().all(|byte| *byte == 0)));
+        }
+    }
+}
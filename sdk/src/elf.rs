@@ -0,0 +1,308 @@
+//! Pre-flight validation of program ELFs
+//!
+//! The node only reports a broken ELF (`ElfFailedToParse`,
+//! `ProgramFailedToCompile`, unresolved syscalls) once every chunk has been
+//! uploaded. [validate_elf] runs the same checks locally so that a bad file is
+//! rejected before a single transaction is spent.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use goblin::elf::{header, section_header, Elf};
+use serde::{Deserialize, Serialize};
+
+use crate::arch_program::entrypoint::MAX_PERMITTED_DATA_LENGTH;
+use crate::arch_program::syscalls::{PROPOSED_SYSCALLS, SYSCALLS};
+use crate::helper::extend_bytes_max_len;
+
+/// `e_machine` of objects built for the original eBPF target
+pub const EM_BPF: u16 = 247;
+/// `e_machine` of objects built for the SBF target
+pub const EM_SBF: u16 = 263;
+
+/// Symbol the runtime jumps to, exported by the `entrypoint!` macro
+pub const ENTRYPOINT_SYMBOL: &str = "entrypoint";
+
+/// Imports emitted by the SBF standard library and compiler builtins rather
+/// than by arch_program. The runtime resolves them like any other syscall.
+pub const TOOLCHAIN_SYSCALLS: &[&str] = &[
+    "abort",
+    "sol_panic_",
+    "sol_memcpy_",
+    "sol_memmove_",
+    "sol_memset_",
+    "sol_memcmp_",
+    "sol_alloc_free_",
+];
+
+/// What was found in a program ELF
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ElfReport {
+    pub size: usize,
+    /// Largest program account the runtime accepts
+    pub max_size: usize,
+    /// Number of `ExtendBytes` transactions needed to upload the ELF
+    pub transactions: usize,
+    pub machine: u16,
+    pub is_shared_object: bool,
+    pub has_entrypoint: bool,
+    /// Every imported symbol, sorted by name
+    pub syscalls: Vec<String>,
+    /// Imported symbols the runtime does not provide
+    pub unknown_syscalls: Vec<String>,
+    /// Imported syscalls arch_program declares for proposed node features,
+    /// which no node serves yet
+    pub proposed_syscalls: Vec<String>,
+}
+
+impl ElfReport {
+    /// Human readable reasons why the ELF would be rejected by the node
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+
+        if self.machine != EM_BPF && self.machine != EM_SBF {
+            problems.push(format!(
+                "not an SBF/eBPF object (e_machine {})",
+                self.machine
+            ));
+        }
+        if !self.is_shared_object {
+            problems.push("not a shared object, build it with cargo build-sbf".to_string());
+        }
+        if !self.has_entrypoint {
+            problems.push(format!(
+                "missing `{}` symbol, is `entrypoint!` used?",
+                ENTRYPOINT_SYMBOL
+            ));
+        }
+        for syscall in &self.unknown_syscalls {
+            problems.push(format!("unknown syscall `{}`", syscall));
+        }
+        for syscall in &self.proposed_syscalls {
+            problems.push(format!("syscall `{}` is not served by nodes yet", syscall));
+        }
+        if self.size > self.max_size {
+            problems.push(format!(
+                "{} Bytes exceeds the maximum program size of {} Bytes",
+                self.size, self.max_size
+            ));
+        }
+
+        problems
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.problems().is_empty()
+    }
+}
+
+impl fmt::Display for ElfReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "ELF size         : {} / {} Bytes ({} transactions)",
+            self.size, self.max_size, self.transactions
+        )?;
+        writeln!(f, "Entrypoint       : {}", self.has_entrypoint)?;
+        write!(f, "Syscalls         :")?;
+        for syscall in &self.syscalls {
+            if self.unknown_syscalls.contains(syscall) {
+                write!(f, " {} (UNKNOWN)", syscall)?;
+            } else if self.proposed_syscalls.contains(syscall) {
+                write!(f, " {} (PROPOSED)", syscall)?;
+            } else {
+                write!(f, " {}", syscall)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses `elf` and collects what [ElfReport::problems] needs to know.
+/// Only fails if the file cannot be parsed as an ELF at all.
+pub fn inspect_elf(elf: &[u8]) -> Result<ElfReport> {
+    let parsed = Elf::parse(elf).map_err(|e| anyhow!("Unable to parse ELF: {}", e))?;
+
+    let mut imports = BTreeSet::new();
+    let mut has_entrypoint = false;
+
+    let symbols = parsed
+        .dynsyms
+        .iter()
+        .map(|sym| (sym, parsed.dynstrtab.get_at(sym.st_name)))
+        .chain(
+            parsed
+                .syms
+                .iter()
+                .map(|sym| (sym, parsed.strtab.get_at(sym.st_name))),
+        );
+    for (sym, name) in symbols {
+        let Some(name) = name.filter(|name| !name.is_empty()) else {
+            continue;
+        };
+
+        if sym.st_shndx == section_header::SHN_UNDEF as usize {
+            imports.insert(name.to_string());
+        } else if name == ENTRYPOINT_SYMBOL {
+            has_entrypoint = true;
+        }
+    }
+
+    let proposed_syscalls = imports
+        .iter()
+        .filter(|name| PROPOSED_SYSCALLS.contains(&name.as_str()))
+        .cloned()
+        .collect();
+    let unknown_syscalls = imports
+        .iter()
+        .filter(|name| {
+            ![SYSCALLS, PROPOSED_SYSCALLS, TOOLCHAIN_SYSCALLS]
+                .iter()
+                .any(|known| known.contains(&name.as_str()))
+        })
+        .cloned()
+        .collect();
+
+    Ok(ElfReport {
+        size: elf.len(),
+        max_size: MAX_PERMITTED_DATA_LENGTH,
+        transactions: elf.len().div_ceil(extend_bytes_max_len()),
+        machine: parsed.header.e_machine,
+        is_shared_object: parsed.header.e_type == header::ET_DYN,
+        has_entrypoint,
+        syscalls: imports.into_iter().collect(),
+        unknown_syscalls,
+        proposed_syscalls,
+    })
+}
+
+/// Inspects `elf` and fails with every problem found if the node would reject it
+pub fn validate_elf(elf: &[u8]) -> Result<ElfReport> {
+    let report = inspect_elf(elf)?;
+
+    let problems = report.problems();
+    if !problems.is_empty() {
+        return Err(anyhow!("Invalid program ELF: {}", problems.join(", ")));
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inspect_elf, validate_elf, EM_SBF};
+
+    /// Builds a minimal 64-bit little endian ELF with a `.symtab` holding the
+    /// given defined and undefined symbols
+    fn build_elf(e_type: u16, machine: u16, defined: &[&str], imported: &[&str]) -> Vec<u8> {
+        let mut strtab = vec![0u8];
+        let mut symtab = vec![0u8; 24];
+        for (name, shndx) in defined
+            .iter()
+            .map(|name| (name, 1u16))
+            .chain(imported.iter().map(|name| (name, 0u16)))
+        {
+            symtab.extend((strtab.len() as u32).to_le_bytes());
+            symtab.push(if shndx == 0 { 0x10 } else { 0x12 });
+            symtab.push(0);
+            symtab.extend(shndx.to_le_bytes());
+            symtab.extend((if shndx == 0 { 0u64 } else { 0x120 }).to_le_bytes());
+            symtab.extend(0u64.to_le_bytes());
+            strtab.extend(name.as_bytes());
+            strtab.push(0);
+        }
+        let shstrtab = b"\0.symtab\0.strtab\0.shstrtab\0".to_vec();
+
+        let symtab_offset = 64;
+        let strtab_offset = symtab_offset + symtab.len();
+        let shstrtab_offset = strtab_offset + strtab.len();
+        let shoff = shstrtab_offset + shstrtab.len();
+
+        let mut elf = vec![0x7f, b'E', b'L', b'F', 2, 1, 1];
+        elf.resize(16, 0);
+        elf.extend(e_type.to_le_bytes());
+        elf.extend(machine.to_le_bytes());
+        elf.extend(1u32.to_le_bytes());
+        elf.extend(0u64.to_le_bytes());
+        elf.extend(0u64.to_le_bytes());
+        elf.extend((shoff as u64).to_le_bytes());
+        elf.extend(0u32.to_le_bytes());
+        for field in [64u16, 56, 0, 64, 4, 3] {
+            elf.extend(field.to_le_bytes());
+        }
+        elf.extend(&symtab);
+        elf.extend(&strtab);
+        elf.extend(&shstrtab);
+
+        let mut section = |name: u32, sh_type: u32, offset: usize, size: usize, link: u32| {
+            let entsize = if sh_type == 2 { 24u64 } else { 0 };
+            elf.extend(name.to_le_bytes());
+            elf.extend(sh_type.to_le_bytes());
+            elf.extend(0u64.to_le_bytes());
+            elf.extend(0u64.to_le_bytes());
+            elf.extend((offset as u64).to_le_bytes());
+            elf.extend((size as u64).to_le_bytes());
+            elf.extend(link.to_le_bytes());
+            elf.extend((if sh_type == 2 { 1u32 } else { 0 }).to_le_bytes());
+            elf.extend(1u64.to_le_bytes());
+            elf.extend(entsize.to_le_bytes());
+        };
+        section(0, 0, 0, 0, 0);
+        section(1, 2, symtab_offset, symtab.len(), 2);
+        section(9, 3, strtab_offset, strtab.len(), 0);
+        section(17, 3, shstrtab_offset, shstrtab.len(), 0);
+
+        elf
+    }
+
+    #[test]
+    fn test_valid_program() {
+        let elf = build_elf(3, EM_SBF, &["entrypoint"], &["sol_log_", "abort"]);
+        let report = validate_elf(&elf).unwrap();
+
+        assert!(report.has_entrypoint);
+        assert_eq!(report.syscalls, vec!["abort", "sol_log_"]);
+        assert!(report.unknown_syscalls.is_empty());
+        assert!(report.proposed_syscalls.is_empty());
+        assert_eq!(report.size, elf.len());
+        assert_eq!(report.transactions, 1);
+    }
+
+    #[test]
+    fn test_invalid_programs() {
+        let unknown = build_elf(3, EM_SBF, &["entrypoint"], &["sol_log_", "sol_curve_"]);
+        let report = inspect_elf(&unknown).unwrap();
+        assert_eq!(report.unknown_syscalls, vec!["sol_curve_"]);
+        assert!(validate_elf(&unknown)
+            .unwrap_err()
+            .to_string()
+            .contains("sol_curve_"));
+
+        let proposed = build_elf(
+            3,
+            EM_SBF,
+            &["entrypoint"],
+            &["sol_log_", "arch_get_bitcoin_tx_chunk"],
+        );
+        let report = inspect_elf(&proposed).unwrap();
+        assert!(report.unknown_syscalls.is_empty());
+        assert_eq!(report.proposed_syscalls, vec!["arch_get_bitcoin_tx_chunk"]);
+        assert!(validate_elf(&proposed)
+            .unwrap_err()
+            .to_string()
+            .contains("arch_get_bitcoin_tx_chunk"));
+
+        let no_entrypoint = build_elf(3, EM_SBF, &["process"], &[]);
+        assert!(!inspect_elf(&no_entrypoint).unwrap().has_entrypoint);
+        assert!(validate_elf(&no_entrypoint).is_err());
+
+        let x86 = build_elf(3, 62, &["entrypoint"], &[]);
+        assert_eq!(inspect_elf(&x86).unwrap().problems().len(), 1);
+
+        let executable = build_elf(2, EM_SBF, &["entrypoint"], &[]);
+        assert!(!inspect_elf(&executable).unwrap().is_valid());
+
+        assert!(inspect_elf(b"not an elf").is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! Decoding of the events programs log with `emit!`.
+//!
+//! `emit!` logs an event as a `Program data:` line holding its 8 byte
+//! discriminator followed by its borsh encoding. [`events`] picks the events
+//! of one type out of a transaction's logs, while [`raw_events`] lists all of
+//! them for indexers dispatching on the discriminator.
+
+use borsh::BorshDeserialize;
+
+use crate::arch_program::event::{decode_event, Event};
+use crate::arch_program::log::parse_data_log;
+use crate::processed_transaction::ProcessedTransaction;
+
+/// An event found in the logs, not decoded yet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawEvent {
+    pub discriminator: [u8; 8],
+    /// Borsh encoding of the event
+    pub data: Vec<u8>,
+}
+
+impl RawEvent {
+    /// Whether the event is of type `E`
+    pub fn is<E: Event>(&self) -> bool {
+        self.discriminator == E::DISCRIMINATOR
+    }
+
+    /// The event as an `E`, `None` when it is of another type or does not
+    /// decode
+    pub fn decode<E: Event + BorshDeserialize>(&self) -> Option<E> {
+        if !self.is::<E>() {
+            return None;
+        }
+        E::try_from_slice(&self.data).ok()
+    }
+}
+
+/// Every program data logged, in log order, as events
+///
+/// Data shorter than a discriminator cannot be an event and is skipped.
+pub fn raw_events<S: AsRef<str>>(logs: &[S]) -> Vec<RawEvent> {
+    logs.iter()
+        .filter_map(|log| parse_data_log(log.as_ref()))
+        .flatten()
+        .filter_map(|data| {
+            let (discriminator, data) = data.split_first_chunk::<8>()?;
+            Some(RawEvent {
+                discriminator: *discriminator,
+                data: data.to_vec(),
+            })
+        })
+        .collect()
+}
+
+/// The events of type `E` in the logs, in log order
+pub fn events<E: Event + BorshDeserialize, S: AsRef<str>>(logs: &[S]) -> Vec<E> {
+    logs.iter()
+        .filter_map(|log| parse_data_log(log.as_ref()))
+        .flatten()
+        .filter_map(|data| decode_event(&data))
+        .collect()
+}
+
+impl ProcessedTransaction {
+    /// The events of type `E` the transaction's programs emitted
+    pub fn events<E: Event + BorshDeserialize>(&self) -> Vec<E> {
+        events(&self.logs)
+    }
+
+    /// Every event the transaction's programs emitted
+    pub fn raw_events(&self) -> Vec<RawEvent> {
+        raw_events(&self.logs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch_program::log::format_data_log;
+    use crate::arch_program::pubkey::Pubkey;
+    use borsh::BorshSerialize;
+    use proptest::prelude::*;
+
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize, Event)]
+    struct Minted {
+        mint: Pubkey,
+        amount: u64,
+    }
+
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize, Event)]
+    struct Transferred {
+        from: Pubkey,
+        to: Pubkey,
+        amount: u64,
+    }
+
+    fn logs(minted: &Minted, transferred: &Transferred) -> Vec<String> {
+        vec![
+            "Program log: Instruction: Mint".to_string(),
+            format_data_log(&[&minted.data()]),
+            "circulating_supply: 5".to_string(),
+            format_data_log(&[&transferred.data()]),
+            format_data_log(&[&[1, 2, 3]]),
+        ]
+    }
+
+    #[test]
+    fn test_events() {
+        let minted = Minted {
+            mint: Pubkey([1; 32]),
+            amount: 5,
+        };
+        let transferred = Transferred {
+            from: Pubkey([2; 32]),
+            to: Pubkey([3; 32]),
+            amount: 2,
+        };
+        let logs = logs(&minted, &transferred);
+
+        assert_eq!(events::<Minted, _>(&logs), vec![minted]);
+        assert_eq!(events::<Transferred, _>(&logs), vec![transferred]);
+
+        let raw = raw_events(&logs);
+        assert_eq!(raw.len(), 2);
+        assert!(raw[0].is::<Minted>() && !raw[0].is::<Transferred>());
+        assert_eq!(raw[1].decode::<Minted>(), None);
+        assert_eq!(raw[1].decode::<Transferred>().unwrap().amount, 2);
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_events(amounts in prop::collection::vec(any::<u64>(), 0..10)) {
+            let logs: Vec<String> = amounts
+                .iter()
+                .map(|amount| format_data_log(&[&Minted { mint: Pubkey([1; 32]), amount: *amount }.data()]))
+                .collect();
+            let decoded: Vec<u64> = events::<Minted, _>(&logs).iter().map(|event| event.amount).collect();
+            prop_assert_eq!(decoded, amounts);
+        }
+
+        #[test]
+        fn fuzz_raw_events_never_panic(logs in prop::collection::vec("(Program data: )?[A-Za-z0-9+/= ]*", 0..5)) {
+            raw_events(&logs);
+        }
+    }
+}
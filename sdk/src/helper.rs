@@ -12,23 +12,25 @@ use bitcoin::{
     Amount, OutPoint, ScriptBuf, Sequence, TapSighashType, Transaction, TxIn, Txid, Witness,
 };
 use bitcoincore_rpc::{Auth, Client, RawTx, RpcApi};
-use indicatif::{ProgressBar, ProgressStyle};
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::{from_str, json, Value};
 use std::fs;
 use std::str::FromStr;
 
-use crate::processed_transaction::ProcessedTransaction;
+use crate::deploy::{deploy_program, DeployOptions, DeploymentReport};
+use crate::elf::{validate_elf, ElfReport};
+use crate::loader::ProgramInfo;
+use crate::processed_transaction::{ProcessedTransaction, Status};
 
 use crate::arch_program::instruction::Instruction;
 use crate::arch_program::message::Message;
 use crate::arch_program::pubkey::Pubkey;
 use crate::arch_program::system_instruction::SystemInstruction;
 use crate::constants::{
-    BITCOIN_NETWORK, BITCOIN_NODE_ENDPOINT, BITCOIN_NODE_PASSWORD, BITCOIN_NODE_USERNAME,
-    CALLER_FILE_PATH, GET_ACCOUNT_ADDRESS, GET_BEST_BLOCK_HASH, GET_BLOCK,
-    GET_PROCESSED_TRANSACTION, GET_PROGRAM, NODE1_ADDRESS, READ_ACCOUNT_INFO,
+    ACCOUNT_NOT_FOUND_CODE, BITCOIN_NETWORK, BITCOIN_NODE_ENDPOINT, BITCOIN_NODE_PASSWORD,
+    BITCOIN_NODE_USERNAME, CALLER_FILE_PATH, GET_ACCOUNT_ADDRESS, GET_BEST_BLOCK_HASH, GET_BLOCK,
+    GET_PROCESSED_TRANSACTION, NODE1_ADDRESS, READ_ACCOUNT_INFO, SEND_TRANSACTION,
     TRANSACTION_NOT_FOUND_CODE,
 };
 use crate::models::CallerInfo;
@@ -88,6 +90,15 @@ pub fn post(url: &str, method: &str) -> String {
 }
 
 pub fn post_data<T: Serialize + std::fmt::Debug>(url: &str, method: &str, params: T) -> String {
+    try_post_data(url, method, params).expect("post method should not fail")
+}
+
+/// Same as [post_data], but returns an error instead of panicking on transport failures
+pub fn try_post_data<T: Serialize + std::fmt::Debug>(
+    url: &str,
+    method: &str,
+    params: T,
+) -> Result<String> {
     let client = reqwest::blocking::Client::new();
     let res = client
         .post(url)
@@ -98,11 +109,11 @@ pub fn post_data<T: Serialize + std::fmt::Debug>(url: &str, method: &str, params
             "method": method,
             "params": params,
         }))
-        .send();
+        .send()
+        .map_err(|e| anyhow!("{} request failed: {}", method, e))?;
 
-    res.expect("post method should not fail")
-        .text()
-        .expect("result should be text decodable")
+    res.text()
+        .map_err(|e| anyhow!("{} response is not text decodable: {}", method, e))
 }
 
 /// Returns a caller information using the secret key file specified
@@ -132,7 +143,7 @@ pub fn with_secret_key_file(file_path: &str) -> Result<(UntweakedKeypair, Pubkey
     Ok((keypair, pubkey))
 }
 
-fn extend_bytes_max_len() -> usize {
+pub(crate) fn extend_bytes_max_len() -> usize {
     let message = Message {
         signers: vec![Pubkey::system_program()],
         instructions: vec![SystemInstruction::new_extend_bytes_instruction(
@@ -206,10 +217,11 @@ pub fn sign_and_send_instruction(
     Ok((result, hashed_instruction))
 }
 
-pub fn sign_and_send_transaction(
+/// Builds a runtime transaction holding `instructions`, signed by every keypair in `signers`
+pub fn build_signed_transaction(
     instructions: Vec<Instruction>,
-    signers: Vec<UntweakedKeypair>,
-) -> Result<String> {
+    signers: &[UntweakedKeypair],
+) -> RuntimeTransaction {
     let pubkeys = signers
         .iter()
         .map(|signer| Pubkey::from_slice(&XOnlyPublicKey::from_keypair(signer).0.serialize()))
@@ -227,11 +239,37 @@ pub fn sign_and_send_transaction(
         })
         .collect::<Vec<Signature>>();
 
-    let params = RuntimeTransaction {
+    RuntimeTransaction {
         version: 0,
         signatures,
         message,
-    };
+    }
+}
+
+/// Sends `instructions` signed by `signers` and waits until they are processed
+pub fn send_and_confirm(
+    url: &str,
+    instructions: Vec<Instruction>,
+    signers: &[UntweakedKeypair],
+) -> Result<String> {
+    let transaction = build_signed_transaction(instructions, signers);
+
+    let txid = process_result(try_post_data(url, SEND_TRANSACTION, transaction)?)?
+        .as_str()
+        .ok_or_else(|| anyhow!("send_transaction did not return a txid"))?
+        .to_string();
+
+    match get_processed_transaction(url, txid.clone())?.status {
+        Status::Failed(reason) => Err(anyhow!("Transaction {} failed: {}", txid, reason)),
+        _ => Ok(txid),
+    }
+}
+
+pub fn sign_and_send_transaction(
+    instructions: Vec<Instruction>,
+    signers: Vec<UntweakedKeypair>,
+) -> Result<String> {
+    let params = build_signed_transaction(instructions, &signers);
     let result = process_result(post_data(NODE1_ADDRESS, "send_transaction", params))
         .expect("send_transaction should not fail")
         .as_str()
@@ -241,103 +279,29 @@ pub fn sign_and_send_transaction(
     Ok(result)
 }
 
-/// Deploys the HelloWorld program using the compiled ELF
-pub fn deploy_program_txs(program_keypair: UntweakedKeypair, elf_path: &str) {
-    let program_pubkey =
-        Pubkey::from_slice(&XOnlyPublicKey::from_keypair(&program_keypair).0.serialize());
-
-    let elf = fs::read(elf_path).expect("elf path should be available");
-
-    //println!("Program size is : {} Bytes", elf.len());
-
-    let txs = elf
-        .chunks(extend_bytes_max_len())
-        .enumerate()
-        .map(|(i, chunk)| {
-            let mut bytes = vec![];
-
-            let offset: u32 = (i * extend_bytes_max_len()) as u32;
-            let len: u32 = chunk.len() as u32;
-
-            bytes.extend(offset.to_le_bytes());
-            bytes.extend(len.to_le_bytes());
-            bytes.extend(chunk);
-
-            let message = Message {
-                signers: vec![program_pubkey],
-                instructions: vec![SystemInstruction::new_extend_bytes_instruction(
-                    bytes,
-                    program_pubkey,
-                )],
-            };
-
-            let digest_slice = message.hash();
-
-            RuntimeTransaction {
-                version: 0,
-                signatures: vec![Signature(
-                    sign_message_bip322(&program_keypair, &digest_slice, BITCOIN_NETWORK).to_vec(),
-                )],
-                message,
-            }
-        })
-        .collect::<Vec<RuntimeTransaction>>();
-
-    /*println!(
-        "Program deployment split into {} Chunks, sending {} runtime transactions",
-        txs.len(),
-        txs.len()
-    );
-     */
-    let txids = process_result(post_data(NODE1_ADDRESS, "send_transactions", txs))
-        .expect("send_transaction should not fail")
-        .as_array()
-        .expect("cannot convert result to array")
-        .iter()
-        .map(|r| {
-            r.as_str()
-                .expect("cannot convert object to string")
-                .to_string()
-        })
-        .collect::<Vec<String>>();
-
-    let pb = ProgressBar::new(txids.len() as u64);
-
-    pb.set_style(ProgressStyle::default_bar()
-        .progress_chars("#>-")
-        .template("{spinner:.green}[{elapsed_precise:.blue}] {msg:.blue} [{bar:100.green/blue}] {pos}/{len} ({eta})").unwrap());
-
-    pb.set_message("Successfully Processed Deployment Transactions :");
-
-    for txid in txids {
-        let _processed_tx = get_processed_transaction(NODE1_ADDRESS, txid.clone())
-            .expect("get processed transaction should not fail");
-        pb.inc(1);
-        pb.set_message("Successfully Processed Deployment Transactions :");
-    }
-
-    pb.finish();
-
-    // for tx_batch in txs.chunks(12) {
-    //     let mut txids = vec![];
-    //     for tx in tx_batch {
-    //         let txid = process_result(post_data(NODE1_ADDRESS, "send_transaction", tx))
-    //             .expect("send_transaction should not fail")
-    //             .as_str()
-    //             .expect("cannot convert result to string")
-    //             .to_string();
-
-    //         println!("sent tx {:?}", txid);
-    //         txids.push(txid);
-    //     };
-
-    //     for txid in txids {
-    //         let processed_tx = get_processed_transaction(NODE1_ADDRESS, txid.clone())
-    //             .expect("get processed transaction should not fail");
+/// Deploys the program ELF found at `elf_path` into the program account.
+///
+/// The ELF is checked with [validate_elf] before any transaction is sent.
+/// Chunks already present on-chain are skipped, so an interrupted deployment
+/// can simply be re-run. See [deploy_program] for details.
+///
+/// Returns what validation found in the ELF along with the deployment report.
+pub fn deploy_program_txs(
+    program_keypair: UntweakedKeypair,
+    elf_path: &str,
+) -> Result<(ElfReport, DeploymentReport)> {
+    let elf = fs::read(elf_path).map_err(|e| anyhow!("Unable to read {}: {}", elf_path, e))?;
+
+    let elf_report = validate_elf(&elf).map_err(|e| anyhow!("{}: {}", elf_path, e))?;
+
+    let report = deploy_program(
+        NODE1_ADDRESS,
+        program_keypair,
+        &elf,
+        DeployOptions::default(),
+    )?;
 
-    //         println!("{:?}", read_account_info(NODE1_ADDRESS, program_pubkey.clone()));
-    //     }
-    // }
+    Ok((elf_report, report))
 }
 
 /// Starts Key Exchange by calling the RPC method
@@ -369,6 +333,22 @@ pub fn read_account_info(url: &str, pubkey: Pubkey) -> Result<AccountInfoResult>
     let result = process_result(post_data(url, READ_ACCOUNT_INFO, pubkey))?;
     serde_json::from_value(result).map_err(|_| anyhow!("Unable to decode read_account_info result"))
 }
+
+/// Same as [read_account_info], but returns `None` when the account does not
+/// exist and an error instead of panicking on transport failures
+pub fn try_read_account_info(url: &str, pubkey: Pubkey) -> Result<Option<AccountInfoResult>> {
+    let response = try_post_data(url, READ_ACCOUNT_INFO, pubkey)?;
+    let value = from_str::<Value>(&response)
+        .map_err(|e| anyhow!("read_account_info response is not JSON: {}", e))?;
+    if value["error"]["code"].as_i64() == Some(ACCOUNT_NOT_FOUND_CODE) {
+        return Ok(None);
+    }
+
+    let result = process_result(response)?;
+    serde_json::from_value(result)
+        .map(Some)
+        .map_err(|_| anyhow!("Unable to decode read_account_info result"))
+}
 /*
 pub async fn get_program_accounts(
     context: Arc<ValidatorContext>,
@@ -393,13 +373,16 @@ pub async fn get_program_accounts(
 }
 */
 
-/// Returns a program given the program ID
-pub fn get_program(url: &str, program_id: String) -> String {
-    process_result(post_data(url, GET_PROGRAM, program_id))
-        .expect("get_program should not fail")
-        .as_str()
-        .expect("cannot convert result to string")
-        .to_string()
+/// Returns the program at `program_id` along with who is allowed to upgrade it
+pub fn get_program(url: &str, program_id: Pubkey) -> Result<ProgramInfo> {
+    let account = read_account_info(url, program_id)?;
+    if !account.is_executable {
+        return Err(anyhow!(
+            "Account {:x} is not an executable program",
+            program_id
+        ));
+    }
+    ProgramInfo::from_account(program_id, &account)
 }
 
 /// Returns the best block
@@ -554,22 +537,25 @@ pub fn prepare_fees() -> String {
 }
 
 pub fn send_utxo(pubkey: Pubkey) -> (String, u32) {
+    try_send_utxo(NODE1_ADDRESS, pubkey).expect("SATs should be sent to address")
+}
+
+/// Same as [send_utxo], but asks the node at `url` for the account address
+/// and returns an error instead of panicking
+pub fn try_send_utxo(url: &str, pubkey: Pubkey) -> Result<(String, u32)> {
     let userpass = Auth::UserPass(
         BITCOIN_NODE_USERNAME.to_string(),
         BITCOIN_NODE_PASSWORD.to_string(),
     );
-    let rpc =
-        Client::new(BITCOIN_NODE_ENDPOINT, userpass).expect("rpc shouldn not fail to be initiated");
-
-    let _caller = CallerInfo::with_secret_key_file(CALLER_FILE_PATH)
-        .expect("getting caller info should not fail");
+    let rpc = Client::new(BITCOIN_NODE_ENDPOINT, userpass)
+        .map_err(|e| anyhow!("Unable to connect to the bitcoin node: {:?}", e))?;
 
-    let address = get_account_address(pubkey);
+    let address = try_get_account_address(url, pubkey)?;
 
     let account_address = Address::from_str(&address)
-        .unwrap()
+        .map_err(|e| anyhow!("Invalid account address {}: {}", address, e))?
         .require_network(BITCOIN_NETWORK)
-        .unwrap();
+        .map_err(|e| anyhow!("Invalid account address {}: {}", address, e))?;
 
     let txid = rpc
         .send_to_address(
@@ -582,11 +568,11 @@ pub fn send_utxo(pubkey: Pubkey) -> (String, u32) {
             None,
             None,
         )
-        .expect("SATs should be sent to address");
+        .map_err(|e| anyhow!("Unable to send SATs to {}: {:?}", address, e))?;
 
     let sent_tx = rpc
         .get_raw_transaction(&txid, None)
-        .expect("should get raw transaction");
+        .map_err(|e| anyhow!("Unable to get transaction {}: {:?}", txid, e))?;
     let mut vout = 0;
 
     for (index, output) in sent_tx.output.iter().enumerate() {
@@ -595,7 +581,7 @@ pub fn send_utxo(pubkey: Pubkey) -> (String, u32) {
         }
     }
 
-    (txid.to_string(), vout)
+    Ok((txid.to_string(), vout))
 }
 
 pub fn send_utxo_2(pubkey: Pubkey) -> (Txid, u32) {
@@ -644,6 +630,13 @@ pub fn send_utxo_2(pubkey: Pubkey) -> (Txid, u32) {
     (txid, vout)
 }
 
+fn try_get_account_address(url: &str, pubkey: Pubkey) -> Result<String> {
+    process_result(try_post_data(url, GET_ACCOUNT_ADDRESS, pubkey.serialize())?)?
+        .as_str()
+        .map(|address| address.to_string())
+        .ok_or_else(|| anyhow!("get_account_address did not return a string"))
+}
+
 fn get_account_address(pubkey: Pubkey) -> String {
     process_result(post_data(
         NODE1_ADDRESS,
@@ -0,0 +1,394 @@
+//! Typed Rust clients generated from a program IDL
+//!
+//! [generate_client] turns the [Idl] emitted by a program crate into the
+//! source of a module holding the program's types, one builder per
+//! instruction, a decoder per account layout and an enum of its custom errors.
+
+use std::fmt::Write;
+use std::fs;
+
+use anyhow::{anyhow, Result};
+
+use crate::arch_program::idl::{Idl, IdlFields, IdlType, IdlTypeDef, IdlTypeDefKind};
+
+/// Reads an IDL from a JSON file
+pub fn load_idl(path: &str) -> Result<Idl> {
+    let json = fs::read_to_string(path).map_err(|e| anyhow!("Unable to read {}: {}", path, e))?;
+    serde_json::from_str(&json).map_err(|e| anyhow!("Invalid IDL in {}: {}", path, e))
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "yield",
+];
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut previous: Option<char> = None;
+
+    for ch in name.chars() {
+        if ch.is_uppercase() {
+            if previous.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit()) {
+                snake.push('_');
+            }
+            snake.extend(ch.to_lowercase());
+        } else if ch == '-' || ch == ' ' {
+            snake.push('_');
+        } else {
+            snake.push(ch);
+        }
+        previous = Some(ch);
+    }
+
+    snake
+}
+
+fn to_pascal_case(name: &str) -> String {
+    name.split(['_', '-', ' '])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap();
+            first.to_uppercase().chain(chars).collect::<String>()
+        })
+        .collect()
+}
+
+/// Snake case identifier, escaped if it is a keyword
+fn field_ident(name: &str) -> String {
+    let ident = to_snake_case(name);
+    if KEYWORDS.contains(&ident.as_str()) {
+        format!("r#{}", ident)
+    } else {
+        ident
+    }
+}
+
+fn rust_type(ty: &IdlType) -> String {
+    match ty {
+        IdlType::Bool => "bool".to_string(),
+        IdlType::U8 => "u8".to_string(),
+        IdlType::U16 => "u16".to_string(),
+        IdlType::U32 => "u32".to_string(),
+        IdlType::U64 => "u64".to_string(),
+        IdlType::U128 => "u128".to_string(),
+        IdlType::I8 => "i8".to_string(),
+        IdlType::I16 => "i16".to_string(),
+        IdlType::I32 => "i32".to_string(),
+        IdlType::I64 => "i64".to_string(),
+        IdlType::I128 => "i128".to_string(),
+        IdlType::String => "String".to_string(),
+        IdlType::Pubkey => "arch_program::pubkey::Pubkey".to_string(),
+        IdlType::UtxoMeta => "arch_program::utxo::UtxoMeta".to_string(),
+        IdlType::Option(inner) => format!("Option<{}>", rust_type(inner)),
+        IdlType::Vec(inner) => format!("Vec<{}>", rust_type(inner)),
+        IdlType::Array(inner, len) => format!("[{}; {}]", rust_type(inner), len),
+        IdlType::Tuple(types) => match types.as_slice() {
+            [single] => format!("({},)", rust_type(single)),
+            types => format!(
+                "({})",
+                types.iter().map(rust_type).collect::<Vec<_>>().join(", ")
+            ),
+        },
+        IdlType::Map(key, value) => format!(
+            "std::collections::BTreeMap<{}, {}>",
+            rust_type(key),
+            rust_type(value)
+        ),
+        IdlType::Defined(name) => to_pascal_case(name),
+    }
+}
+
+/// Fields of a struct or enum variant, `prefix` being `pub ` for structs
+fn write_fields(out: &mut String, fields: &IdlFields, indent: &str, prefix: &str) {
+    match fields {
+        IdlFields::Unit => {}
+        IdlFields::Named(fields) => {
+            out.push_str(" {\n");
+            for field in fields {
+                let _ = writeln!(
+                    out,
+                    "{}    {}{}: {},",
+                    indent,
+                    prefix,
+                    field_ident(&field.name),
+                    rust_type(&field.ty)
+                );
+            }
+            let _ = write!(out, "{}}}", indent);
+        }
+        IdlFields::Tuple(types) => {
+            let types = types
+                .iter()
+                .map(|ty| format!("{}{}", prefix, rust_type(ty)))
+                .collect::<Vec<_>>();
+            let _ = write!(out, "({})", types.join(", "));
+        }
+    }
+}
+
+fn write_type_def(out: &mut String, def: &IdlTypeDef) {
+    out.push_str(
+        "#[derive(Debug, Clone, PartialEq, borsh::BorshSerialize, borsh::BorshDeserialize)]\n",
+    );
+    let name = to_pascal_case(&def.name);
+
+    match &def.kind {
+        IdlTypeDefKind::Struct(fields) => {
+            let _ = write!(out, "pub struct {}", name);
+            write_fields(out, fields, "", "pub ");
+            if !matches!(fields, IdlFields::Named(_)) {
+                out.push(';');
+            }
+            out.push('\n');
+        }
+        IdlTypeDefKind::Enum(variants) => {
+            let _ = writeln!(out, "pub enum {} {{", name);
+            for variant in variants {
+                let _ = write!(out, "    {}", to_pascal_case(&variant.name));
+                write_fields(out, &variant.fields, "    ", "");
+                out.push_str(",\n");
+            }
+            out.push_str("}\n");
+        }
+    }
+}
+
+fn byte_list(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| byte.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Generates the source of a Rust module to interact with the program described by `idl`
+pub fn generate_client(idl: &Idl) -> String {
+    let mut out = String::new();
+
+    let _ = writeln!(
+        out,
+        "//! Client for the `{}` program (version {}), generated from its IDL.\n//! Do not edit by hand.",
+        idl.name, idl.version
+    );
+
+    for def in &idl.types {
+        out.push('\n');
+        write_type_def(&mut out, def);
+    }
+
+    for instruction in &idl.instructions {
+        let account_names = instruction
+            .accounts
+            .iter()
+            .map(|account| field_ident(&account.name))
+            .collect::<Vec<_>>();
+        let arg_names = instruction
+            .args
+            .iter()
+            .map(|arg| {
+                let name = field_ident(&arg.name);
+                if account_names.contains(&name) || name == "program_id" {
+                    format!("{}_arg", name.trim_start_matches("r#"))
+                } else {
+                    name
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let _ = writeln!(out, "\n/// Builds a `{}` instruction", instruction.name);
+        if !instruction.accounts.is_empty() {
+            out.push_str("///\n/// Accounts:\n");
+            for (index, account) in instruction.accounts.iter().enumerate() {
+                let mut flags = vec![];
+                if account.is_signer {
+                    flags.push("signer");
+                }
+                if account.is_writable {
+                    flags.push("writable");
+                }
+                let _ = writeln!(
+                    out,
+                    "/// {}. `[{}]` {}",
+                    index,
+                    flags.join(", "),
+                    account.name
+                );
+            }
+        }
+        out.push_str("#[allow(clippy::too_many_arguments)]\n");
+        let _ = write!(
+            out,
+            "pub fn {}(\n    program_id: arch_program::pubkey::Pubkey,\n",
+            field_ident(&instruction.name)
+        );
+        for name in &account_names {
+            let _ = writeln!(out, "    {}: arch_program::pubkey::Pubkey,", name);
+        }
+        for (name, arg) in arg_names.iter().zip(&instruction.args) {
+            let _ = writeln!(out, "    {}: &{},", name, rust_type(&arg.ty));
+        }
+        out.push_str(") -> arch_program::instruction::Instruction {\n");
+
+        let mutable = if arg_names.is_empty() { "" } else { "mut " };
+        let _ = writeln!(
+            out,
+            "    let {}data: Vec<u8> = vec![{}];",
+            mutable,
+            byte_list(&instruction.discriminator)
+        );
+        for name in &arg_names {
+            let _ = writeln!(
+                out,
+                "    borsh::BorshSerialize::serialize({}, &mut data)\n        .expect(\"serializing into a Vec should not fail\");",
+                name
+            );
+        }
+
+        out.push_str("\n    arch_program::instruction::Instruction {\n        program_id,\n");
+        if account_names.is_empty() {
+            out.push_str("        accounts: vec![],\n        data,\n    }\n}\n");
+            continue;
+        }
+        out.push_str("        accounts: vec![\n");
+        for (name, account) in account_names.iter().zip(&instruction.accounts) {
+            let _ = writeln!(
+                out,
+                "            arch_program::account::AccountMeta {{\n                pubkey: {},\n                is_signer: {},\n                is_writable: {},\n            }},",
+                name, account.is_signer, account.is_writable
+            );
+        }
+        out.push_str("        ],\n        data,\n    }\n}\n");
+    }
+
+    for account in &idl.accounts {
+        let ty = rust_type(&account.ty);
+        let _ = writeln!(
+            out,
+            "\n/// Decodes the data of a `{}` account\npub fn decode_{}(data: &[u8]) -> Result<{}, arch_program::program_error::ProgramError> {{",
+            account.name,
+            to_snake_case(&account.name),
+            ty
+        );
+        if account.discriminator.is_empty() {
+            out.push_str("    let mut data = data;\n");
+        } else {
+            let _ = writeln!(
+                out,
+                "    let mut data = data\n        .strip_prefix(&[{}][..])\n        .ok_or(arch_program::program_error::ProgramError::InvalidAccountData)?;",
+                byte_list(&account.discriminator)
+            );
+        }
+        out.push_str("    borsh::BorshDeserialize::deserialize(&mut data)\n        .map_err(|_| arch_program::program_error::ProgramError::InvalidAccountData)\n}\n");
+    }
+
+    if !idl.errors.is_empty() {
+        let name = format!("{}Error", to_pascal_case(&idl.name));
+
+        let _ = writeln!(
+            out,
+            "\n/// Custom errors returned by the program as `ProgramError::Custom(code)`\n#[derive(Debug, Clone, Copy, PartialEq, Eq)]\n#[repr(u32)]\npub enum {} {{",
+            name
+        );
+        for error in &idl.errors {
+            let _ = writeln!(out, "    {} = {},", to_pascal_case(&error.name), error.code);
+        }
+        let _ = writeln!(out, "}}\n\nimpl {} {{", name);
+
+        out.push_str("    pub fn from_code(code: u32) -> Option<Self> {\n        match code {\n");
+        for error in &idl.errors {
+            let _ = writeln!(
+                out,
+                "            {} => Some(Self::{}),",
+                error.code,
+                to_pascal_case(&error.name)
+            );
+        }
+        out.push_str("            _ => None,\n        }\n    }\n\n");
+
+        out.push_str("    pub fn code(&self) -> u32 {\n        *self as u32\n    }\n\n");
+
+        out.push_str("    pub fn message(&self) -> &'static str {\n        match self {\n");
+        for error in &idl.errors {
+            let _ = writeln!(
+                out,
+                "            Self::{} => {:?},",
+                to_pascal_case(&error.name),
+                error.msg.as_deref().unwrap_or(&error.name)
+            );
+        }
+        out.push_str("        }\n    }\n}\n");
+
+        let _ = writeln!(
+            out,
+            "\nimpl std::fmt::Display for {0} {{\n    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {{\n        write!(f, \"{{}}\", self.message())\n    }}\n}}\n\nimpl From<{0}> for arch_program::program_error::ProgramError {{\n    fn from(error: {0}) -> Self {{\n        arch_program::program_error::ProgramError::Custom(error.code())\n    }}\n}}",
+            name
+        );
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_client, to_pascal_case, to_snake_case};
+    use crate::arch_program::idl::{Idl, IdlBuild, IdlInstruction};
+    use crate::arch_program::pubkey::Pubkey;
+
+    #[derive(IdlBuild)]
+    #[allow(dead_code)]
+    struct TransferInput {
+        amount: u64,
+        r#type: Option<u8>,
+    }
+
+    #[derive(IdlBuild)]
+    #[allow(dead_code)]
+    enum MintStatus {
+        Ongoing,
+        Finished(u64),
+    }
+
+    #[derive(IdlBuild)]
+    #[allow(dead_code)]
+    struct TokenBalance {
+        owner: Pubkey,
+        status: MintStatus,
+    }
+
+    #[test]
+    fn test_case_conversion() {
+        assert_eq!(to_snake_case("InitializeMint"), "initialize_mint");
+        assert_eq!(to_snake_case("mint_v2"), "mint_v2");
+        assert_eq!(to_pascal_case("fungible-token"), "FungibleToken");
+        assert_eq!(to_pascal_case("initialize_mint"), "InitializeMint");
+    }
+
+    #[test]
+    fn test_generate_client() {
+        let idl = Idl::new("fungible-token", "0.1.0")
+            .instruction(
+                IdlInstruction::new("transfer", &[3])
+                    .account("owner", true, false)
+                    .account("mint", false, true)
+                    .arg::<TransferInput>("input"),
+            )
+            .account::<TokenBalance>("balance", &[7])
+            .error(501, "InsufficientBalance", Some("Not enough tokens"));
+
+        let client = generate_client(&idl);
+
+        assert!(client.contains(
+            "pub struct TransferInput {\n    pub amount: u64,\n    pub r#type: Option<u8>,\n}"
+        ));
+        assert!(client.contains("pub enum MintStatus {\n    Ongoing,\n    Finished(u64),\n}"));
+        assert!(client.contains("pub fn transfer(\n    program_id: arch_program::pubkey::Pubkey,\n    owner: arch_program::pubkey::Pubkey,\n    mint: arch_program::pubkey::Pubkey,\n    input: &TransferInput,\n)"));
+        assert!(client.contains("let mut data: Vec<u8> = vec![3];"));
+        assert!(client.contains("pub fn decode_balance(data: &[u8]) -> Result<TokenBalance, "));
+        assert!(client.contains(".strip_prefix(&[7][..])"));
+        assert!(client.contains("    InsufficientBalance = 501,"));
+        assert!(client.contains("Self::InsufficientBalance => \"Not enough tokens\","));
+    }
+}
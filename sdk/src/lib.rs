@@ -1,6 +1,16 @@
 pub use arch_program;
 
+pub mod constants;
+pub mod deploy;
+pub mod elf;
+pub mod events;
+pub mod helper;
+pub mod idl;
+pub mod loader;
+pub mod models;
 pub mod processed_transaction;
+pub mod program_errors;
+pub mod psbt;
 pub mod runtime_transaction;
 pub mod signature;
 pub mod transaction_to_sign;
@@ -0,0 +1,304 @@
+//! Helpers for the upgradeable program loader
+//!
+//! Deploying through the loader is a two step process: the ELF is first
+//! uploaded into a staging buffer with [upload_program_buffer], then made live
+//! with [deploy_program_from_buffer] (or [upgrade_program_from_buffer] for a
+//! program that is already deployed). Only the recorded upgrade authority can
+//! upgrade a program, transfer its authority or make it immutable.
+//!
+//! The loader is a client side proposal, see
+//! [`loader_instruction`](crate::arch_program::loader_instruction): nodes do
+//! not run it yet, so these helpers fail against them. [`ProgramInfo`] also
+//! reads programs deployed with system `ExtendBytes` instructions, which is
+//! how nodes deploy today.
+
+use anyhow::{anyhow, Result};
+use bitcoin::key::UntweakedKeypair;
+use bitcoin::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+
+use crate::arch_program::loader_instruction::{LoaderInstruction, LoaderState};
+use crate::arch_program::pubkey::Pubkey;
+use crate::deploy::{upload_chunks, DeployChunk, DeployOptions, DeploymentReport};
+use crate::helper::{
+    build_signed_transaction, read_account_info, send_and_confirm, AccountInfoResult,
+};
+use crate::runtime_transaction::RuntimeTransaction;
+
+/// Who is allowed to replace the code of a program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProgramAuthority {
+    /// Deployed with system `ExtendBytes` instructions: anyone holding the
+    /// program key can overwrite it
+    ProgramKey,
+    /// Deployed through the loader and upgradeable by this key
+    Authority(Pubkey),
+    /// Deployed through the loader and no longer upgradeable
+    Immutable,
+}
+
+/// A deployed program, as returned by [crate::helper::get_program]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgramInfo {
+    pub program_id: Pubkey,
+    pub owner: Pubkey,
+    pub is_executable: bool,
+    pub authority: ProgramAuthority,
+    pub elf_len: usize,
+}
+
+impl ProgramInfo {
+    pub fn from_account(program_id: Pubkey, account: &AccountInfoResult) -> Result<Self> {
+        let (authority, elf_len) = if account.owner.is_loader_program() {
+            let state = LoaderState::from_slice(&account.data)
+                .map_err(|e| anyhow!("Invalid loader account {:x}: {}", program_id, e))?;
+
+            let authority = match state {
+                LoaderState::Program {
+                    authority: Some(authority),
+                } => ProgramAuthority::Authority(authority),
+                LoaderState::Program { authority: None } => ProgramAuthority::Immutable,
+                _ => return Err(anyhow!("Account {:x} is not a program", program_id)),
+            };
+            (authority, account.data.len() - LoaderState::LEN)
+        } else {
+            (ProgramAuthority::ProgramKey, account.data.len())
+        };
+
+        Ok(Self {
+            program_id,
+            owner: account.owner,
+            is_executable: account.is_executable,
+            authority,
+            elf_len,
+        })
+    }
+}
+
+fn pubkey_of(keypair: &UntweakedKeypair) -> Pubkey {
+    Pubkey::from_slice(&XOnlyPublicKey::from_keypair(keypair).0.serialize())
+}
+
+fn loader_state(url: &str, pubkey: Pubkey) -> Result<LoaderState> {
+    let account = read_account_info(url, pubkey)?;
+    LoaderState::from_slice(&account.data)
+        .map_err(|e| anyhow!("Invalid loader account {:x}: {}", pubkey, e))
+}
+
+/// Initializes `buffer_keypair`'s account as a staging buffer for
+/// `authority_keypair` if needed, then uploads `elf` into it.
+///
+/// Like [crate::deploy::deploy_program], chunks already present in the
+/// buffer are skipped, so the upload can be resumed.
+pub fn upload_program_buffer(
+    url: &str,
+    buffer_keypair: UntweakedKeypair,
+    authority_keypair: UntweakedKeypair,
+    elf: &[u8],
+    options: DeployOptions,
+) -> Result<DeploymentReport> {
+    let buffer_pubkey = pubkey_of(&buffer_keypair);
+    let authority_pubkey = pubkey_of(&authority_keypair);
+
+    match loader_state(url, buffer_pubkey)? {
+        LoaderState::Uninitialized => {
+            send_and_confirm(
+                url,
+                vec![LoaderInstruction::new_initialize_buffer_instruction(
+                    buffer_pubkey,
+                    authority_pubkey,
+                )],
+                &[buffer_keypair, authority_keypair],
+            )?;
+        }
+        LoaderState::Buffer { authority } if authority == authority_pubkey => {}
+        LoaderState::Buffer { authority } => {
+            return Err(anyhow!(
+                "Buffer {:x} belongs to authority {:x}",
+                buffer_pubkey,
+                authority
+            ))
+        }
+        LoaderState::Program { .. } => {
+            return Err(anyhow!(
+                "Account {:x} is a program, not a buffer",
+                buffer_pubkey
+            ))
+        }
+    }
+
+    upload_chunks(
+        url,
+        buffer_pubkey,
+        LoaderState::LEN,
+        elf,
+        options,
+        |chunk| write_transaction(buffer_pubkey, &authority_keypair, chunk),
+    )
+}
+
+fn write_transaction(
+    buffer: Pubkey,
+    authority_keypair: &UntweakedKeypair,
+    chunk: &DeployChunk,
+) -> RuntimeTransaction {
+    build_signed_transaction(
+        vec![LoaderInstruction::new_write_instruction(
+            buffer,
+            pubkey_of(authority_keypair),
+            chunk.offset as u32,
+            chunk.bytes.to_vec(),
+        )],
+        &[*authority_keypair],
+    )
+}
+
+/// Makes the content of `buffer` live as a new program, with the buffer's
+/// authority recorded as the program's upgrade authority
+pub fn deploy_program_from_buffer(
+    url: &str,
+    program_keypair: UntweakedKeypair,
+    buffer: Pubkey,
+    authority_keypair: UntweakedKeypair,
+) -> Result<String> {
+    send_and_confirm(
+        url,
+        vec![LoaderInstruction::new_deploy_instruction(
+            pubkey_of(&program_keypair),
+            buffer,
+            pubkey_of(&authority_keypair),
+        )],
+        &[program_keypair, authority_keypair],
+    )
+}
+
+/// Replaces the code of `program` with the content of `buffer`
+pub fn upgrade_program_from_buffer(
+    url: &str,
+    program: Pubkey,
+    buffer: Pubkey,
+    authority_keypair: UntweakedKeypair,
+) -> Result<String> {
+    send_and_confirm(
+        url,
+        vec![LoaderInstruction::new_upgrade_instruction(
+            program,
+            buffer,
+            pubkey_of(&authority_keypair),
+        )],
+        &[authority_keypair],
+    )
+}
+
+/// Transfers the authority of a program or buffer to `new_authority`
+pub fn set_authority(
+    url: &str,
+    account: Pubkey,
+    authority_keypair: UntweakedKeypair,
+    new_authority: Pubkey,
+) -> Result<String> {
+    send_and_confirm(
+        url,
+        vec![LoaderInstruction::new_set_authority_instruction(
+            account,
+            pubkey_of(&authority_keypair),
+            new_authority,
+        )],
+        &[authority_keypair],
+    )
+}
+
+/// Removes the upgrade authority of `program`. This cannot be undone.
+pub fn make_program_immutable(
+    url: &str,
+    program: Pubkey,
+    authority_keypair: UntweakedKeypair,
+) -> Result<String> {
+    send_and_confirm(
+        url,
+        vec![LoaderInstruction::new_make_immutable_instruction(
+            program,
+            pubkey_of(&authority_keypair),
+        )],
+        &[authority_keypair],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_transaction, ProgramAuthority, ProgramInfo};
+    use crate::arch_program::loader_instruction::LoaderState;
+    use crate::arch_program::pubkey::Pubkey;
+    use crate::deploy::{max_chunk_len, plan_chunks, DeployChunk};
+    use crate::helper::{extend_bytes_max_len, AccountInfoResult};
+    use crate::runtime_transaction::RUNTIME_TX_SIZE_LIMIT;
+    use bitcoin::key::UntweakedKeypair;
+    use bitcoin::secp256k1::Secp256k1;
+
+    fn account(owner: Pubkey, data: Vec<u8>) -> AccountInfoResult {
+        AccountInfoResult {
+            owner,
+            data,
+            utxo: String::new(),
+            is_executable: true,
+            tag: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_program_info_authority() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let elf = vec![0x7f, b'E', b'L', b'F'];
+
+        let legacy = account(Pubkey::system_program(), elf.clone());
+        let info = ProgramInfo::from_account(program_id, &legacy).unwrap();
+        assert_eq!(info.authority, ProgramAuthority::ProgramKey);
+        assert_eq!(info.elf_len, 4);
+
+        let mut data = LoaderState::Program {
+            authority: Some(authority),
+        }
+        .serialize()
+        .to_vec();
+        data.extend(&elf);
+        let upgradeable = account(Pubkey::loader_program(), data);
+        let info = ProgramInfo::from_account(program_id, &upgradeable).unwrap();
+        assert_eq!(info.authority, ProgramAuthority::Authority(authority));
+        assert_eq!(info.elf_len, 4);
+
+        let mut data = LoaderState::Program { authority: None }
+            .serialize()
+            .to_vec();
+        data.extend(&elf);
+        let immutable = account(Pubkey::loader_program(), data);
+        let info = ProgramInfo::from_account(program_id, &immutable).unwrap();
+        assert_eq!(info.authority, ProgramAuthority::Immutable);
+
+        let buffer = account(
+            Pubkey::loader_program(),
+            LoaderState::Buffer { authority }.serialize().to_vec(),
+        );
+        assert!(ProgramInfo::from_account(program_id, &buffer).is_err());
+    }
+
+    #[test]
+    fn test_max_size_write_transaction_fits() {
+        let keypair = UntweakedKeypair::from_seckey_slice(&Secp256k1::new(), &[7; 32]).unwrap();
+        let buffer = Pubkey([1; 32]);
+        let build_transaction = |chunk: &DeployChunk| write_transaction(buffer, &keypair, chunk);
+
+        // two accounts instead of one, but a shorter instruction header
+        let chunk_len = max_chunk_len(&build_transaction);
+        assert_ne!(chunk_len, extend_bytes_max_len());
+
+        let elf = vec![7u8; chunk_len + 1];
+        let chunks = plan_chunks(&elf, chunk_len);
+        let tx = build_transaction(&chunks[0]);
+        assert_eq!(tx.serialize().len(), RUNTIME_TX_SIZE_LIMIT);
+        assert!(tx.check_tx_size_limit().is_ok());
+
+        let chunks = plan_chunks(&elf, chunk_len + 1);
+        assert!(build_transaction(&chunks[0]).check_tx_size_limit().is_err());
+    }
+}
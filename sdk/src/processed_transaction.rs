@@ -3,6 +3,7 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::arch_program::pubkey::Pubkey;
 use crate::runtime_transaction::RuntimeTransaction;
 
 #[derive(Clone, Debug, Deserialize, Serialize, BorshDeserialize, BorshSerialize, PartialEq)]
@@ -34,6 +35,21 @@ pub struct ProcessedTransaction {
     pub status: Status,
     pub bitcoin_txid: Option<String>,
     pub accounts_tags: Vec<String>,
+    /// Logs of the programs run by the transaction, empty when the node does
+    /// not report them
+    #[serde(default)]
+    pub logs: Vec<String>,
+    /// Return data left by the transaction's programs, `None` when they set
+    /// none or the node does not report it
+    #[serde(default)]
+    pub return_data: Option<ReturnData>,
+}
+
+/// Return data and the program that set it last
+#[derive(Clone, Debug, Deserialize, Serialize, BorshDeserialize, BorshSerialize, PartialEq)]
+pub struct ReturnData {
+    pub program_id: Pubkey,
+    pub data: Vec<u8>,
 }
 
 impl ProcessedTransaction {
@@ -71,6 +87,23 @@ impl ProcessedTransaction {
                 result
             }
         });
+
+        serialized.extend((self.logs.len() as u64).to_le_bytes());
+        for log in &self.logs {
+            serialized.extend((log.len() as u64).to_le_bytes());
+            serialized.extend(log.as_bytes());
+        }
+
+        serialized.extend(match &self.return_data {
+            Some(return_data) => {
+                let mut res = vec![1];
+                res.extend(return_data.program_id.serialize());
+                res.extend((return_data.data.len() as u64).to_le_bytes());
+                res.extend(&return_data.data);
+                res
+            }
+            None => vec![0],
+        });
         Ok(serialized)
     }
 
@@ -102,23 +135,62 @@ impl ProcessedTransaction {
         }
 
         let status = match data[size] {
-            0 => Status::Processing,
-            1 => Status::Processed,
+            0 => {
+                size += 1;
+                Status::Processing
+            }
+            1 => {
+                size += 1;
+                Status::Processed
+            }
             2 => {
                 let data_bytes = data[(size + 1)..(size + 9)].try_into()?;
                 let error_len = u64::from_le_bytes(data_bytes) as usize;
                 size += 9;
                 let error = String::from_utf8(data[size..(size + error_len)].to_vec())?;
+                size += error_len;
                 Status::Failed(error)
             }
             _ => unreachable!("status doesn't exist"),
         };
 
+        // transactions encoded before logs were added end with the status
+        let mut logs = vec![];
+        if size < data.len() {
+            let data_bytes = data[size..(size + 8)].try_into()?;
+            let logs_len = u64::from_le_bytes(data_bytes) as usize;
+            size += 8;
+            for _ in 0..logs_len {
+                let data_bytes = data[size..(size + 8)].try_into()?;
+                let log_len = u64::from_le_bytes(data_bytes) as usize;
+                size += 8;
+                logs.push(String::from_utf8(data[size..(size + log_len)].to_vec())?);
+                size += log_len;
+            }
+        }
+
+        // and before return data was added with the logs
+        let mut return_data = None;
+        if size < data.len() && data[size] == 1 {
+            size += 1;
+            let program_id = Pubkey::from_slice(&data[size..(size + 32)]);
+            size += 32;
+            let data_bytes = data[size..(size + 8)].try_into()?;
+            let data_len = u64::from_le_bytes(data_bytes) as usize;
+            size += 8;
+            return_data = Some(ReturnData {
+                program_id,
+                data: data[size..(size + data_len)].to_vec(),
+            });
+        }
+
         Ok(ProcessedTransaction {
             runtime_transaction,
             status,
             bitcoin_txid,
             accounts_tags,
+            logs,
+            return_data,
         })
     }
 }
@@ -126,6 +198,7 @@ impl ProcessedTransaction {
 #[cfg(test)]
 mod tests {
     use crate::processed_transaction::ProcessedTransaction;
+    use crate::processed_transaction::ReturnData;
     use crate::processed_transaction::Status;
     use crate::runtime_transaction::RuntimeTransaction;
     use crate::signature::Signature;
@@ -143,7 +216,9 @@ mod tests {
             signers in prop::collection::vec(any::<[u8; 32]>(), 0..10),
             instructions in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..100), 0..10),
             bitcoin_txid in "[0-9a-f]{64}",
-            accounts_tags in prop::collection::vec("[0-9a-f]{64}", 0..10)
+            accounts_tags in prop::collection::vec("[0-9a-f]{64}", 0..10),
+            logs in prop::collection::vec(".*", 0..10),
+            return_data in prop::option::of((any::<[u8; 32]>(), prop::collection::vec(any::<u8>(), 0..1024)))
         ) {
             // Generate a random RuntimeTransaction
             let signatures: Vec<Signature> = signatures.into_iter()
@@ -178,6 +253,11 @@ mod tests {
                 status: Status::Processing,
                 bitcoin_txid: Some(bitcoin_txid.to_string()),
                 accounts_tags: accounts_tags.iter().map(|s| s.to_string()).collect(),
+                logs,
+                return_data: return_data.map(|(program_id, data)| ReturnData {
+                    program_id: Pubkey::from(program_id),
+                    data,
+                }),
             };
 
             let serialized = processed_transaction.to_vec().unwrap();
@@ -185,6 +265,20 @@ mod tests {
 
             let reserialized = deserialized.to_vec().unwrap();
             assert_eq!(serialized, reserialized);
+
+            // encodings from before return data was added stop after the
+            // logs, and from before logs were added after the status
+            if processed_transaction.return_data.is_none() {
+                let legacy = &serialized[..serialized.len() - 1];
+                let deserialized = ProcessedTransaction::from_vec(legacy).unwrap();
+                assert_eq!(deserialized.to_vec().unwrap(), serialized);
+
+                if processed_transaction.logs.is_empty() {
+                    let legacy = &serialized[..serialized.len() - 9];
+                    let deserialized = ProcessedTransaction::from_vec(legacy).unwrap();
+                    assert_eq!(deserialized.to_vec().unwrap(), serialized);
+                }
+            }
         }
     }
 }
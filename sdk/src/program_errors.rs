@@ -0,0 +1,244 @@
+//! Decoding of the custom errors returned by programs.
+//!
+//! A failed transaction only carries the `ProgramError::Custom` code, whose
+//! meaning depends on the program. The [`ErrorRegistry`] maps the codes of
+//! known programs back to their error, registered either from the program's
+//! `#[derive(CustomError)]` enum or from its IDL.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::arch_program::idl::Idl;
+use crate::arch_program::program_error::CustomError;
+use crate::arch_program::pubkey::Pubkey;
+use crate::processed_transaction::{ProcessedTransaction, Status};
+
+/// A custom error of a program
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ProgramErrorInfo {
+    pub code: u32,
+    pub name: String,
+    pub message: String,
+}
+
+impl fmt::Display for ProgramErrorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.name, self.code, self.message)
+    }
+}
+
+/// Custom errors of known programs, by program id
+#[derive(Clone, Debug, Default)]
+pub struct ErrorRegistry {
+    programs: HashMap<Pubkey, HashMap<u32, ProgramErrorInfo>>,
+}
+
+impl ErrorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the errors of a `#[derive(CustomError)]` enum for `program_id`
+    pub fn register<E: CustomError>(&mut self, program_id: Pubkey) -> &mut Self {
+        let errors = self.programs.entry(program_id).or_default();
+        for (code, name, message) in E::ERRORS {
+            errors.insert(
+                *code,
+                ProgramErrorInfo {
+                    code: *code,
+                    name: name.to_string(),
+                    message: message.to_string(),
+                },
+            );
+        }
+        self
+    }
+
+    /// Registers the errors listed in the IDL of `program_id`
+    pub fn register_idl(&mut self, program_id: Pubkey, idl: &Idl) -> &mut Self {
+        let errors = self.programs.entry(program_id).or_default();
+        for error in &idl.errors {
+            errors.insert(
+                error.code,
+                ProgramErrorInfo {
+                    code: error.code,
+                    name: error.name.clone(),
+                    message: error.msg.clone().unwrap_or_else(|| error.name.clone()),
+                },
+            );
+        }
+        self
+    }
+
+    pub fn lookup(&self, program_id: &Pubkey, code: u32) -> Option<&ProgramErrorInfo> {
+        self.programs.get(program_id)?.get(&code)
+    }
+
+    /// The error a failed transaction returned, looked up in the registered
+    /// programs it invokes, in instruction order
+    pub fn decode_transaction(
+        &self,
+        transaction: &ProcessedTransaction,
+    ) -> Option<(Pubkey, &ProgramErrorInfo)> {
+        let code = custom_error_code(&transaction.status)?;
+        transaction
+            .runtime_transaction
+            .message
+            .instructions
+            .iter()
+            .find_map(|instruction| {
+                self.lookup(&instruction.program_id, code)
+                    .map(|error| (instruction.program_id, error))
+            })
+    }
+}
+
+/// Custom code of a failed transaction
+///
+/// Accepts both the `Display` form of `ProgramError::Custom`,
+/// `custom program error: 0x1f4`, and its `Debug` form, `Custom(500)`.
+pub fn custom_error_code(status: &Status) -> Option<u32> {
+    let Status::Failed(message) = status else {
+        return None;
+    };
+    let lowercase = message.to_lowercase();
+
+    const DISPLAY: &str = "custom program error: 0x";
+    if let Some(start) = lowercase.find(DISPLAY) {
+        let hex: String = lowercase[start + DISPLAY.len()..]
+            .chars()
+            .take_while(char::is_ascii_hexdigit)
+            .collect();
+        return u32::from_str_radix(&hex, 16).ok();
+    }
+
+    const DEBUG: &str = "custom(";
+    let start = lowercase.find(DEBUG)?;
+    let digits: String = lowercase[start + DEBUG.len()..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+/// Typed custom error of a failed transaction, for callers that know which
+/// program failed
+pub fn decode_custom_error<E: CustomError>(status: &Status) -> Option<E> {
+    E::from_code(custom_error_code(status)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch_program::instruction::Instruction;
+    use crate::arch_program::message::Message;
+    use crate::arch_program::program_error::ProgramError;
+    use crate::runtime_transaction::RuntimeTransaction;
+    use proptest::prelude::*;
+
+    #[derive(Debug, PartialEq, CustomError)]
+    enum TokenError {
+        #[msg("The mint has reached its supply")]
+        MintFinished = 600,
+        #[msg("Not enough tokens")]
+        InsufficientBalance,
+    }
+
+    fn failed(message: &str, program_ids: &[Pubkey]) -> ProcessedTransaction {
+        ProcessedTransaction {
+            runtime_transaction: RuntimeTransaction {
+                version: 0,
+                signatures: vec![],
+                message: Message {
+                    signers: vec![],
+                    instructions: program_ids
+                        .iter()
+                        .map(|program_id| Instruction {
+                            program_id: *program_id,
+                            accounts: vec![],
+                            data: vec![],
+                        })
+                        .collect(),
+                },
+            },
+            status: Status::Failed(message.to_string()),
+            bitcoin_txid: None,
+            accounts_tags: vec![],
+            logs: vec![],
+            return_data: None,
+        }
+    }
+
+    #[test]
+    fn test_custom_error_code() {
+        let display = ProgramError::from(TokenError::InsufficientBalance).to_string();
+        let debug = format!("{:?}", ProgramError::from(TokenError::MintFinished));
+        for (message, code) in [
+            (display.as_str(), Some(601)),
+            (debug.as_str(), Some(600)),
+            (
+                "Error processing Instruction 1: custom program error: 0x258",
+                Some(600),
+            ),
+            ("InstructionError(0, Custom(7))", Some(7)),
+            ("An account's data contents was invalid", None),
+        ] {
+            assert_eq!(
+                custom_error_code(&Status::Failed(message.to_string())),
+                code
+            );
+        }
+        assert_eq!(custom_error_code(&Status::Processed), None);
+        assert_eq!(
+            decode_custom_error::<TokenError>(&Status::Failed(display)),
+            Some(TokenError::InsufficientBalance)
+        );
+    }
+
+    #[test]
+    fn test_registry() {
+        let token = Pubkey([1; 32]);
+        let other = Pubkey([2; 32]);
+        let idl = Idl::new("other", "0.1.0").error(600, "Frozen", None);
+        let mut registry = ErrorRegistry::new();
+        registry
+            .register::<TokenError>(token)
+            .register_idl(other, &idl);
+
+        assert_eq!(registry.lookup(&token, 600).unwrap().name, "MintFinished");
+        assert_eq!(registry.lookup(&other, 600).unwrap().message, "Frozen");
+        assert_eq!(registry.lookup(&token, 602), None);
+
+        let tx = failed("Custom(601)", &[other, token]);
+        let (program_id, error) = registry.decode_transaction(&tx).unwrap();
+        assert_eq!(program_id, token);
+        assert_eq!(
+            error.to_string(),
+            "InsufficientBalance (601): Not enough tokens"
+        );
+
+        let tx = failed("Custom(600)", &[other, token]);
+        assert_eq!(registry.decode_transaction(&tx).unwrap().0, other);
+        assert_eq!(
+            registry.decode_transaction(&failed("Custom(5)", &[token])),
+            None
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_custom_error_code(code in any::<u32>()) {
+            let display = ProgramError::Custom(code).to_string();
+            prop_assert_eq!(custom_error_code(&Status::Failed(display)), Some(code));
+            let debug = format!("{:?}", ProgramError::Custom(code));
+            prop_assert_eq!(custom_error_code(&Status::Failed(debug)), Some(code));
+        }
+
+        #[test]
+        fn fuzz_custom_error_code_never_panics(message in ".*") {
+            custom_error_code(&Status::Failed(message));
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! PSBT (BIP-174/370) export and import of transactions to sign.
+//!
+//! A program's transaction to sign often spends inputs the network does not
+//! sign, such as the fee input from `prepare_fees`. [`TransactionToSign::to_psbt`]
+//! hands the transaction to an external wallet or hardware signer with the
+//! outputs its inputs spend and their tap internal keys. The inputs the
+//! network signs carry their signer, tap leaf hash and annex in proprietary
+//! `arch` fields, their sighash type in the standard field, so that wallets
+//! leave them alone. [`merge_signed_psbt`] takes the wallet's signatures back
+//! and [`TransactionToSign::from_psbt`] reads the transaction to submit.
+
+use anyhow::{anyhow, Result};
+use arch_program::input_to_sign::InputToSign;
+use arch_program::pubkey::Pubkey;
+use bitcoin::hashes::Hash;
+use bitcoin::psbt::raw::ProprietaryKey;
+use bitcoin::psbt::{Input, Psbt, PsbtSighashType};
+use bitcoin::secp256k1::XOnlyPublicKey;
+use bitcoin::{TapLeafHash, TapSighashType, Transaction, TxOut, Witness};
+
+use crate::transaction_to_sign::TransactionToSign;
+
+/// Prefix of the proprietary PSBT fields
+pub const PROPRIETARY_PREFIX: &[u8] = b"arch";
+
+/// Proprietary input field holding the account the network signs for
+const NETWORK_SIGNER: u8 = 0;
+/// Proprietary input field holding the tap leaf hash the network signs
+const LEAF_HASH: u8 = 1;
+/// Proprietary input field holding the annex the network signature commits to
+const ANNEX: u8 = 2;
+
+/// The output an input spends
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prevout {
+    pub txout: TxOut,
+    /// Taproot internal key of the output, when known
+    pub internal_key: Option<XOnlyPublicKey>,
+}
+
+impl From<TxOut> for Prevout {
+    fn from(txout: TxOut) -> Self {
+        Self {
+            txout,
+            internal_key: None,
+        }
+    }
+}
+
+impl TransactionToSign {
+    /// The transaction as a PSBT, `prevouts[i]` being the output input `i`
+    /// spends
+    ///
+    /// Witnesses and script sigs already in the transaction become final
+    /// ones.
+    pub fn to_psbt(&self, prevouts: &[Prevout]) -> Result<Psbt> {
+        let mut tx: Transaction = bitcoin::consensus::deserialize(&self.tx_bytes)
+            .map_err(|e| anyhow!("Unable to decode transaction to sign: {}", e))?;
+        if prevouts.len() != tx.input.len() {
+            return Err(anyhow!(
+                "{} prevouts given for {} inputs",
+                prevouts.len(),
+                tx.input.len()
+            ));
+        }
+
+        let finals: Vec<_> = tx
+            .input
+            .iter_mut()
+            .map(|input| {
+                (
+                    std::mem::take(&mut input.witness),
+                    std::mem::take(&mut input.script_sig),
+                )
+            })
+            .collect();
+        let mut psbt = Psbt::from_unsigned_tx(tx).map_err(|e| anyhow!("Invalid PSBT: {}", e))?;
+
+        for ((input, prevout), (witness, script_sig)) in
+            psbt.inputs.iter_mut().zip(prevouts).zip(finals)
+        {
+            input.witness_utxo = Some(prevout.txout.clone());
+            input.tap_internal_key = prevout.internal_key;
+            input.final_script_witness = (!witness.is_empty()).then_some(witness);
+            input.final_script_sig = (!script_sig.is_empty()).then_some(script_sig);
+        }
+
+        for input_to_sign in self.inputs_to_sign.iter() {
+            let input = psbt
+                .inputs
+                .get_mut(input_to_sign.index as usize)
+                .ok_or_else(|| anyhow!("Input to sign {} out of range", input_to_sign.index))?;
+            input.proprietary.insert(
+                proprietary_key(NETWORK_SIGNER),
+                input_to_sign.signer.serialize().to_vec(),
+            );
+            if input_to_sign.sighash_type != TapSighashType::Default {
+                input.sighash_type = Some(PsbtSighashType::from(input_to_sign.sighash_type));
+            }
+            if let Some(leaf_hash) = input_to_sign.leaf_hash {
+                input.proprietary.insert(
+                    proprietary_key(LEAF_HASH),
+                    leaf_hash.to_byte_array().to_vec(),
+                );
+            }
+            if let Some(annex) = &input_to_sign.annex {
+                input
+                    .proprietary
+                    .insert(proprietary_key(ANNEX), annex.clone());
+            }
+        }
+
+        Ok(psbt)
+    }
+
+    /// The transaction to sign a PSBT from [`Self::to_psbt`] holds,
+    /// including the final witnesses and script sigs of the inputs signed so
+    /// far
+    pub fn from_psbt(psbt: &Psbt) -> Result<Self> {
+        let mut tx = psbt.unsigned_tx.clone();
+        let mut inputs_to_sign = vec![];
+
+        for (index, (txin, input)) in tx.input.iter_mut().zip(&psbt.inputs).enumerate() {
+            if let Some(witness) = &input.final_script_witness {
+                txin.witness = witness.clone();
+            }
+            if let Some(script_sig) = &input.final_script_sig {
+                txin.script_sig = script_sig.clone();
+            }
+
+            let Some(signer) = input.proprietary.get(&proprietary_key(NETWORK_SIGNER)) else {
+                continue;
+            };
+            let signer: [u8; 32] = signer
+                .as_slice()
+                .try_into()
+                .map_err(|_| anyhow!("Invalid network signer of input {}", index))?;
+            let mut input_to_sign = InputToSign::new(index as u32, Pubkey(signer));
+            if let Some(sighash_type) = input.sighash_type {
+                input_to_sign = input_to_sign.with_sighash_type(
+                    sighash_type
+                        .taproot_hash_ty()
+                        .map_err(|e| anyhow!("Invalid sighash type of input {}: {}", index, e))?,
+                );
+            }
+            if let Some(leaf_hash) = input.proprietary.get(&proprietary_key(LEAF_HASH)) {
+                let leaf_hash = TapLeafHash::from_slice(leaf_hash)
+                    .map_err(|_| anyhow!("Invalid tap leaf hash of input {}", index))?;
+                input_to_sign = input_to_sign.with_leaf_hash(leaf_hash);
+            }
+            if let Some(annex) = input.proprietary.get(&proprietary_key(ANNEX)) {
+                input_to_sign = input_to_sign
+                    .with_annex(annex.clone())
+                    .map_err(|_| anyhow!("Invalid annex of input {}", index))?;
+            }
+            inputs_to_sign.push(input_to_sign);
+        }
+
+        Ok(TransactionToSign {
+            tx_bytes: bitcoin::consensus::serialize(&tx),
+            inputs_to_sign,
+        })
+    }
+}
+
+/// Merges `signed`, a copy of `psbt` a wallet signed, into `psbt` and
+/// finalizes the key path signatures the wallet added to inputs the network
+/// does not sign
+pub fn merge_signed_psbt(psbt: &mut Psbt, signed: Psbt) -> Result<()> {
+    psbt.combine(signed)
+        .map_err(|e| anyhow!("Unable to merge signed PSBT: {}", e))?;
+
+    for input in psbt.inputs.iter_mut() {
+        if input.final_script_witness.is_some() || is_network_signed(input) {
+            continue;
+        }
+        if let Some(signature) = input.tap_key_sig.take() {
+            input.final_script_witness = Some(Witness::p2tr_key_spend(&signature));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the network signs `input`
+pub fn is_network_signed(input: &Input) -> bool {
+    input
+        .proprietary
+        .contains_key(&proprietary_key(NETWORK_SIGNER))
+}
+
+fn proprietary_key(subtype: u8) -> ProprietaryKey {
+    ProprietaryKey {
+        prefix: PROPRIETARY_PREFIX.to_vec(),
+        subtype,
+        key: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::secp256k1::{Keypair, Message, Secp256k1};
+    use bitcoin::transaction::Version;
+    use bitcoin::{taproot, Amount, OutPoint, ScriptBuf, Sequence, TxIn, Txid};
+    use proptest::prelude::*;
+
+    fn unsigned(inputs: usize, inputs_to_sign: Vec<InputToSign>) -> TransactionToSign {
+        let tx = Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: (0..inputs)
+                .map(|vout| TxIn {
+                    previous_output: OutPoint::new(Txid::from_byte_array([1; 32]), vout as u32),
+                    script_sig: ScriptBuf::new(),
+                    sequence: Sequence::MAX,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            output: vec![TxOut {
+                value: Amount::from_sat(1000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+        TransactionToSign {
+            tx_bytes: bitcoin::consensus::serialize(&tx),
+            inputs_to_sign,
+        }
+    }
+
+    fn prevouts(inputs: usize) -> Vec<Prevout> {
+        (0..inputs)
+            .map(|value| {
+                Prevout::from(TxOut {
+                    value: Amount::from_sat(value as u64),
+                    script_pubkey: ScriptBuf::new(),
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let transaction_to_sign = unsigned(
+            2,
+            vec![InputToSign::new(0, Pubkey([3; 32]))
+                .with_sighash_type(TapSighashType::SinglePlusAnyoneCanPay)
+                .with_leaf_hash(TapLeafHash::from_byte_array([4; 32]))
+                .with_annex(vec![0x50, 5])
+                .unwrap()],
+        );
+        let psbt = transaction_to_sign.to_psbt(&prevouts(2)).unwrap();
+        assert!(is_network_signed(&psbt.inputs[0]));
+        assert!(!is_network_signed(&psbt.inputs[1]));
+        assert_eq!(
+            psbt.inputs[1].witness_utxo,
+            Some(prevouts(2)[1].txout.clone())
+        );
+
+        let psbt = Psbt::deserialize(&psbt.serialize()).unwrap();
+        assert_eq!(
+            TransactionToSign::from_psbt(&psbt).unwrap(),
+            transaction_to_sign
+        );
+
+        assert!(transaction_to_sign.to_psbt(&prevouts(1)).is_err());
+    }
+
+    #[test]
+    fn test_merge_signed_psbt() {
+        let transaction_to_sign = unsigned(2, vec![InputToSign::new(0, Pubkey([3; 32]))]);
+        let mut psbt = transaction_to_sign.to_psbt(&prevouts(2)).unwrap();
+
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &[7; 32]).unwrap();
+        let signature = taproot::Signature {
+            signature: secp.sign_schnorr_no_aux_rand(&Message::from_digest([8; 32]), &keypair),
+            sighash_type: TapSighashType::Default,
+        };
+        let mut signed = psbt.clone();
+        signed.inputs[1].tap_key_sig = Some(signature);
+
+        merge_signed_psbt(&mut psbt, signed).unwrap();
+        let merged = TransactionToSign::from_psbt(&psbt).unwrap();
+        let tx: Transaction = bitcoin::consensus::deserialize(&merged.tx_bytes).unwrap();
+        assert!(tx.input[0].witness.is_empty());
+        assert_eq!(tx.input[1].witness, Witness::p2tr_key_spend(&signature));
+        assert_eq!(merged.inputs_to_sign, transaction_to_sign.inputs_to_sign);
+
+        // the final witness survives another export
+        assert_eq!(
+            TransactionToSign::from_psbt(&merged.to_psbt(&prevouts(2)).unwrap()).unwrap(),
+            merged
+        );
+
+        let other = unsigned(3, vec![]).to_psbt(&prevouts(3)).unwrap();
+        assert!(merge_signed_psbt(&mut psbt, other).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_psbt_roundtrip(
+            inputs in 1..8usize,
+            signed in prop::collection::vec((any::<bool>(), any::<[u8; 32]>(), 0..3u8), 8)
+        ) {
+            let sighash_types = [
+                TapSighashType::Default,
+                TapSighashType::All,
+                TapSighashType::SinglePlusAnyoneCanPay,
+            ];
+            let inputs_to_sign = signed
+                .iter()
+                .take(inputs)
+                .enumerate()
+                .filter(|(_, (signed, _, _))| *signed)
+                .map(|(index, (_, signer, sighash_type))| {
+                    InputToSign::new(index as u32, Pubkey(*signer))
+                        .with_sighash_type(sighash_types[*sighash_type as usize])
+                })
+                .collect();
+            let transaction_to_sign = unsigned(inputs, inputs_to_sign);
+            let psbt = transaction_to_sign.to_psbt(&prevouts(inputs)).unwrap();
+            let psbt = Psbt::deserialize(&psbt.serialize()).unwrap();
+            prop_assert_eq!(TransactionToSign::from_psbt(&psbt).unwrap(), transaction_to_sign);
+        }
+    }
+}
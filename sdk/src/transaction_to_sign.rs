@@ -1,5 +1,8 @@
-use arch_program::{input_to_sign::InputToSign, pubkey::Pubkey};
+use arch_program::input_to_sign::InputToSign;
+use arch_program::transaction_to_sign::{self, deserialise_parts};
 
+/// Owned copy of [`arch_program::transaction_to_sign::TransactionToSign`],
+/// serialised in the same versioned format
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct TransactionToSign {
     pub tx_bytes: Vec<u8>,
@@ -7,53 +10,53 @@ pub struct TransactionToSign {
 }
 
 impl TransactionToSign {
+    /// Reads a transaction to sign of any serialisation version
     pub fn from_slice(data: &[u8]) -> Self {
-        let tx_bytes_len =
-            u32::from_le_bytes(data[0..4].try_into().expect("slice with incorrect length"));
-        let tx_bytes = data[4..(4 + tx_bytes_len as usize)].to_vec();
-
-        let mut size = tx_bytes_len as usize + 4;
-        let inputs_to_sign_length = u32::from_le_bytes(data[size..(size + 4)].try_into().unwrap());
-        size += 4;
-        let mut inputs_to_sign = vec![];
-        for _ in 0..inputs_to_sign_length {
-            let index = u32::from_le_bytes(
-                data[size..size + 4]
-                    .try_into()
-                    .expect("slice with incorrect length"),
-            );
-            size += 4;
-            let signer = Pubkey::from_slice(&data[size..size + 32]);
-            size += 32;
-            inputs_to_sign.push(InputToSign { index, signer });
-        }
-
+        let (tx_bytes, inputs_to_sign) = deserialise_parts(data);
         TransactionToSign {
-            tx_bytes,
+            tx_bytes: tx_bytes.to_vec(),
             inputs_to_sign,
         }
     }
 
     pub fn serialise(&self) -> Vec<u8> {
-        let mut serialized = vec![];
+        self.as_program().serialise()
+    }
 
-        serialized.extend_from_slice(&(self.tx_bytes.len() as u32).to_le_bytes());
-        serialized.extend_from_slice(&self.tx_bytes);
-        serialized.extend_from_slice(&(self.inputs_to_sign.len() as u32).to_le_bytes());
-        for input_to_sign in self.inputs_to_sign.iter() {
-            serialized.extend_from_slice(&input_to_sign.index.to_le_bytes());
-            serialized.extend_from_slice(&input_to_sign.signer.serialize());
+    /// The borrowed form programs hand to the runtime
+    pub fn as_program(&self) -> transaction_to_sign::TransactionToSign<'_> {
+        transaction_to_sign::TransactionToSign {
+            tx_bytes: &self.tx_bytes,
+            inputs_to_sign: &self.inputs_to_sign,
         }
-
-        serialized
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::InputToSign;
-    use super::Pubkey;
     use super::TransactionToSign;
+    use arch_program::bitcoin::TapSighashType;
+    use arch_program::pubkey::Pubkey;
+
+    #[test]
+    fn test_versioned_roundtrip() {
+        let program_return = TransactionToSign {
+            tx_bytes: vec![10; 32],
+            inputs_to_sign: vec![
+                InputToSign::new(0, Pubkey::system_program()),
+                InputToSign::new(1, Pubkey::system_program())
+                    .with_sighash_type(TapSighashType::SinglePlusAnyoneCanPay)
+                    .with_annex(vec![0x50, 1])
+                    .unwrap(),
+            ],
+        };
+        assert_eq!(program_return.as_program().version(), 1);
+        assert_eq!(
+            program_return,
+            TransactionToSign::from_slice(&program_return.serialise())
+        );
+    }
 
     #[test]
     fn test_serialize_and_back() {
@@ -289,377 +292,377 @@ mod tests {
         let program_return = TransactionToSign {
             tx_bytes,
             inputs_to_sign: vec![
-                InputToSign {
-                    index: 0,
-                    signer: Pubkey([
+                InputToSign::new(
+                    0,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 1,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    1,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 2,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    2,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 3,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    3,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 4,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    4,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 5,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    5,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 6,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    6,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 7,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    7,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 8,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    8,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 9,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    9,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 10,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    10,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 11,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    11,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 12,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    12,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 13,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    13,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 14,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    14,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 15,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    15,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 16,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    16,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 17,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    17,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 18,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    18,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 19,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    19,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 20,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    20,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 21,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    21,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 22,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    22,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 23,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    23,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 24,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    24,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 25,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    25,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 26,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    26,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 27,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    27,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 28,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    28,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 29,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    29,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 30,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    30,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 31,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    31,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 32,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    32,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 33,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    33,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 34,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    34,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 35,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    35,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 36,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    36,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 37,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    37,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 38,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    38,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 39,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    39,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 40,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    40,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 41,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    41,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 42,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    42,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 43,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    43,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 44,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    44,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 45,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    45,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 46,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    46,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 47,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    47,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 48,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    48,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 49,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    49,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 50,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    50,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 51,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    51,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
-                InputToSign {
-                    index: 52,
-                    signer: Pubkey([
+                ),
+                InputToSign::new(
+                    52,
+                    Pubkey([
                         199, 62, 220, 99, 239, 171, 63, 140, 90, 151, 154, 255, 244, 245, 239, 168,
                         206, 235, 216, 86, 168, 13, 50, 242, 66, 107, 239, 255, 250, 213, 163, 48,
                     ]),
-                },
+                ),
             ],
         };
         assert_eq!(